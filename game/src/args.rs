@@ -8,17 +8,27 @@ pub struct ProgramContext {
     /// Directory for game assets and saved files
     #[arg(long)]
     pub install_dir: PathBuf,
+    /// File of console commands to run at startup, one per line
+    #[arg(long)]
+    pub exec: Option<PathBuf>,
 }
 
 impl ProgramContext {
     pub fn new(install_dir: PathBuf) -> Self {
-        Self { install_dir }
+        Self {
+            install_dir,
+            exec: None,
+        }
     }
 
     pub fn settings_path(&self) -> PathBuf {
         self.install_dir.join("settings.yaml")
     }
 
+    pub fn console_history_path(&self) -> PathBuf {
+        self.install_dir.join("console_history.txt")
+    }
+
     pub fn names_path(&self) -> PathBuf {
         self.install_dir.join("ship_names.txt")
     }
@@ -27,6 +37,10 @@ impl ProgramContext {
         self.install_dir.join("vehicles")
     }
 
+    pub fn assembly_dir(&self) -> PathBuf {
+        self.install_dir.join("assemblies")
+    }
+
     pub fn parts_dir(&self) -> PathBuf {
         self.install_dir.join("parts")
     }
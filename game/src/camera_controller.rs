@@ -1,5 +1,5 @@
 use crate::input::InputState;
-use bevy::input::keyboard::KeyCode;
+use crate::keybindings::InputAction;
 use starling::math::DVec2;
 use starling::prelude::*;
 
@@ -11,12 +11,18 @@ pub struct LinearCameraController {
     target_scale: f64,
     speed: f64,
     parent: EntityId,
+    /// Offset from the center, in the rotated frame of the camera
     offset: DVec2,
+    rotation: f64,
 }
 
 impl CameraProjection for LinearCameraController {
     fn origin(&self) -> DVec2 {
-        self.center + self.offset
+        self.center + self.offset()
+    }
+
+    fn rotation(&self) -> f64 {
+        self.rotation
     }
 
     fn scale(&self) -> f64 {
@@ -24,7 +30,7 @@ impl CameraProjection for LinearCameraController {
     }
 
     fn offset(&self) -> DVec2 {
-        self.offset
+        self.offset()
     }
 
     fn parent(&self) -> EntityId {
@@ -50,6 +56,7 @@ impl LinearCameraController {
             speed,
             parent: EntityId(0),
             offset: DVec2::ZERO,
+            rotation: 0.0,
         }
     }
 
@@ -73,14 +80,21 @@ impl LinearCameraController {
     pub fn follow(&mut self, parent: EntityId, p: DVec2) {
         if parent != self.parent {
             self.target_center = DVec2::ZERO;
-            self.offset = self.center + self.offset - p;
+            self.offset = rotate_f64(self.origin() - p, -self.rotation);
         }
         self.parent = parent;
         self.center = p;
     }
 
+    /// Sets the angle of the camera's frame. Any offset from the center
+    /// is held in the camera's frame, so it turns along with it.
+    pub fn set_rotation(&mut self, rotation: f64) {
+        self.rotation = rotation;
+    }
+
+    /// Offset of the camera from its center, in the world frame
     pub fn offset(&self) -> DVec2 {
-        self.offset
+        rotate_f64(self.offset, self.rotation)
     }
 
     pub fn parent(&self) -> EntityId {
@@ -100,23 +114,23 @@ impl LinearCameraController {
             self.target_scale += SCROLL_WHEEL_DELTA;
         }
 
-        if input.is_pressed(KeyCode::Equal) {
+        if input.action_pressed(InputAction::ZoomIn) {
             self.target_scale += BUTTON_ZOOM_SPEED;
         }
-        if input.is_pressed(KeyCode::Minus) {
+        if input.action_pressed(InputAction::ZoomOut) {
             self.target_scale -= BUTTON_ZOOM_SPEED;
         }
 
-        if input.is_pressed(KeyCode::KeyD) {
+        if input.action_pressed(InputAction::PanRight) {
             self.target_center.x += speed / self.scale();
         }
-        if input.is_pressed(KeyCode::KeyA) {
+        if input.action_pressed(InputAction::PanLeft) {
             self.target_center.x -= speed / self.scale();
         }
-        if input.is_pressed(KeyCode::KeyW) {
+        if input.action_pressed(InputAction::PanUp) {
             self.target_center.y += speed / self.scale();
         }
-        if input.is_pressed(KeyCode::KeyS) {
+        if input.action_pressed(InputAction::PanDown) {
             self.target_center.y -= speed / self.scale();
         }
 
@@ -127,7 +141,12 @@ impl LinearCameraController {
 pub trait CameraProjection {
     /// World to camera transform
     fn w2c(&self, p: DVec2) -> Vec2 {
-        graphics_cast((p - self.origin()) * self.scale())
+        graphics_cast(rotate_f64(p - self.origin(), -self.rotation()) * self.scale())
+    }
+
+    /// Converts an angle in the world frame to the camera frame
+    fn w2c_angle(&self, angle: f64) -> f32 {
+        (angle - self.rotation()) as f32
     }
 
     fn w2c_aabb(&self, aabb: AABB) -> AABB {
@@ -138,7 +157,7 @@ pub trait CameraProjection {
 
     /// Camera to world transform
     fn c2w(&self, p: Vec2) -> DVec2 {
-        p.as_dvec2() / self.scale() + self.origin()
+        rotate_f64(p.as_dvec2() / self.scale(), self.rotation()) + self.origin()
     }
 
     #[allow(unused)]
@@ -158,4 +177,9 @@ pub trait CameraProjection {
     fn offset(&self) -> DVec2;
 
     fn parent(&self) -> EntityId;
+
+    /// Angle of the camera's frame relative to the world frame
+    fn rotation(&self) -> f64 {
+        0.0
+    }
 }
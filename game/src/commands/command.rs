@@ -1,6 +1,6 @@
 use crate::commands::*;
 use crate::game::GameState;
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use enum_iterator::*;
 use std::fmt::Debug;
 
@@ -32,6 +32,22 @@ pub enum CommandDecl {
     Pwd,
     Listing,
     ListVehicles,
+    Spawn,
+    Teleport,
+    SetOrbit,
+    SetPolicy,
+    SetTarget,
+    Refuel,
+    EmptyTanks,
+    Validate,
+    Delete,
+    Dump,
+    Pause,
+    Rate,
+    Warp,
+    Gravity,
+    PhysicsWarp,
+    SetIntegrator,
 }
 
 impl CommandDecl {
@@ -41,16 +57,57 @@ impl CommandDecl {
             CommandDecl::Pwd => do_command::<Pwd>(state, args),
             CommandDecl::Listing => do_command::<Listing>(state, args),
             CommandDecl::ListVehicles => do_command::<ListVehicles>(state, args),
+            CommandDecl::Spawn => do_command::<Spawn>(state, args),
+            CommandDecl::Teleport => do_command::<Teleport>(state, args),
+            CommandDecl::SetOrbit => do_command::<SetOrbit>(state, args),
+            CommandDecl::SetPolicy => do_command::<SetPolicy>(state, args),
+            CommandDecl::SetTarget => do_command::<SetTarget>(state, args),
+            CommandDecl::Refuel => do_command::<Refuel>(state, args),
+            CommandDecl::EmptyTanks => do_command::<EmptyTanks>(state, args),
+            CommandDecl::Validate => do_command::<Validate>(state, args),
+            CommandDecl::Delete => do_command::<Delete>(state, args),
+            CommandDecl::Dump => do_command::<Dump>(state, args),
+            CommandDecl::Pause => do_command::<Pause>(state, args),
+            CommandDecl::Rate => do_command::<Rate>(state, args),
+            CommandDecl::Warp => do_command::<Warp>(state, args),
+            CommandDecl::Gravity => do_command::<Gravity>(state, args),
+            CommandDecl::PhysicsWarp => do_command::<PhysicsWarp>(state, args),
+            CommandDecl::SetIntegrator => do_command::<SetIntegrator>(state, args),
         }
     }
 
-    pub fn from_str(s: &str) -> Option<Self> {
-        for variant in all::<Self>() {
-            let v = format!("{:?}", variant);
-            if s.to_lowercase() == v.to_lowercase() {
-                return Some(variant);
-            }
+    /// Clap definition of the command's arguments, for completion.
+    pub fn clap_command(&self) -> clap::Command {
+        match self {
+            CommandDecl::Example => Example::command(),
+            CommandDecl::Pwd => Pwd::command(),
+            CommandDecl::Listing => Listing::command(),
+            CommandDecl::ListVehicles => ListVehicles::command(),
+            CommandDecl::Spawn => Spawn::command(),
+            CommandDecl::Teleport => Teleport::command(),
+            CommandDecl::SetOrbit => SetOrbit::command(),
+            CommandDecl::SetPolicy => SetPolicy::command(),
+            CommandDecl::SetTarget => SetTarget::command(),
+            CommandDecl::Refuel => Refuel::command(),
+            CommandDecl::EmptyTanks => EmptyTanks::command(),
+            CommandDecl::Validate => Validate::command(),
+            CommandDecl::Delete => Delete::command(),
+            CommandDecl::Dump => Dump::command(),
+            CommandDecl::Pause => Pause::command(),
+            CommandDecl::Rate => Rate::command(),
+            CommandDecl::Warp => Warp::command(),
+            CommandDecl::Gravity => Gravity::command(),
+            CommandDecl::PhysicsWarp => PhysicsWarp::command(),
+            CommandDecl::SetIntegrator => SetIntegrator::command(),
         }
-        None
+    }
+
+    /// What the command is typed as in the console.
+    pub fn name(&self) -> String {
+        format!("{:?}", self).to_lowercase()
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        all::<Self>().find(|variant| variant.name() == s.to_lowercase())
     }
 }
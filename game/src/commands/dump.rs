@@ -0,0 +1,84 @@
+use crate::commands::*;
+use crate::game::GameState;
+use clap::Parser;
+use serde::Serialize;
+use starling::prelude::*;
+
+#[derive(Serialize)]
+struct VehicleDump {
+    id: EntityId,
+    name: String,
+    model: String,
+    parent: EntityId,
+    /// Relative to the parent
+    pv: PV,
+    angle: f64,
+    angular_velocity: f64,
+    landed: bool,
+    policy: String,
+    status: String,
+    orbit: Option<String>,
+    target: Option<EntityId>,
+    mission: Option<String>,
+    has_comms: bool,
+    mass_kg: f64,
+    fuel_percent: f64,
+}
+
+#[derive(Serialize)]
+struct PlanetDump {
+    id: EntityId,
+    name: String,
+    body: Body,
+    /// In the world frame
+    pv: PV,
+}
+
+/// Print the state of a vehicle or planet as YAML
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+pub struct Dump {
+    #[arg(value_parser = parse_entity_id)]
+    pub id: EntityId,
+}
+
+impl Command for Dump {
+    fn execute(&self, state: &mut GameState) -> Result<(), String> {
+        let universe = &state.universe;
+        let yaml = if let Some(sv) = universe.surface_vehicles.get(&self.id) {
+            let vehicle = sv.vehicle();
+            serde_yaml::to_string(&VehicleDump {
+                id: self.id,
+                name: vehicle.name().to_string(),
+                model: vehicle.model().to_string(),
+                parent: sv.parent(),
+                pv: sv.pv(),
+                angle: sv.body.angle,
+                angular_velocity: sv.body.angular_velocity,
+                landed: sv.is_landed(),
+                policy: format!("{:?}", sv.controller.mode()),
+                status: format!("{:?}", sv.controller.status()),
+                orbit: sv.orbit.map(|o| o.to_string()),
+                target: sv.target(),
+                mission: sv.mission().map(|m| m.to_string()),
+                has_comms: universe.has_comms(self.id),
+                mass_kg: vehicle.total_mass().to_kg_f64(),
+                fuel_percent: vehicle.fuel_percentage() * 100.0,
+            })
+        } else {
+            let lup = universe
+                .lup_planet(self.id)
+                .ok_or(format!("No entity {}", self.id))?;
+            let (name, body) = lup.named_body().ok_or("Planet has no body")?;
+            serde_yaml::to_string(&PlanetDump {
+                id: self.id,
+                name: name.clone(),
+                body,
+                pv: lup.pv(),
+            })
+        };
+
+        state.console.print(yaml.map_err(|e| e.to_string())?);
+        Ok(())
+    }
+}
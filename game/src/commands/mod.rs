@@ -9,3 +9,12 @@ pub use utilities::*;
 
 pub mod list_vehicles;
 pub use list_vehicles::*;
+
+pub mod vehicles;
+pub use vehicles::*;
+
+pub mod simulation;
+pub use simulation::*;
+
+pub mod dump;
+pub use dump::*;
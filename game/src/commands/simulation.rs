@@ -0,0 +1,147 @@
+use crate::commands::*;
+use crate::game::GameState;
+use crate::sim_rate::SimRate;
+use clap::Parser;
+use starling::prelude::*;
+
+/// Pause or unpause the simulation
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+pub struct Pause;
+
+impl Command for Pause {
+    fn execute(&self, state: &mut GameState) -> Result<(), String> {
+        state.paused = !state.paused;
+        let s = if state.paused { "Paused" } else { "Unpaused" };
+        state.console.print(s);
+        Ok(())
+    }
+}
+
+/// Set the simulation rate, as shown on the rate buttons
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+pub struct Rate {
+    /// One of 1s, 3s, 10s, 1m, 5m, 30m, hr, dy, wk or mn
+    pub rate: String,
+}
+
+impl Command for Rate {
+    fn execute(&self, state: &mut GameState) -> Result<(), String> {
+        let rate = SimRate::all()
+            .find(|r| r.as_str().to_lowercase() == self.rate.to_lowercase())
+            .ok_or(format!("No sim rate \"{}\"", self.rate))?;
+        state.universe_ticks_per_game_tick = rate;
+        state.warp_to = None;
+        Ok(())
+    }
+}
+
+/// Run the simulation flat out until it reaches a given time
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+pub struct Warp {
+    /// Sim time to warp to, e.g. 36h, or +2h to warp ahead by that long
+    pub time: String,
+}
+
+impl Command for Warp {
+    fn execute(&self, state: &mut GameState) -> Result<(), String> {
+        let now = state.universe.stamp();
+        let time = match self.time.strip_prefix('+') {
+            Some(dt) => now + parse_duration(dt)?,
+            None => parse_duration(&self.time)?,
+        };
+        if time <= now {
+            return Err(format!("{} is in the past", time));
+        }
+        state.warp_to = Some(time);
+        state.paused = false;
+        Ok(())
+    }
+}
+
+/// Choose how gravity is modeled, or show the current model
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+pub struct Gravity {
+    /// One of patched, nbody-vehicles or nbody
+    pub model: Option<String>,
+}
+
+impl Command for Gravity {
+    fn execute(&self, state: &mut GameState) -> Result<(), String> {
+        if let Some(model) = &self.model {
+            state.universe.physics.gravity = match model.to_lowercase().as_str() {
+                "patched" => GravityModel::PatchedConics,
+                "nbody-vehicles" => GravityModel::NBodyVehicles,
+                "nbody" => GravityModel::NBody,
+                _ => return Err(format!("No gravity model \"{}\"", model)),
+            };
+        }
+        let current = state.universe.physics.gravity;
+        state.console.print(format!("Gravity model: {:?}", current));
+        Ok(())
+    }
+}
+
+/// Let off-rails vehicles take several ticks per physics step when warping,
+/// or show whether they do
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+pub struct PhysicsWarp {
+    /// Either on or off
+    pub enabled: Option<String>,
+}
+
+impl Command for PhysicsWarp {
+    fn execute(&self, state: &mut GameState) -> Result<(), String> {
+        if let Some(enabled) = &self.enabled {
+            state.universe.physics.warp = match enabled.to_lowercase().as_str() {
+                "on" => true,
+                "off" => false,
+                _ => return Err(format!("Expected on or off, not \"{}\"", enabled)),
+            };
+        }
+        let current = if state.universe.physics.warp {
+            "on"
+        } else {
+            "off"
+        };
+        state.console.print(format!("Physics warp: {}", current));
+        Ok(())
+    }
+}
+
+/// Choose how off-rails vehicles are integrated, or show the current scheme
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+pub struct SetIntegrator {
+    /// One of euler, rk4 or yoshida
+    pub scheme: Option<String>,
+    /// Integration steps per physics tick
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub substeps: Option<u32>,
+}
+
+impl Command for SetIntegrator {
+    fn execute(&self, state: &mut GameState) -> Result<(), String> {
+        if let Some(scheme) = &self.scheme {
+            state.universe.physics.integrator = match scheme.to_lowercase().as_str() {
+                "euler" => Integrator::SemiImplicitEuler,
+                "rk4" => Integrator::RungeKutta4,
+                "yoshida" => Integrator::Yoshida4,
+                _ => return Err(format!("No integrator \"{}\"", scheme)),
+            };
+        }
+        if let Some(substeps) = self.substeps {
+            state.universe.physics.substeps = substeps;
+        }
+        let physics = &state.universe.physics;
+        state.console.print(format!(
+            "Integrator: {:?}, {} substeps",
+            physics.integrator, physics.substeps
+        ));
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use crate::commands::*;
 use crate::game::GameState;
 use clap::Parser;
+use starling::prelude::*;
 
 #[derive(Parser, Debug, Default, Clone)]
 #[command(about)]
@@ -30,3 +31,32 @@ impl Command for Listing {
         Ok(())
     }
 }
+
+pub fn parse_entity_id(s: &str) -> Result<EntityId, String> {
+    s.parse()
+        .map(EntityId)
+        .map_err(|_| format!("Bad entity id \"{}\"", s))
+}
+
+pub fn parse_item(s: &str) -> Result<Item, String> {
+    enum_iterator::all::<Item>()
+        .find(|item| format!("{:?}", item).to_lowercase() == s.to_lowercase())
+        .ok_or(format!("No item named \"{}\"", s))
+}
+
+/// Finds a planet by name, ignoring case.
+pub fn find_planet(state: &GameState, name: &str) -> Result<EntityId, String> {
+    state
+        .universe
+        .planets
+        .planet_ids()
+        .into_iter()
+        .find(|id| {
+            state
+                .universe
+                .lup_planet(*id)
+                .and_then(|lup| lup.named_body())
+                .is_some_and(|(n, _)| n.to_lowercase() == name.to_lowercase())
+        })
+        .ok_or(format!("No planet named \"{}\"", name))
+}
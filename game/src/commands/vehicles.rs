@@ -0,0 +1,348 @@
+use crate::commands::*;
+use crate::game::GameState;
+use clap::{Args, Parser, ValueEnum};
+use starling::prelude::*;
+
+/// Orbit given by the altitudes of its apsides
+#[derive(Args, Debug, Clone)]
+pub struct OrbitArgs {
+    /// Name of the body to orbit
+    #[arg(long, default_value = "Earth")]
+    pub body: String,
+    /// Periapsis altitude, e.g. 300km
+    #[arg(long, value_parser = parse_distance)]
+    pub periapsis: f64,
+    /// Apoapsis altitude; the orbit is circular if left out
+    #[arg(long, value_parser = parse_distance)]
+    pub apoapsis: Option<f64>,
+    /// Argument of periapsis, e.g. 90deg
+    #[arg(long, value_parser = parse_angle, default_value = "0")]
+    pub argp: f64,
+    /// Go round clockwise
+    #[arg(long)]
+    pub retrograde: bool,
+}
+
+impl OrbitArgs {
+    /// The orbit, with the vehicle passing through periapsis right now.
+    pub fn orbit(&self, state: &GameState) -> Result<GlobalOrbit, String> {
+        let parent = find_planet(state, &self.body)?;
+        let body = state
+            .universe
+            .lup_planet(parent)
+            .and_then(|lup| lup.body())
+            .ok_or("Planet has no body")?;
+        let rp = body.radius + self.periapsis;
+        let ra = body.radius + self.apoapsis.unwrap_or(self.periapsis);
+        if ra < rp {
+            return Err("Apoapsis is below periapsis".into());
+        }
+        let stamp = state.universe.stamp();
+        let orbit = SparseOrbit::new(ra, rp, self.argp, body, stamp, self.retrograde)
+            .ok_or("Invalid orbit")?;
+        Ok(GlobalOrbit(parent, orbit))
+    }
+}
+
+/// Spawn a vehicle of the given model on an orbit
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+pub struct Spawn {
+    /// Model name, as found in the vehicles directory
+    pub model: String,
+    #[command(flatten)]
+    pub orbit: OrbitArgs,
+}
+
+impl Command for Spawn {
+    fn execute(&self, state: &mut GameState) -> Result<(), String> {
+        let orbit = self.orbit.orbit(state)?;
+        let vehicle = state
+            .get_vehicle_by_model(&self.model)
+            .ok_or(format!("No vehicle model \"{}\"", self.model))?;
+        let id = state
+            .universe
+            .add_orbital_vehicle(vehicle, orbit)
+            .ok_or("Failed to add vehicle")?;
+        state.console.print(format!("Spawned {}", id));
+        Ok(())
+    }
+}
+
+/// Move a vehicle alongside another one, matching its velocity
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+pub struct Teleport {
+    #[arg(value_parser = parse_entity_id)]
+    pub id: EntityId,
+    /// Vehicle to end up next to
+    #[arg(value_parser = parse_entity_id)]
+    pub to: EntityId,
+    /// How far above it to end up
+    #[arg(long, value_parser = parse_distance, default_value = "100m")]
+    pub distance: f64,
+}
+
+impl Command for Teleport {
+    fn execute(&self, state: &mut GameState) -> Result<(), String> {
+        if self.id == self.to {
+            return Err("Can't teleport a vehicle to itself".into());
+        }
+        let other = state
+            .universe
+            .surface_vehicles
+            .get(&self.to)
+            .ok_or(format!("No vehicle {}", self.to))?;
+        let parent = other.parent();
+        let pv = other.pv();
+        let offset = pv.pos.normalize_or_zero() * self.distance;
+        Ok(state
+            .universe
+            .teleport(self.id, parent, pv + PV::pos(offset))?)
+    }
+}
+
+/// Put a vehicle onto an orbit, wherever it is now
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+pub struct SetOrbit {
+    #[arg(value_parser = parse_entity_id)]
+    pub id: EntityId,
+    #[command(flatten)]
+    pub orbit: OrbitArgs,
+}
+
+impl Command for SetOrbit {
+    fn execute(&self, state: &mut GameState) -> Result<(), String> {
+        let GlobalOrbit(parent, orbit) = self.orbit.orbit(state)?;
+        let pv = orbit
+            .pv(state.universe.stamp())
+            .map_err(|_| "Invalid orbit")?;
+        Ok(state.universe.teleport(self.id, parent, pv)?)
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum PolicyArg {
+    Idle,
+    Prograde,
+    Retrograde,
+    Launch,
+    Hold,
+    Land,
+}
+
+/// Set what a vehicle's controller is doing
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+pub struct SetPolicy {
+    #[arg(value_parser = parse_entity_id)]
+    pub id: EntityId,
+    pub policy: PolicyArg,
+    /// Altitude to launch to
+    #[arg(long, value_parser = parse_distance, default_value = "300km")]
+    pub altitude: f64,
+    /// Attitude to hold; keeps the current one if left out
+    #[arg(long, value_parser = parse_angle)]
+    pub angle: Option<f64>,
+    /// Set the policy even if the vehicle has no comms
+    #[arg(long)]
+    pub force: bool,
+}
+
+impl Command for SetPolicy {
+    fn execute(&self, state: &mut GameState) -> Result<(), String> {
+        let policy = match self.policy {
+            PolicyArg::Idle => VehicleControlPolicy::Idle,
+            PolicyArg::Prograde => VehicleControlPolicy::BurnPrograde,
+            PolicyArg::Retrograde => VehicleControlPolicy::BurnRetrograde,
+            PolicyArg::Launch => VehicleControlPolicy::LaunchToOrbit(self.altitude),
+            PolicyArg::Hold => VehicleControlPolicy::HoldAttitude(self.angle),
+            PolicyArg::Land => VehicleControlPolicy::Land,
+        };
+        let sv = state
+            .universe
+            .surface_vehicles
+            .get_mut(&self.id)
+            .ok_or(format!("No vehicle {}", self.id))?;
+        if self.force {
+            sv.controller.set_policy(policy);
+            return Ok(());
+        }
+        state
+            .universe
+            .set_vehicle_policy(self.id, policy)
+            .map_err(|e| format!("Can't command {}: {}", self.id, e))
+    }
+}
+
+/// Set or clear a vehicle's target
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+pub struct SetTarget {
+    #[arg(value_parser = parse_entity_id)]
+    pub id: EntityId,
+    /// Vehicle to target; clears the target if left out
+    #[arg(value_parser = parse_entity_id)]
+    pub target: Option<EntityId>,
+}
+
+impl Command for SetTarget {
+    fn execute(&self, state: &mut GameState) -> Result<(), String> {
+        if let Some(target) = self.target {
+            if target == self.id || !state.universe.surface_vehicles.contains_key(&target) {
+                return Err(format!("Can't target {}", target));
+            }
+        }
+        let sv = state
+            .universe
+            .surface_vehicles
+            .get_mut(&self.id)
+            .ok_or(format!("No vehicle {}", self.id))?;
+        sv.set_target(self.target);
+        Ok(())
+    }
+}
+
+/// Fill up a vehicle's tanks
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+pub struct Refuel {
+    #[arg(value_parser = parse_entity_id)]
+    pub id: EntityId,
+    /// What to fill empty tanks with
+    #[arg(long, value_parser = parse_item, default_value = "methane")]
+    pub item: Item,
+}
+
+impl Command for Refuel {
+    fn execute(&self, state: &mut GameState) -> Result<(), String> {
+        let sv = state
+            .universe
+            .surface_vehicles
+            .get_mut(&self.id)
+            .ok_or(format!("No vehicle {}", self.id))?;
+        sv.vehicle.fill_tanks(self.item);
+        Ok(())
+    }
+}
+
+/// Empty out all of a vehicle's tanks
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+pub struct EmptyTanks {
+    #[arg(value_parser = parse_entity_id)]
+    pub id: EntityId,
+}
+
+impl Command for EmptyTanks {
+    fn execute(&self, state: &mut GameState) -> Result<(), String> {
+        let sv = state
+            .universe
+            .surface_vehicles
+            .get_mut(&self.id)
+            .ok_or(format!("No vehicle {}", self.id))?;
+        sv.vehicle.empty_tanks();
+        Ok(())
+    }
+}
+
+/// Check a vehicle's design for mistakes; checks the craft open in the
+/// editor if no vehicle is given
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+pub struct Validate {
+    /// Model name, as found in the vehicles directory
+    pub model: Option<String>,
+    /// Vehicle already out in the universe
+    #[arg(long, value_parser = parse_entity_id, conflicts_with = "model")]
+    pub id: Option<EntityId>,
+}
+
+impl Command for Validate {
+    fn execute(&self, state: &mut GameState) -> Result<(), String> {
+        let issues = if let Some(id) = self.id {
+            let sv = state
+                .universe
+                .surface_vehicles
+                .get(&id)
+                .ok_or(format!("No vehicle {}", id))?;
+            sv.vehicle.validate()
+        } else if let Some(model) = &self.model {
+            state
+                .get_vehicle_by_model(model)
+                .ok_or(format!("No vehicle model \"{}\"", model))?
+                .validate()
+        } else {
+            state.editor_context.vehicle.validate()
+        };
+
+        if issues.is_empty() {
+            state.console.print("No problems found");
+        }
+        for issue in issues {
+            state
+                .console
+                .print(format!("{:?}: {}", issue.severity(), issue));
+        }
+        Ok(())
+    }
+}
+
+/// Delete every vehicle matching all of the given filters
+#[derive(Parser, Debug, Clone)]
+#[command(about)]
+pub struct Delete {
+    /// Particular vehicles to delete
+    #[arg(value_parser = parse_entity_id)]
+    pub ids: Vec<EntityId>,
+    /// Vehicles of this model
+    #[arg(long)]
+    pub model: Option<String>,
+    /// Vehicles orbiting or landed on this body
+    #[arg(long)]
+    pub body: Option<String>,
+    /// Only landed vehicles
+    #[arg(long)]
+    pub landed: bool,
+    /// Delete everything if no other filters are given
+    #[arg(long)]
+    pub all: bool,
+}
+
+impl Command for Delete {
+    fn execute(&self, state: &mut GameState) -> Result<(), String> {
+        let has_filters =
+            !self.ids.is_empty() || self.model.is_some() || self.body.is_some() || self.landed;
+        if !has_filters && !self.all {
+            return Err("Give some filters, or --all to delete every vehicle".into());
+        }
+
+        let parent = match &self.body {
+            Some(name) => Some(find_planet(state, name)?),
+            None => None,
+        };
+
+        let mut ids: Vec<EntityId> = state
+            .universe
+            .surface_vehicles
+            .iter()
+            .filter(|(id, sv)| {
+                (self.ids.is_empty() || self.ids.contains(id))
+                    && self.model.as_ref().is_none_or(|m| sv.vehicle.model() == m)
+                    && parent.is_none_or(|p| sv.parent() == p)
+                    && (!self.landed || sv.is_landed())
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort();
+
+        for id in &ids {
+            state.delete_orbiter(*id);
+        }
+        state
+            .console
+            .print(format!("Deleted {} vehicles", ids.len()));
+        Ok(())
+    }
+}
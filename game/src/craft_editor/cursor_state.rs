@@ -5,6 +5,8 @@ pub enum CursorState {
     #[default]
     None,
     Part(PartPrototype),
+    /// Copied parts or a saved assembly waiting to be placed
+    Group(Assembly),
 }
 
 impl CursorState {
@@ -14,4 +16,8 @@ impl CursorState {
             _ => None,
         }
     }
+
+    pub fn is_none(&self) -> bool {
+        matches!(self, Self::None)
+    }
 }
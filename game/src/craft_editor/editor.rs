@@ -6,6 +6,7 @@ use crate::drawing::*;
 use crate::game::GameState;
 use crate::input::InputState;
 use crate::input::{FrameId, MouseButt};
+use crate::keybindings::InputAction;
 use crate::names::*;
 use crate::onclick::OnClick;
 use crate::scenes::Render;
@@ -14,6 +15,7 @@ use crate::z_index::ZOrdering;
 use bevy::color::palettes::css::*;
 use bevy::input::keyboard::KeyCode;
 use bevy::prelude::*;
+use enum_iterator::Sequence;
 use layout::layout::{Node, Size, Tree};
 use rfd::FileDialog;
 use starling::prelude::*;
@@ -22,15 +24,119 @@ use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone)]
 pub enum Action {
-    Add(IVec2, Rotation, PartPrototype),
-    Remove(IVec2, Rotation, PartPrototype),
+    /// Part as placed, or as it was before removal, along with its state
+    Add(InstantiatedPart),
+    Remove(InstantiatedPart),
+    AddPipe(IVec2),
+    RemovePipe(IVec2),
+    /// Recipe of the machine with the given origin and layer, before and after
+    SetRecipe(IVec2, PartLayer, RecipeListing, RecipeListing),
+    /// Change to the whole craft, with the craft as it was before and after
+    Replace(String, Box<Snapshot>, Box<Snapshot>),
+    /// Several changes made at once, which are undone together
+    Group(Vec<Action>),
 }
 
 impl Action {
     pub fn to_string(&self) -> String {
         match self {
-            Self::Add(_, _, proto) => format!("Add {}", proto.part_name()),
-            Self::Remove(_, _, proto) => format!("Remove {}", proto.part_name()),
+            Self::Add(part) => format!("Add {}", part.prototype().part_name()),
+            Self::Remove(part) => format!("Remove {}", part.prototype().part_name()),
+            Self::AddPipe(_) => "Add pipe".to_string(),
+            Self::RemovePipe(_) => "Remove pipe".to_string(),
+            Self::SetRecipe(_, _, _, recipe) => format!("Set recipe to {:?}", recipe),
+            Self::Replace(name, _, _) => name.clone(),
+            Self::Group(actions) => match actions.as_slice() {
+                [Self::AddPipe(_), ..] => format!("Add {} pipes", actions.len()),
+                [Self::RemovePipe(_), ..] => format!("Remove {} pipes", actions.len()),
+                _ => format!("{} changes", actions.len()),
+            },
+        }
+    }
+
+    /// Action which undoes this one.
+    pub fn inverse(&self) -> Action {
+        match self {
+            Self::Add(part) => Self::Remove(part.clone()),
+            Self::Remove(part) => Self::Add(part.clone()),
+            Self::AddPipe(p) => Self::RemovePipe(*p),
+            Self::RemovePipe(p) => Self::AddPipe(*p),
+            Self::SetRecipe(p, layer, before, after) => {
+                Self::SetRecipe(*p, *layer, *after, *before)
+            }
+            Self::Replace(name, before, after) => {
+                Self::Replace(name.clone(), after.clone(), before.clone())
+            }
+            Self::Group(actions) => Self::Group(actions.iter().rev().map(Self::inverse).collect()),
+        }
+    }
+}
+
+/// Craft being edited, as it was at some point.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    vehicle: Vehicle,
+    filepath: Option<PathBuf>,
+}
+
+/// Number of actions kept around to be undone.
+const MAX_HISTORY: usize = 200;
+
+/// Line through the craft's origin that new parts are mirrored across.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Sequence)]
+pub enum Symmetry {
+    None,
+    /// Top to bottom, across the X axis
+    AcrossX,
+    /// Left to right, across the Y axis
+    AcrossY,
+}
+
+impl Symmetry {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::None => "Mirror: Off",
+            Self::AcrossX => "Mirror: X Axis",
+            Self::AcrossY => "Mirror: Y Axis",
+        }
+    }
+
+    /// Position and rotation of the mirror image of a part, if there's
+    /// any mirroring going on.
+    pub fn mirror(
+        &self,
+        pos: IVec2,
+        rot: Rotation,
+        proto: &PartPrototype,
+    ) -> Option<(IVec2, Rotation)> {
+        let wh = pixel_dims_with_rotation(rot, proto).as_ivec2();
+        match self {
+            Self::None => None,
+            Self::AcrossX => {
+                let rot = match rot {
+                    Rotation::North => Rotation::South,
+                    Rotation::South => Rotation::North,
+                    r => r,
+                };
+                Some((IVec2::new(pos.x, -pos.y - wh.y), rot))
+            }
+            Self::AcrossY => {
+                let rot = match rot {
+                    Rotation::East => Rotation::West,
+                    Rotation::West => Rotation::East,
+                    r => r,
+                };
+                Some((IVec2::new(-pos.x - wh.x, pos.y), rot))
+            }
+        }
+    }
+
+    /// Mirror image of the pipe in the given pixel.
+    pub fn mirror_pipe(&self, p: IVec2) -> Option<IVec2> {
+        match self {
+            Self::None => None,
+            Self::AcrossX => Some(IVec2::new(p.x, -p.y - 1)),
+            Self::AcrossY => Some(IVec2::new(-p.x - 1, p.y)),
         }
     }
 }
@@ -42,9 +148,26 @@ pub struct EditorContext {
     rotation: Rotation,
     filepath: Option<PathBuf>,
     focus_layer: Option<PartLayer>,
-    selected_part: Option<PartId>,
+    selected_parts: HashSet<PartId>,
+    symmetry: Symmetry,
+    clipboard: Assembly,
     snap_info: Option<(IVec2, UVec2)>,
     action_queue: Vec<Action>,
+    /// Undone actions, with the next one to be redone last
+    redo_queue: Vec<Action>,
+    /// Pipes added or removed while the mouse is held, recorded on release
+    pipe_stroke: Vec<Action>,
+    /// Problems with the design, as of the last change
+    issues: Vec<DesignIssue>,
+    /// Bodies the craft's thrust to weight ratios are worked out for
+    planets: PlanetarySystem,
+    /// Performance of each stage, as of the last change
+    stages: Vec<StageAnalysis>,
+    /// Delta-v of the first stage with every tank full of methane, as it
+    /// would be on the launch pad
+    full_tank_delta_v: Option<f64>,
+    /// Whether the craft has changed since it was last saved or loaded
+    is_dirty: bool,
     occupied: HashMap<PartLayer, HashMap<IVec2, PartId>>,
     pub vehicle: Vehicle,
     particles: ThrustParticleEffects,
@@ -63,16 +186,25 @@ pub struct EditorContext {
 }
 
 impl EditorContext {
-    pub fn new() -> Self {
+    pub fn new(planets: PlanetarySystem) -> Self {
         EditorContext {
             camera: LinearCameraController::new(DVec2::ZERO, 18.0, 1100.0),
             cursor_state: CursorState::None,
             rotation: Rotation::East,
             filepath: None,
             focus_layer: None,
-            selected_part: None,
+            selected_parts: HashSet::new(),
+            symmetry: Symmetry::None,
+            clipboard: Assembly::default(),
             snap_info: None,
             action_queue: Vec::new(),
+            redo_queue: Vec::new(),
+            pipe_stroke: Vec::new(),
+            issues: Vec::new(),
+            planets,
+            stages: Vec::new(),
+            full_tank_delta_v: None,
+            is_dirty: false,
             occupied: HashMap::new(),
             vehicle: Vehicle::new(),
             particles: ThrustParticleEffects::new(),
@@ -93,23 +225,232 @@ impl EditorContext {
     }
 
     pub fn remove_part(&mut self, id: PartId) {
-        self.vehicle.remove_part(id);
+        self.remove_parts(vec![id]);
     }
 
     pub fn undo(&mut self) -> Option<()> {
         let action = self.action_queue.pop()?;
+        self.apply(action.inverse());
+        self.redo_queue.push(action);
+        self.is_dirty = true;
+        Some(())
+    }
+
+    pub fn redo(&mut self) -> Option<()> {
+        let action = self.redo_queue.pop()?;
+        self.apply(action.clone());
+        self.action_queue.push(action);
+        self.is_dirty = true;
+        Some(())
+    }
+
+    /// Undoes or redoes actions until the given number of them are in effect.
+    pub fn revert_to(&mut self, n: usize) {
+        while self.action_queue.len() > n && self.undo().is_some() {}
+        while self.action_queue.len() < n && self.redo().is_some() {}
+    }
+
+    pub fn history(&self) -> (&[Action], &[Action]) {
+        (&self.action_queue, &self.redo_queue)
+    }
+
+    /// Part whose origin is exactly the given position.
+    fn part_with_origin(&self, p: IVec2, layer: PartLayer) -> Option<PartId> {
+        self.vehicle
+            .parts()
+            .find(|(_, part)| part.origin() == p && part.prototype().layer() == layer)
+            .map(|(id, _)| *id)
+    }
+
+    fn apply(&mut self, action: Action) {
         match action {
-            Action::Add(pos, _, proto) => match self.vehicle.remove_part_at(pos, proto.layer()) {
-                Ok(p) => println!("Removed {:?}", p),
-                Err(s) => println!("Failed to remove: {}", s),
-            },
-            Action::Remove(pos, rot, proto) => self.add_part(pos, rot, proto),
+            Action::Add(part) => {
+                self.vehicle.add_instance(part);
+            }
+            Action::Remove(part) => {
+                if let Some(id) = self.part_with_origin(part.origin(), part.prototype().layer()) {
+                    self.vehicle.remove_part(id);
+                    self.selected_parts.remove(&id);
+                }
+            }
+            Action::AddPipe(p) => self.vehicle.add_pipe(p),
+            Action::RemovePipe(p) => self.vehicle.remove_pipe(p),
+            Action::SetRecipe(pos, layer, _, recipe) => {
+                if let Some(id) = self.part_with_origin(pos, layer) {
+                    self.vehicle.set_recipe(id, recipe);
+                }
+            }
+            Action::Replace(_, _, after) => {
+                self.vehicle = after.vehicle;
+                self.filepath = after.filepath;
+                self.selected_parts.clear();
+            }
+            Action::Group(actions) => {
+                for action in actions {
+                    self.apply(action);
+                }
+            }
+        }
+        self.update();
+    }
+
+    /// Adds changes that were made together to the undo queue.
+    fn record(&mut self, mut actions: Vec<Action>) {
+        match actions.len() {
+            0 => return,
+            1 => self.action_queue.extend(actions.pop()),
+            _ => self.action_queue.push(Action::Group(actions)),
+        }
+        self.redo_queue.clear();
+        self.is_dirty = true;
+        if self.action_queue.len() > MAX_HISTORY {
+            let excess = self.action_queue.len() - MAX_HISTORY;
+            self.action_queue.drain(..excess);
+        }
+    }
+
+    fn snapshot(&self) -> Box<Snapshot> {
+        Box::new(Snapshot {
+            vehicle: self.vehicle.clone(),
+            filepath: self.filepath.clone(),
+        })
+    }
+
+    /// Makes a change to the whole craft, keeping a copy of it from
+    /// beforehand so the change can be undone.
+    fn record_replace(&mut self, name: impl Into<String>, f: impl FnOnce(&mut Self)) {
+        let before = self.snapshot();
+        f(self);
+        let after = self.snapshot();
+        self.record(vec![Action::Replace(name.into(), before, after)]);
+        self.update();
+    }
+
+    pub fn set_recipe(&mut self, id: PartId, recipe: RecipeListing) -> bool {
+        let Some(part) = self.vehicle.get_part(id) else {
+            return false;
+        };
+        let (pos, layer) = (part.origin(), part.prototype().layer());
+        let Some(before) = part.as_machine().map(|(_, d)| d.recipe) else {
+            return false;
+        };
+        if !self.vehicle.set_recipe(id, recipe) {
+            return false;
+        }
+        self.record(vec![Action::SetRecipe(pos, layer, before, recipe)]);
+        true
+    }
+
+    pub fn clear_contents(&mut self, id: PartId) -> bool {
+        let before = self.snapshot();
+        if !self.vehicle.clear_contents(id) {
+            return false;
+        }
+        let after = self.snapshot();
+        self.record(vec![Action::Replace(
+            "Clear contents".into(),
+            before,
+            after,
+        )]);
+        self.update();
+        true
+    }
+
+    pub fn toggle_thruster(&mut self, id: PartId) -> Option<bool> {
+        let enabled = !self.vehicle.get_part(id)?.as_thruster()?.1.is_enabled();
+        let before = self.snapshot();
+        self.vehicle.set_thruster_enabled(id, enabled);
+        let after = self.snapshot();
+        let name = if enabled {
+            "Enable thruster"
+        } else {
+            "Disable thruster"
+        };
+        self.record(vec![Action::Replace(name.into(), before, after)]);
+        self.update();
+        Some(enabled)
+    }
+
+    fn add_pipe(&mut self, p: IVec2) {
+        if !self.vehicle.has_pipe(p) {
+            self.vehicle.add_pipe(p);
+            self.pipe_stroke.push(Action::AddPipe(p));
+            self.update();
+        }
+    }
+
+    fn remove_pipe(&mut self, p: IVec2) {
+        if self.vehicle.has_pipe(p) {
+            self.vehicle.remove_pipe(p);
+            self.pipe_stroke.push(Action::RemovePipe(p));
+            self.update();
+        }
+    }
+
+    /// The selected part, if exactly one is selected.
+    pub fn single_selection(&self) -> Option<PartId> {
+        match self.selected_parts.iter().collect::<Vec<_>>().as_slice() {
+            [id] => Some(**id),
+            _ => None,
         }
-        Some(())
     }
 
     pub fn selected_part(&self) -> Option<&InstantiatedPart> {
-        self.vehicle.get_part(self.selected_part?)
+        self.vehicle.get_part(self.single_selection()?)
+    }
+
+    pub fn cycle_symmetry(&mut self) {
+        self.symmetry = enum_iterator::next_cycle(&self.symmetry);
+    }
+
+    /// Selects every part in a visible layer which overlaps the given box,
+    /// given in meters.
+    fn select_in_box(&mut self, aabb: AABB, add: bool) {
+        if !add {
+            self.selected_parts.clear();
+        }
+        for (id, instance) in self.vehicle.parts() {
+            if !self.is_layer_visible(instance.prototype().layer()) {
+                continue;
+            }
+            let p = instance.origin_meters();
+            let bounds = AABB::from_arbitrary(p, p + instance.dims_meters());
+            if bounds.intersects(&aabb) {
+                self.selected_parts.insert(*id);
+            }
+        }
+    }
+
+    /// Copies the selected parts and the pipes under them, returning
+    /// whether there were any.
+    pub fn copy_selection(&mut self) -> bool {
+        let ids = self.selected_parts.iter().cloned();
+        let assembly = Assembly::from_vehicle(String::new(), &self.vehicle, ids);
+        if assembly.is_empty() {
+            return false;
+        }
+        self.clipboard = assembly;
+        true
+    }
+
+    /// Picks up whatever was last copied, ready to be placed.
+    pub fn paste(&mut self) {
+        if !self.clipboard.is_empty() {
+            self.cursor_state = CursorState::Group(self.clipboard.clone());
+        }
+    }
+
+    /// Removes the selected parts and picks them up to be placed again.
+    pub fn cut_selection(&mut self) {
+        if self.copy_selection() {
+            self.delete_selection();
+            self.paste();
+        }
+    }
+
+    pub fn delete_selection(&mut self) {
+        let ids: Vec<_> = self.selected_parts.drain().collect();
+        self.remove_parts(ids);
     }
 
     pub fn cursor_box(&self, input: &InputState) -> Option<AABB> {
@@ -121,11 +462,37 @@ impl EditorContext {
         ))
     }
 
+    /// Box being dragged out to select parts, in meters.
+    fn selection_box(&self, input: &InputState, end: FrameId) -> Option<AABB> {
+        let p1 = input.position(MouseButt::Left, FrameId::Down)?;
+        let p2 = input.position(MouseButt::Left, end)?;
+        if p1.distance(p2) < 5.0 {
+            return None;
+        }
+        Some(AABB::from_arbitrary(
+            graphics_cast(self.c2w(p1)),
+            graphics_cast(self.c2w(p2)),
+        ))
+    }
+
+    /// Group of parts being carried, and where its lower left corner would
+    /// go if placed at the cursor.
+    fn group_at_cursor(state: &GameState) -> Option<(&Assembly, IVec2)> {
+        let CursorState::Group(assembly) = &state.editor_context.cursor_state else {
+            return None;
+        };
+        let pos = state.input.position(MouseButt::Hover, FrameId::Current)?;
+        let pos = vround_f64(state.editor_context.c2w(pos) * PIXELS_PER_METER as f64);
+        Some((assembly, pos - assembly.dims() / 2))
+    }
+
     pub fn new_craft(&mut self) {
-        self.filepath = None;
-        self.vehicle = Vehicle::new();
         self.cursor_state = CursorState::None;
-        self.update();
+        self.selected_parts.clear();
+        self.record_replace("New craft", |ctx| {
+            ctx.vehicle = Vehicle::new();
+            ctx.filepath = None;
+        });
     }
 
     pub fn write_image_to_file(&self, args: &ProgramContext) {
@@ -133,22 +500,22 @@ impl EditorContext {
     }
 
     pub fn rotate_craft(&mut self) {
-        let new_instances: Vec<_> = self
-            .vehicle
-            .parts()
-            .map(|(_, instance)| instance.rotated())
-            .collect();
-        self.vehicle.clear();
-        for instance in new_instances {
-            self.vehicle
-                .add_part(instance.prototype(), instance.origin(), instance.rotation());
-        }
-        self.update();
+        self.selected_parts.clear();
+        self.record_replace("Rotate craft", |ctx| {
+            let new_instances: Vec<_> = ctx
+                .vehicle
+                .parts()
+                .map(|(_, instance)| instance.rotated())
+                .collect();
+            ctx.vehicle.clear();
+            for instance in new_instances {
+                ctx.vehicle.add_instance(instance);
+            }
+        });
     }
 
     pub fn normalize_coordinates(&mut self) {
-        self.vehicle.normalize_coordinates();
-        self.update();
+        self.record_replace("Normalize", |ctx| ctx.vehicle.normalize_coordinates());
     }
 
     pub fn set_current_part(state: &mut GameState, name: &String) {
@@ -157,6 +524,39 @@ impl EditorContext {
         }
     }
 
+    pub fn set_current_assembly(state: &mut GameState, path: &Path) {
+        match load_assembly(path, &state.part_database) {
+            Ok(assembly) => state.editor_context.cursor_state = CursorState::Group(assembly),
+            Err(e) => state.notice(format!("Failed to load {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Saves the selected parts and the pipes under them to an assembly
+    /// file, named after the file.
+    pub fn save_selection_as_assembly(state: &mut GameState) -> Option<()> {
+        let ctx = &state.editor_context;
+        let ids = ctx.selected_parts.iter().cloned();
+        let mut assembly = Assembly::from_vehicle(String::new(), &ctx.vehicle, ids);
+        if assembly.is_empty() {
+            return None;
+        }
+
+        let dir = state.args.assembly_dir();
+        _ = std::fs::create_dir_all(&dir);
+        let path = FileDialog::new()
+            .set_directory(dir)
+            .add_filter("Assembly", &["assembly"])
+            .save_file()?
+            .with_extension("assembly");
+        assembly.set_name(path.file_stem()?.to_string_lossy().to_string());
+
+        match save_assembly(&path, &assembly) {
+            Ok(()) => state.notice(format!("Saved assembly to {}", path.display())),
+            Err(e) => state.notice(format!("Failed to save assembly: {}", e)),
+        }
+        Some(())
+    }
+
     fn open_existing_file(&mut self) -> Option<PathBuf> {
         if let Some(p) = FileDialog::new().set_directory("/").pick_file() {
             self.filepath = Some(p);
@@ -191,25 +591,35 @@ impl EditorContext {
         let choice: PathBuf = state.editor_context.open_file_to_save()?;
         state.notice(format!("Saving to {}", choice.display()));
 
-        let parts = state
+        state.editor_context.write_to(&choice)?;
+
+        let errors = state
             .editor_context
-            .vehicle
-            .parts()
-            .map(|(_, instance)| VehiclePartFileStorage {
-                partname: instance.prototype().sprite_path().to_string(),
-                pos: instance.origin(),
-                rot: instance.rotation(),
-            })
-            .collect();
+            .issues
+            .iter()
+            .filter(|i| i.severity() == Severity::Error)
+            .count();
+        if errors > 0 {
+            state.notice(format!("Saved a craft with {} design errors", errors));
+        }
+        Some(())
+    }
 
-        let storage = VehicleFileStorage {
-            name: state.editor_context.vehicle.model().to_string(),
-            parts,
-            lines: state.editor_context.vehicle.pipes().collect(),
-        };
+    fn write_to(&mut self, path: &Path) -> Option<()> {
+        save_vehicle(path, &self.vehicle).ok()?;
+        self.is_dirty = false;
+        Some(())
+    }
 
-        let s = serde_yaml::to_string(&storage).ok()?;
-        std::fs::write(choice, s).ok()
+    /// Quietly saves to the file the craft was last saved to or loaded
+    /// from, if it's changed since. Never asks for a file.
+    pub fn autosave(state: &mut GameState) -> Option<()> {
+        let ctx = &mut state.editor_context;
+        if !ctx.is_dirty {
+            return None;
+        }
+        let path = ctx.filepath.clone()?;
+        ctx.write_to(&path)
     }
 
     pub fn load_from_file(state: &mut GameState) -> Option<()> {
@@ -227,11 +637,15 @@ impl EditorContext {
             }
         };
 
-        state.editor_context.vehicle = vehicle;
-        state.editor_context.filepath = Some(path.to_path_buf());
-        state.editor_context.update();
-        state.editor_context.vehicles_menu_collapsed = true;
-        state.editor_context.action_queue.clear();
+        let ctx = &mut state.editor_context;
+        let name = format!("Load {}", vehicle.model());
+        ctx.selected_parts.clear();
+        ctx.record_replace(name, |ctx| {
+            ctx.vehicle = vehicle;
+            ctx.filepath = Some(path.to_path_buf());
+        });
+        ctx.is_dirty = false;
+        ctx.vehicles_menu_collapsed = true;
         Some(())
     }
 
@@ -277,51 +691,138 @@ impl EditorContext {
                 self.occupied.insert(instance.prototype().layer(), occ);
             }
         }
+        self.issues = self.vehicle.validate();
+        self.stages = self.vehicle.analyze(&self.planets);
+        let mut fueled = self.vehicle.clone();
+        fueled.fill_tanks(Item::Methane);
+        self.full_tank_delta_v = fueled.analyze(&self.planets).first().map(|s| s.delta_v);
     }
 
-    fn add_part(&mut self, p: IVec2, rot: Rotation, proto: PartPrototype) {
-        self.vehicle.add_part(proto, p, rot);
-        self.update();
+    pub fn issues(&self) -> &[DesignIssue] {
+        &self.issues
     }
 
     fn try_place_part(&mut self, p: IVec2, new_part: PartPrototype) -> Option<()> {
-        let layer = new_part.layer();
-
-        if !self.is_layer_visible(layer) {
+        if !self.is_layer_visible(new_part.layer()) {
             return None;
         }
 
-        let new_pixels = occupied_pixels(p, self.rotation, &new_part);
+        self.place_with_symmetry(vec![(p, self.rotation, new_part)], Vec::new())
+    }
 
-        if let Some(occ) = self.occupied.get(&layer) {
-            for p in &new_pixels {
-                if occ.contains_key(p) {
+    /// Places all of the given parts and pipes as one change, or none of
+    /// them if any part would overlap an existing part or each other.
+    fn place_parts(
+        &mut self,
+        parts: Vec<(IVec2, Rotation, PartPrototype)>,
+        pipes: Vec<IVec2>,
+    ) -> Option<()> {
+        let mut claimed = HashSet::new();
+        for (p, rot, proto) in &parts {
+            let layer = proto.layer();
+            let occ = self.occupied.get(&layer);
+            for q in occupied_pixels(*p, *rot, proto) {
+                if occ.is_some_and(|occ| occ.contains_key(&q)) || !claimed.insert((layer, q)) {
                     return None;
                 }
             }
         }
 
-        self.vehicle.add_part(new_part.clone(), p, self.rotation);
-
-        self.action_queue
-            .push(Action::Add(p, self.rotation, new_part));
+        let mut actions: Vec<_> = parts
+            .into_iter()
+            .map(|(p, rot, proto)| {
+                let part = InstantiatedPart::from_prototype(proto, p, rot);
+                self.vehicle.add_instance(part.clone());
+                Action::Add(part)
+            })
+            .collect();
+        for p in pipes {
+            if !self.vehicle.has_pipe(p) {
+                self.vehicle.add_pipe(p);
+                actions.push(Action::AddPipe(p));
+            }
+        }
+        self.record(actions);
 
         self.update();
         Some(())
     }
 
-    fn remove_part_at(&mut self, p: Vec2) {
-        let pixel_p = vround(p * PIXELS_PER_METER);
-        if let Ok(part) = self.vehicle.remove_part_at(pixel_p, self.focus_layer) {
-            self.action_queue.push(Action::Remove(
-                part.origin(),
-                part.rotation(),
-                part.prototype(),
-            ));
+    /// Places parts and pipes along with their mirror images, or just the
+    /// parts and pipes themselves if the mirror images don't fit.
+    fn place_with_symmetry(
+        &mut self,
+        parts: Vec<(IVec2, Rotation, PartPrototype)>,
+        pipes: Vec<IVec2>,
+    ) -> Option<()> {
+        let mirrored: Vec<_> = parts
+            .iter()
+            .filter_map(|(p, rot, proto)| {
+                let (q, r) = self.symmetry.mirror(*p, *rot, proto)?;
+                ((q, r) != (*p, *rot)).then(|| (q, r, proto.clone()))
+            })
+            .collect();
+
+        if !mirrored.is_empty() {
+            let both = parts.iter().cloned().chain(mirrored).collect();
+            let all_pipes = pipes
+                .iter()
+                .cloned()
+                .chain(pipes.iter().filter_map(|p| self.symmetry.mirror_pipe(*p)))
+                .collect();
+            if self.place_parts(both, all_pipes).is_some() {
+                return Some(());
+            }
         }
+
+        self.place_parts(parts, pipes)
+    }
+
+    fn remove_parts(&mut self, ids: Vec<PartId>) {
+        let actions = ids
+            .into_iter()
+            .filter_map(|id| self.vehicle.remove_part(id))
+            .map(Action::Remove)
+            .collect();
+        self.record(actions);
+        self.selected_parts
+            .retain(|id| self.vehicle.get_part(*id).is_some());
         self.update();
     }
 
+    /// Part sitting exactly where the mirror image of the given one would be.
+    fn find_mirror_image(
+        &self,
+        pos: IVec2,
+        rot: Rotation,
+        proto: &PartPrototype,
+    ) -> Option<PartId> {
+        let (q, r) = self.symmetry.mirror(pos, rot, proto)?;
+        if (q, r) == (pos, rot) {
+            return None;
+        }
+        self.vehicle
+            .parts()
+            .find(|(_, p)| {
+                p.origin() == q
+                    && p.rotation() == r
+                    && p.prototype().part_name() == proto.part_name()
+            })
+            .map(|(id, _)| *id)
+    }
+
+    fn remove_part_at(&mut self, p: Vec2) {
+        let pixel_p = vround(p * PIXELS_PER_METER);
+        let Some(id) = self.vehicle.get_part_at(pixel_p, self.focus_layer) else {
+            return;
+        };
+        let twin = self
+            .vehicle
+            .get_part(id)
+            .and_then(|p| self.find_mirror_image(p.origin(), p.rotation(), &p.prototype()));
+        self.remove_parts([id].into_iter().chain(twin).collect());
+    }
+
     fn current_part_and_cursor_position(state: &GameState) -> Option<(IVec2, PartPrototype)> {
         let ctx = &state.editor_context;
         let part = state.editor_context.cursor_state.current_part()?;
@@ -399,6 +900,26 @@ fn highlight_part(
     }
 }
 
+fn draw_ghost(
+    canvas: &mut Canvas,
+    ctx: &EditorContext,
+    p: IVec2,
+    rot: Rotation,
+    proto: &PartPrototype,
+    alpha: f32,
+) {
+    let dims = pixel_dims_with_rotation(rot, proto);
+    canvas
+        .sprite(
+            ctx.w2c((p.as_dvec2() + dims.as_dvec2() / 2.0) / PIXELS_PER_METER as f64),
+            gcast(rot.to_angle()),
+            proto.sprite_path().to_string(),
+            ZOrdering::EditorCursor,
+            proto.dims().as_vec2() / PIXELS_PER_METER * gcast(ctx.scale()),
+        )
+        .set_color(WHITE.with_alpha(alpha));
+}
+
 impl Render for EditorContext {
     fn background_color(_state: &GameState) -> bevy::color::Srgba {
         GRAY.with_luminance(0.12)
@@ -417,10 +938,14 @@ impl Render for EditorContext {
         let layers = layer_selection(state);
         let vehicles = vehicle_selection(state);
 
-        let other_buttons = other_buttons(state.settings.ui_button_height, &state.universe);
-        // let actions = action_queue(&state.editor_context.action_queue);
+        let other_buttons = other_buttons(
+            state.settings.ui_button_height,
+            &state.universe,
+            &state.editor_context,
+        );
+        let actions = action_queue(state.settings.ui_button_height * 0.8, &state.editor_context);
 
-        let part_buttons = if let Some(id) = state.editor_context.selected_part {
+        let part_buttons = if let Some(id) = state.editor_context.single_selection() {
             if let Some(instance) = state.editor_context.vehicle.get_part(id) {
                 Some(part_ui_layout(
                     state.settings.ui_button_height,
@@ -434,10 +959,16 @@ impl Render for EditorContext {
             None
         };
 
+        let issues = design_issues(
+            state.settings.ui_button_height * 0.7,
+            state.editor_context.issues(),
+        );
+
         let right_column = Node::column(400)
             .invisible()
             .with_child(other_buttons)
-            // .with_child(actions)
+            .with_child(issues)
+            .with_child(actions)
             .with_child(part_buttons);
 
         let main_area = Node::grow()
@@ -478,13 +1009,41 @@ impl Render for EditorContext {
         draw_thrust_particles(canvas, ctx, &ctx.particles, &Universe::empty());
 
         match &ctx.cursor_state {
-            CursorState::None | CursorState::Part(_) => {
+            CursorState::None | CursorState::Part(_) | CursorState::Group(_) => {
                 if let Some(p) = state.input.current() {
                     canvas.circle(p, 4.0, WHITE);
                 }
             }
         }
 
+        match ctx.symmetry {
+            Symmetry::None => (),
+            Symmetry::AcrossX => {
+                let p = ctx.w2c(DVec2::ZERO);
+                let w = state.input.screen_bounds.span.x;
+                canvas.gizmos.line_2d(
+                    Vec2::new(-w, p.y),
+                    Vec2::new(w, p.y),
+                    ORANGE.with_alpha(0.3),
+                );
+            }
+            Symmetry::AcrossY => {
+                let p = ctx.w2c(DVec2::ZERO);
+                let h = state.input.screen_bounds.span.y;
+                canvas.gizmos.line_2d(
+                    Vec2::new(p.x, -h),
+                    Vec2::new(p.x, h),
+                    ORANGE.with_alpha(0.3),
+                );
+            }
+        }
+
+        if ctx.cursor_state.is_none() {
+            if let Some(aabb) = ctx.selection_box(&state.input, FrameId::Current) {
+                draw_aabb(canvas, ctx.w2c_aabb(aabb), GREEN.with_alpha(0.6));
+            }
+        }
+
         let radius = ctx.vehicle.bounding_radius();
         let bounds = ctx.vehicle.aabb();
 
@@ -504,7 +1063,16 @@ impl Render for EditorContext {
         .map(|s| format!("{s}\n"))
         .collect();
 
-        let info = format!("{}{}", info, vehicle_info);
+        let mut info = format!("{}{}", info, vehicle_info);
+
+        if ctx.show_vehicle_info {
+            for (i, stage) in ctx.stages.iter().enumerate() {
+                info += &format!("\nStage {}\n{}", i + 1, stage);
+            }
+            if let Some(dv) = ctx.full_tank_delta_v {
+                info += &format!("\nFull tanks\nDV: {:0.1} m/s\n", dv);
+            }
+        }
 
         let world_pos = Vec2::new(0.0, bounds.lower().y - 1.0).as_dvec2();
         canvas
@@ -828,7 +1396,25 @@ impl Render for EditorContext {
             }
         }
 
-        if let Some(instance) = ctx.selected_part() {
+        for issue in ctx.issues() {
+            let color = issue_color(issue.severity()).with_alpha(0.3);
+            for id in issue.parts() {
+                if let Some(instance) = ctx.vehicle.get_part(id) {
+                    highlight_part(
+                        canvas,
+                        instance,
+                        ctx,
+                        color,
+                        ZOrdering::EditorConflictHighlight,
+                    );
+                }
+            }
+        }
+
+        for id in &ctx.selected_parts {
+            let Some(instance) = ctx.vehicle.get_part(*id) else {
+                continue;
+            };
             highlight_part(
                 canvas,
                 instance,
@@ -839,16 +1425,20 @@ impl Render for EditorContext {
             // canvas.text(format!("{:#?}", instance), Vec2::new(300.0, 400.0), 0.6);
         }
 
-        if let Some((p, current_part)) = Self::current_part_and_cursor_position(state) {
-            let dims = pixel_dims_with_rotation(ctx.rotation, &current_part);
-            let sprite_dims = current_part.dims();
-            canvas.sprite(
-                ctx.w2c((p.as_dvec2() + dims.as_dvec2() / 2.0) / PIXELS_PER_METER as f64),
-                gcast(ctx.rotation.to_angle()),
-                current_part.sprite_path().to_string(),
-                ZOrdering::EditorCursor,
-                sprite_dims.as_vec2() / PIXELS_PER_METER * gcast(ctx.scale()),
-            );
+        let ghosts = if let Some((p, current_part)) = Self::current_part_and_cursor_position(state)
+        {
+            vec![(p, ctx.rotation, current_part)]
+        } else {
+            Self::group_at_cursor(state)
+                .map(|(a, offset)| a.placed_at(offset).0)
+                .unwrap_or_default()
+        };
+
+        for (p, rot, proto) in &ghosts {
+            draw_ghost(canvas, ctx, *p, *rot, proto, 1.0);
+            if let Some((q, r)) = ctx.symmetry.mirror(*p, *rot, proto) {
+                draw_ghost(canvas, ctx, q, r, proto, 0.4);
+            }
         }
 
         for particle in &ctx.build_particles {
@@ -904,11 +1494,35 @@ fn part_selection(state: &GameState) -> Node<OnClick> {
             let onclick = OnClick::SelectPart(s.clone());
             Node::button(s, onclick, Size::Grow, state.settings.ui_button_height)
         }));
+
+        let mut assemblies = get_list_of_assemblies(state).unwrap_or(vec![]);
+        assemblies.sort();
+        if !assemblies.is_empty() {
+            n.add_child(Node::hline());
+        }
+        n.add_children(assemblies.into_iter().map(|(name, path)| {
+            let onclick = OnClick::SelectAssembly(path);
+            Node::button(name, onclick, Size::Grow, state.settings.ui_button_height)
+        }));
     }
 
     n
 }
 
+pub fn get_list_of_assemblies(state: &GameState) -> Option<Vec<(String, PathBuf)>> {
+    let mut ret = vec![];
+    if let Ok(paths) = std::fs::read_dir(state.args.assembly_dir()) {
+        for path in paths.flatten() {
+            let path = path.path();
+            if path.extension().is_some_and(|e| e == "assembly") {
+                let s = path.file_stem()?.to_string_lossy().to_string();
+                ret.push((s, path));
+            }
+        }
+    }
+    Some(ret)
+}
+
 pub fn get_list_of_vehicles(state: &GameState) -> Option<Vec<(String, PathBuf)>> {
     let mut ret = vec![];
     if let Ok(paths) = std::fs::read_dir(&state.args.vehicle_dir()) {
@@ -942,21 +1556,94 @@ fn vehicle_selection(state: &GameState) -> Node<OnClick> {
     n
 }
 
-#[allow(unused)]
-fn action_queue(button_height: f32, queue: &Vec<Action>) -> Node<OnClick> {
+fn issue_color(severity: Severity) -> Srgba {
+    match severity {
+        Severity::Warning => ORANGE,
+        Severity::Error => RED,
+    }
+}
+
+fn design_issues(button_height: f32, issues: &[DesignIssue]) -> Node<OnClick> {
+    let summary = if issues.is_empty() {
+        "No design problems".to_string()
+    } else {
+        format!("{} design problems", issues.len())
+    };
+
     Node::structural(Size::Grow, Size::Fit)
         .with_color(UI_BACKGROUND_COLOR)
         .down()
-        .with_children(
-            queue
-                .iter()
-                .map(|a| Node::text(Size::Grow, button_height, format!("{}", a.to_string()))),
+        .with_child(Node::text(Size::Grow, button_height, summary).enabled(false))
+        .with_children(issues.iter().map(|issue| {
+            let color = issue_color(issue.severity()).with_luminance(0.2);
+            Node::text(Size::Grow, button_height, issue.to_string())
+                .with_color(color.to_f32_array())
+                .enabled(false)
+        }))
+}
+
+/// Most recent actions shown in the history panel.
+const HISTORY_ROWS: usize = 12;
+
+fn action_queue(button_height: f32, ctx: &EditorContext) -> Node<OnClick> {
+    let (done, undone) = ctx.history();
+
+    // every action done and undone, oldest first, along with how many
+    // actions would be in effect after reverting to it
+    let entries: Vec<_> = done
+        .iter()
+        .map(|a| (a, true))
+        .chain(undone.iter().rev().map(|a| (a, false)))
+        .enumerate()
+        .map(|(i, (a, is_done))| (i + 1, a, is_done))
+        .collect();
+
+    let skip = entries.len().saturating_sub(HISTORY_ROWS);
+
+    let rows = entries.into_iter().skip(skip).map(|(n, a, is_done)| {
+        let s = format!("{}. {}", n, a.to_string());
+        let node = Node::button(s, OnClick::RevertTo(n), Size::Grow, button_height)
+            .enabled(n != done.len());
+        if is_done {
+            node
+        } else {
+            node.with_color(GRAY.with_luminance(0.2).to_f32_array())
+        }
+    });
+
+    let controls = Node::row(button_height)
+        .invisible()
+        .with_child(
+            Node::button("Undo", OnClick::Undo, Size::Grow, button_height)
+                .enabled(!done.is_empty()),
         )
+        .with_child(
+            Node::button("Redo", OnClick::Redo, Size::Grow, button_height)
+                .enabled(!undone.is_empty()),
+        );
+
+    let start = Node::button("Start", OnClick::RevertTo(0), Size::Grow, button_height)
+        .enabled(!done.is_empty());
+
+    Node::structural(Size::Grow, Size::Fit)
+        .with_color(UI_BACKGROUND_COLOR)
+        .down()
+        .with_child(controls)
+        .with_child(Node::hline())
+        .with_child((skip == 0).then_some(start))
+        .with_children(rows)
 }
 
-fn other_buttons(button_height: f32, universe: &Universe) -> Node<OnClick> {
+fn other_buttons(button_height: f32, universe: &Universe, ctx: &EditorContext) -> Node<OnClick> {
     let rotate = Node::button("Rotate", OnClick::RotateCraft, Size::Grow, button_height);
 
+    let symmetry = Node::button(
+        ctx.symmetry.label(),
+        OnClick::CycleSymmetry,
+        Size::Grow,
+        button_height,
+    );
+
     let normalize = Node::button(
         "Normalize",
         OnClick::NormalizeCraft,
@@ -966,6 +1653,14 @@ fn other_buttons(button_height: f32, universe: &Universe) -> Node<OnClick> {
 
     let new_button = Node::button("New", OnClick::OpenNewCraft, Size::Grow, button_height);
 
+    let save_assembly = Node::button(
+        "Save Assembly",
+        OnClick::SaveAssembly,
+        Size::Grow,
+        button_height,
+    )
+    .enabled(!ctx.selected_parts.is_empty());
+
     let toggle_info = Node::button(
         "Info",
         OnClick::ToggleVehicleInfo,
@@ -989,6 +1684,8 @@ fn other_buttons(button_height: f32, universe: &Universe) -> Node<OnClick> {
         .with_child(Node::hline())
         .with_child(rotate)
         .with_child(normalize)
+        .with_child(symmetry)
+        .with_child(save_assembly)
         .with_child(Node::hline())
         .with_child(toggle_info)
         .with_children(surface_buttons)
@@ -1043,21 +1740,54 @@ impl EditorContext {
             return;
         }
 
-        if state.input.is_pressed(KeyCode::KeyB) {
+        if state.input.action_pressed(InputAction::EditorBuild) {
             for _ in 0..100 {
                 state.editor_context.vehicle.build_once();
             }
         }
 
+        let ctrl = state.input.is_pressed(KeyCode::ControlLeft);
+
         if let Some(p) = state.input.on_frame(MouseButt::Left, FrameId::Down) {
-            let p = state.editor_context.c2w(p);
-            if let Some((id, _)) = state.editor_context.get_part_at(graphics_cast(p)) {
-                state.editor_context.selected_part = Some(id)
-            } else {
-                state.editor_context.selected_part = None;
+            let group = Self::group_at_cursor(state).map(|(a, offset)| a.placed_at(offset));
+            let ctx = &mut state.editor_context;
+            if let Some((parts, pipes)) = group {
+                ctx.place_with_symmetry(parts, pipes);
+            } else if let Some((id, _)) = ctx.get_part_at(graphics_cast(ctx.c2w(p))) {
+                if !ctrl {
+                    ctx.selected_parts.clear();
+                    ctx.selected_parts.insert(id);
+                } else if !ctx.selected_parts.remove(&id) {
+                    ctx.selected_parts.insert(id);
+                }
+            } else if !ctrl {
+                ctx.selected_parts.clear();
+            }
+        }
+
+        if state.input.on_frame(MouseButt::Left, FrameId::Up).is_some()
+            && state.editor_context.cursor_state.is_none()
+        {
+            if let Some(aabb) = state
+                .editor_context
+                .selection_box(&state.input, FrameId::Up)
+            {
+                state.editor_context.select_in_box(aabb, ctrl);
             }
         }
 
+        if state.input.action_just_pressed(InputAction::Copy) {
+            state.editor_context.copy_selection();
+        }
+
+        if state.input.action_just_pressed(InputAction::Cut) {
+            state.editor_context.cut_selection();
+        }
+
+        if state.input.action_just_pressed(InputAction::Paste) {
+            state.editor_context.paste();
+        }
+
         if state.input.is_pressed(KeyCode::ShiftLeft) {
             if let Some((pos, proto)) = EditorContext::current_part_and_cursor_position(state) {
                 if state.editor_context.snap_info.is_none() {
@@ -1080,8 +1810,8 @@ impl EditorContext {
             state
                 .editor_context
                 .remove_part_at(graphics_cast(state.editor_context.c2w(p)));
-        } else if state.input.just_pressed(KeyCode::KeyQ) {
-            if state.editor_context.cursor_state.current_part().is_some() {
+        } else if state.input.action_just_pressed(InputAction::EditorPick) {
+            if !state.editor_context.cursor_state.is_none() {
                 state.editor_context.cursor_state = CursorState::None;
             } else if let Some(p) = state.input.position(MouseButt::Hover, FrameId::Current) {
                 if let Some((_, instance)) = state
@@ -1098,31 +1828,63 @@ impl EditorContext {
             }
         }
 
-        if state.input.just_pressed(KeyCode::KeyR) {
-            state.editor_context.rotation =
-                enum_iterator::next_cycle(&state.editor_context.rotation);
+        if state.input.action_just_pressed(InputAction::EditorRotate) {
+            let ctx = &mut state.editor_context;
+            if let CursorState::Group(assembly) = &mut ctx.cursor_state {
+                *assembly = assembly.rotated();
+            } else {
+                ctx.rotation = enum_iterator::next_cycle(&ctx.rotation);
+            }
         }
 
         if state.editor_context.focus_layer == Some(PartLayer::Plumbing) {
             if let Some(p) = state.input.position(MouseButt::Left, FrameId::Current) {
                 let p = vfloor(graphics_cast(state.editor_context.c2w(p)) * PIXELS_PER_METER);
-                state.editor_context.vehicle.add_pipe(p);
+                state.editor_context.add_pipe(p);
             }
             if let Some(p) = state.input.position(MouseButt::Right, FrameId::Current) {
                 let p = vfloor(graphics_cast(state.editor_context.c2w(p)) * PIXELS_PER_METER);
-                state.editor_context.vehicle.remove_pipe(p);
+                state.editor_context.remove_pipe(p);
+            }
+        }
+
+        if state
+            .input
+            .position(MouseButt::Left, FrameId::Current)
+            .is_none()
+            && state
+                .input
+                .position(MouseButt::Right, FrameId::Current)
+                .is_none()
+        {
+            let stroke = std::mem::take(&mut state.editor_context.pipe_stroke);
+            state.editor_context.record(stroke);
+        }
+
+        let shift = state.input.is_pressed(KeyCode::ShiftLeft);
+        if state.input.action_just_pressed(InputAction::Undo) {
+            if shift {
+                state.editor_context.redo();
+            } else {
+                state.editor_context.undo();
             }
         }
 
-        if state.input.is_pressed(KeyCode::ControlLeft) && state.input.just_pressed(KeyCode::KeyZ) {
-            state.editor_context.undo();
+        if state.input.action_just_pressed(InputAction::Redo) {
+            state.editor_context.redo();
         }
 
-        if state.input.just_pressed(KeyCode::KeyO) {
+        if state
+            .input
+            .action_just_pressed(InputAction::EditorAtmosphereUp)
+        {
             state.editor_context.atmo += 1;
         }
 
-        if state.input.just_pressed(KeyCode::KeyL) {
+        if state
+            .input
+            .action_just_pressed(InputAction::EditorAtmosphereDown)
+        {
             state.editor_context.atmo -= 1;
         }
 
@@ -1259,4 +2021,167 @@ mod tests {
             write_image_to_file(&vehicle, &args, name);
         }
     }
+
+    fn planets() -> PlanetarySystem {
+        PlanetarySystem::new(EntityId(0), "Luna", Body::LUNA)
+    }
+
+    fn block() -> PartPrototype {
+        PartPrototype::Generic(Generic::new(
+            "block".to_string(),
+            UVec2::new(10, 4),
+            PartLayer::Structural,
+            Mass::kilograms(400),
+        ))
+    }
+
+    fn tank() -> PartPrototype {
+        PartPrototype::Tank(TankModel::new(
+            "tank".to_string(),
+            UVec2::new(10, 10),
+            Mass::kilograms(100),
+            Mass::kilograms(1000),
+        ))
+    }
+
+    #[test]
+    fn mirrored_parts_land_on_the_other_side() {
+        let proto = block();
+
+        let (p, rot) = Symmetry::AcrossY
+            .mirror(IVec2::new(3, 7), Rotation::East, &proto)
+            .unwrap();
+        assert_eq!(p, IVec2::new(-13, 7));
+        assert_eq!(rot, Rotation::West);
+
+        let (p, rot) = Symmetry::AcrossX
+            .mirror(IVec2::new(3, 7), Rotation::North, &proto)
+            .unwrap();
+        assert_eq!(p, IVec2::new(3, -17));
+        assert_eq!(rot, Rotation::South);
+
+        assert!(Symmetry::None
+            .mirror(IVec2::ZERO, Rotation::East, &proto)
+            .is_none());
+    }
+
+    #[test]
+    fn undo_and_redo_every_kind_of_change() {
+        let proto = block();
+
+        let mut ctx = EditorContext::new(planets());
+        ctx.place_parts(
+            vec![(IVec2::ZERO, Rotation::East, proto.clone())],
+            Vec::new(),
+        );
+        ctx.place_parts(
+            vec![(IVec2::new(10, 0), Rotation::East, proto.clone())],
+            Vec::new(),
+        );
+        ctx.add_pipe(IVec2::new(3, 3));
+        ctx.add_pipe(IVec2::new(4, 3));
+        let stroke = std::mem::take(&mut ctx.pipe_stroke);
+        ctx.record(stroke);
+        ctx.rotate_craft();
+
+        assert_eq!(ctx.history().0.len(), 4);
+        assert_eq!(ctx.vehicle.parts().count(), 2);
+
+        ctx.revert_to(1);
+        assert_eq!(ctx.vehicle.parts().count(), 1);
+        assert_eq!(ctx.vehicle.pipes().count(), 0);
+        assert_eq!(ctx.history().1.len(), 3);
+
+        ctx.revert_to(3);
+        assert_eq!(ctx.vehicle.parts().count(), 2);
+        assert_eq!(ctx.vehicle.pipes().count(), 2);
+        ctx.redo();
+        assert_eq!(ctx.history().0.len(), 4);
+
+        // undoing the first placement shouldn't touch the part next to it
+        ctx.revert_to(1);
+        ctx.undo();
+        assert_eq!(ctx.vehicle.parts().count(), 0);
+        ctx.redo();
+        assert_eq!(
+            ctx.vehicle.parts().next().map(|(_, p)| p.origin()),
+            Some(IVec2::ZERO)
+        );
+
+        // a new change throws away whatever was undone
+        ctx.remove_part_at(Vec2::ZERO);
+        assert!(ctx.history().1.is_empty());
+        assert!(ctx.redo().is_none());
+    }
+
+    #[test]
+    fn changes_mark_the_craft_unsaved() {
+        let proto = block();
+
+        let mut ctx = EditorContext::new(planets());
+        assert!(!ctx.is_dirty);
+        ctx.place_parts(vec![(IVec2::ZERO, Rotation::East, proto)], Vec::new());
+        assert!(ctx.is_dirty);
+
+        // as if just saved
+        ctx.is_dirty = false;
+        ctx.undo();
+        assert!(ctx.is_dirty);
+    }
+
+    #[test]
+    fn assemblies_are_placed_and_undone_as_a_unit() {
+        let proto = block();
+
+        let assembly = Assembly::new(
+            "pod".into(),
+            vec![
+                (IVec2::ZERO, Rotation::East, proto.clone()),
+                (IVec2::new(10, 0), Rotation::East, proto),
+            ],
+            [IVec2::new(9, 2), IVec2::new(10, 2)],
+        );
+
+        let mut ctx = EditorContext::new(planets());
+        let (parts, pipes) = assembly.rotated().placed_at(IVec2::new(50, 50));
+        ctx.place_with_symmetry(parts, pipes);
+        assert_eq!(ctx.history().0.len(), 1);
+        assert_eq!(ctx.vehicle.parts().count(), 2);
+        assert_eq!(ctx.vehicle.pipes().count(), 2);
+        assert!(ctx.vehicle.get_part_at(IVec2::new(51, 69), None).is_some());
+
+        // the same spot is taken now, so nothing more gets placed
+        let (parts, pipes) = assembly.placed_at(IVec2::new(45, 55));
+        assert!(ctx.place_with_symmetry(parts, pipes).is_none());
+        assert_eq!(ctx.history().0.len(), 1);
+
+        ctx.undo();
+        assert_eq!(ctx.vehicle.parts().count(), 0);
+        assert_eq!(ctx.vehicle.pipes().count(), 0);
+    }
+
+    #[test]
+    fn removed_and_rotated_parts_keep_their_state() {
+        let mut ctx = EditorContext::new(planets());
+        ctx.place_parts(vec![(IVec2::ZERO, Rotation::East, tank())], Vec::new());
+        ctx.vehicle.fill_tanks(Item::H2);
+
+        let contents = |ctx: &EditorContext| {
+            let (_, part) = ctx.vehicle.parts().next()?;
+            part.as_tank()?.1.contents()
+        };
+        let full = contents(&ctx);
+        assert!(full.is_some());
+
+        let id = *ctx.vehicle.parts().next().unwrap().0;
+        ctx.remove_parts(vec![id]);
+        assert_eq!(ctx.vehicle.parts().count(), 0);
+        ctx.undo();
+        assert_eq!(contents(&ctx), full);
+
+        ctx.rotate_craft();
+        assert_eq!(contents(&ctx), full);
+        ctx.undo();
+        assert_eq!(contents(&ctx), full);
+    }
 }
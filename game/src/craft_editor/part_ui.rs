@@ -60,6 +60,30 @@ fn cargo_ui(
     .collect()
 }
 
+fn thruster_ui(
+    button_height: f32,
+    id: PartId,
+    thruster: &ThrusterModel,
+    data: &ThrusterInstanceData,
+) -> Vec<Node<OnClick>> {
+    vec![
+        text_node(
+            button_height,
+            format!("Thrust: {:0.1} kN", thruster.max_thrust() / 1000.0),
+            None,
+        ),
+        text_node(
+            button_height,
+            if data.is_enabled() {
+                "Enabled"
+            } else {
+                "Disabled"
+            },
+            OnClick::ToggleThruster(id),
+        ),
+    ]
+}
+
 fn machine_ui(
     button_height: f32,
     id: PartId,
@@ -96,6 +120,7 @@ pub fn part_ui_layout(
         InstantiatedPartVariant::Tank(t, d) => tank_ui(button_height, id, t, d),
         InstantiatedPartVariant::Cargo(c, d) => cargo_ui(button_height, id, c, d),
         InstantiatedPartVariant::Machine(m, d) => machine_ui(button_height, id, m, d),
+        InstantiatedPartVariant::Thruster(t, d) => thruster_ui(button_height, id, t, d),
         _ => Vec::new(),
     }
     .into_iter();
@@ -2,11 +2,74 @@ use crate::commands::command::CommandDecl;
 use crate::input::InputState;
 use bevy::input::keyboard::Key;
 use bevy::input::ButtonState;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Most commands kept in the history file
+const MAX_HISTORY: usize = 500;
 
 pub struct DebugConsole {
     is_active: bool,
     text: String,
     history: Vec<String>,
+    /// Commands entered, oldest first, including earlier sessions
+    commands: Vec<String>,
+    /// Position in `commands` while stepping back through them
+    recall: Option<usize>,
+    history_file: Option<PathBuf>,
+}
+
+/// Splits a line of console input into the command it names and its
+/// arguments, the first of which is the command name itself.
+pub fn parse_command(line: &str) -> Result<(CommandDecl, Vec<String>), String> {
+    let args = shellwords::split(line).map_err(|e| format!("{:?}", e))?;
+    let name = args.first().cloned().unwrap_or_default();
+    match CommandDecl::from_str(&name) {
+        Some(v) => Ok((v, args)),
+        None => Err(format!("No command named \"{}\"", name)),
+    }
+}
+
+/// Everything the last word of a partly typed command line could be
+/// completed to: command names for the first word, and the flags and
+/// values its command accepts after that.
+pub fn completions(text: &str) -> Vec<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let new_word = text.is_empty() || text.ends_with(char::is_whitespace);
+    let partial = if new_word {
+        ""
+    } else {
+        words.last().copied().unwrap_or("")
+    };
+
+    let candidates: Vec<String> = if words.len() + new_word as usize <= 1 {
+        enum_iterator::all::<CommandDecl>()
+            .map(|c| c.name())
+            .collect()
+    } else {
+        let decl = match CommandDecl::from_str(words[0]) {
+            Some(decl) => decl,
+            None => return vec![],
+        };
+        // building fills in things like which arguments are bare flags
+        let mut cmd = decl.clap_command();
+        cmd.build();
+        cmd.get_arguments()
+            .flat_map(|arg| {
+                let flag = arg.get_long().map(|l| format!("--{}", l));
+                let values = arg
+                    .get_possible_values()
+                    .into_iter()
+                    .map(|v| v.get_name().to_string());
+                flag.into_iter().chain(values)
+            })
+            .collect()
+    };
+
+    candidates
+        .into_iter()
+        .filter(|c| c.starts_with(partial))
+        .collect()
 }
 
 impl DebugConsole {
@@ -15,7 +78,28 @@ impl DebugConsole {
             is_active: false,
             text: String::new(),
             history: Vec::new(),
+            commands: Vec::new(),
+            recall: None,
+            history_file: None,
+        }
+    }
+
+    /// Loads commands from earlier sessions and appends new ones to the
+    /// same file as they're entered.
+    pub fn set_history_file(&mut self, path: PathBuf) {
+        if let Ok(s) = std::fs::read_to_string(&path) {
+            self.commands = s
+                .lines()
+                .filter(|l| !l.trim().is_empty())
+                .map(|l| l.to_string())
+                .collect();
+            if self.commands.len() > MAX_HISTORY {
+                let excess = self.commands.len() - MAX_HISTORY;
+                self.commands.drain(..excess);
+                _ = std::fs::write(&path, self.commands.join("\n") + "\n");
+            }
         }
+        self.history_file = Some(path);
     }
 
     pub fn show(&mut self) {
@@ -47,28 +131,42 @@ impl DebugConsole {
         self.history.push(s);
     }
 
+    /// Shows a command in the log as though it had been typed.
+    pub fn echo(&mut self, cmd: &str) {
+        self.history.push("".into());
+        self.history.push(format!("> {}", cmd));
+    }
+
+    fn remember(&mut self, cmd: &str) {
+        self.recall = None;
+        if self.commands.last().is_some_and(|last| last == cmd) {
+            return;
+        }
+        self.commands.push(cmd.to_string());
+        if let Some(path) = &self.history_file {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path);
+            if let Ok(mut file) = file {
+                _ = writeln!(file, "{}", cmd);
+            }
+        }
+    }
+
     fn enter(&mut self) -> Option<(CommandDecl, Vec<String>)> {
-        if self.text.is_empty() {
+        if self.text.trim().is_empty() {
             return None;
         }
         let cmd = self.text.clone();
-        self.history.push("".into());
-        self.history.push(format!("> {}", cmd));
+        self.echo(&cmd);
+        self.remember(&cmd);
         self.text.clear();
 
-        match shellwords::split(&cmd) {
-            Ok(args) => {
-                let name = args.get(0).cloned().unwrap_or("".to_string());
-                let v = CommandDecl::from_str(&name);
-                if let Some(v) = v {
-                    Some((v, args))
-                } else {
-                    self.print(format!("No command named \"{}\"", name));
-                    None
-                }
-            }
+        match parse_command(&cmd) {
+            Ok(parsed) => Some(parsed),
             Err(e) => {
-                self.print(format!("{:?}", e));
+                self.print(e);
                 None
             }
         }
@@ -87,6 +185,62 @@ impl DebugConsole {
         self.text.pop();
     }
 
+    fn recall_previous(&mut self) {
+        let i = match self.recall {
+            Some(i) => i.saturating_sub(1),
+            None if !self.commands.is_empty() => self.commands.len() - 1,
+            None => return,
+        };
+        self.recall = Some(i);
+        self.text = self.commands[i].clone();
+    }
+
+    fn recall_next(&mut self) {
+        match self.recall {
+            Some(i) if i + 1 < self.commands.len() => {
+                self.recall = Some(i + 1);
+                self.text = self.commands[i + 1].clone();
+            }
+            Some(_) => {
+                self.recall = None;
+                self.text.clear();
+            }
+            None => (),
+        }
+    }
+
+    /// Completes the word being typed as far as it's unambiguous, listing
+    /// the options if there's more than one.
+    fn complete(&mut self) {
+        let options = completions(&self.text);
+        let partial_len = self
+            .text
+            .rsplit(char::is_whitespace)
+            .next()
+            .map(|w| w.len())
+            .unwrap_or(0);
+
+        let common = match options.as_slice() {
+            [] => return,
+            [only] => format!("{} ", only),
+            [first, rest @ ..] => {
+                let len = rest.iter().fold(first.len(), |len, o| {
+                    first
+                        .chars()
+                        .zip(o.chars())
+                        .take(len)
+                        .take_while(|(a, b)| a == b)
+                        .count()
+                });
+                self.print(options.join("  "));
+                first[..len].to_string()
+            }
+        };
+
+        self.text.truncate(self.text.len() - partial_len);
+        self.text += &common;
+    }
+
     pub fn process_input(&mut self, input: &mut InputState) -> Option<(CommandDecl, Vec<String>)> {
         if !self.is_active {
             return None;
@@ -109,6 +263,9 @@ impl DebugConsole {
                 Key::Enter => return self.enter(),
                 Key::Backspace => self.backspace(),
                 Key::Space => self.text += " ",
+                Key::Tab => self.complete(),
+                Key::ArrowUp => self.recall_previous(),
+                Key::ArrowDown => self.recall_next(),
                 _ => (),
             }
         }
@@ -118,3 +275,33 @@ impl DebugConsole {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completes_command_names() {
+        assert_eq!(completions("tele"), vec!["teleport"]);
+        assert!(completions("set").contains(&"setorbit".to_string()));
+        assert!(completions("set").contains(&"setpolicy".to_string()));
+        assert!(completions("nonsense").is_empty());
+    }
+
+    #[test]
+    fn completes_flags_and_values() {
+        assert_eq!(completions("spawn foo --peri"), vec!["--periapsis"]);
+        assert_eq!(completions("setpolicy 1002 ret"), vec!["retrograde"]);
+        assert!(completions("delete ").contains(&"--landed".to_string()));
+        assert!(!completions("delete ").contains(&"true".to_string()));
+        assert!(completions("bogus --").is_empty());
+    }
+
+    #[test]
+    fn parses_quoted_arguments() {
+        let (decl, args) = parse_command("spawn \"big rocket\" --periapsis 300km").unwrap();
+        assert_eq!(decl.name(), "spawn");
+        assert_eq!(args[1], "big rocket");
+        assert!(parse_command("frobnicate").is_err());
+    }
+}
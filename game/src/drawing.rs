@@ -162,7 +162,7 @@ pub fn draw_orbit(
         let b = orb.semi_minor_axis();
         let center = origin + (orb.periapsis() + orb.apoapsis()) / 2.0;
         let center = ctx.w2c(center);
-        let rot = Quat::from_rotation_z(orb.arg_periapsis as f32);
+        let rot = Quat::from_rotation_z(ctx.w2c_angle(orb.arg_periapsis));
         canvas
             .painter
             .set_translation(center.extend(ZOrdering::Orbit.as_f32()));
@@ -245,7 +245,7 @@ fn draw_planets(
 
     canvas.sprite(
         screen_origin,
-        0.0,
+        ctx.w2c_angle(planet.body.rotation_angle(stamp)),
         planet.name.clone(),
         ZOrdering::Planet,
         graphics_cast(DVec2::splat(planet.body.radius) * 2.0 * ctx.scale()),
@@ -664,6 +664,39 @@ pub fn draw_piloting_overlay(
         .anchor_right()
         .color = color;
 
+    if let Some(impact) = state.universe.predict_impact(piloting) {
+        let color = if impact.is_safe() {
+            GRAY.with_alpha(0.3)
+        } else if is_blinking(state.wall_time) {
+            RED
+        } else {
+            RED.with_alpha(0.3)
+        };
+        canvas
+            .text(
+                format!(
+                    "IMPACT T-{} {:0.1} M/S",
+                    impact.stamp - state.universe.stamp(),
+                    impact.speed()
+                ),
+                center + Vec2::new(r * 0.4, r + 220.0),
+                0.8,
+            )
+            .anchor_right()
+            .color = color;
+    }
+
+    if let Some(mission) = sv.mission() {
+        canvas
+            .text(
+                format!("MISSION {}", mission).to_uppercase(),
+                center + Vec2::new(r * 0.4, r + 250.0),
+                0.8,
+            )
+            .anchor_right()
+            .color = GRAY.with_alpha(0.3);
+    }
+
     let orbit_str = orbit
         .map(|o| format!("{}", o))
         .unwrap_or("/ NO INFO".to_string());
@@ -701,7 +734,12 @@ pub fn draw_piloting_overlay(
     let dash_icons = [
         ("low-fuel", "low-fuel-dim", vehicle.low_fuel(), true),
         ("radar", "radar-dim", vehicle.has_radar(), false),
-        ("ctrl", "ctrl-dim", !vehicle.is_controllable(), true),
+        (
+            "ctrl",
+            "ctrl-dim",
+            !vehicle.is_controllable() || !state.universe.has_comms(piloting),
+            true,
+        ),
     ];
 
     let mut icon_pos = center + Vec2::new(r * 0.9, r * 1.1);
@@ -799,12 +837,16 @@ fn draw_orbiter(canvas: &mut Canvas, state: &GameState, id: EntityId) -> Option<
             &sv.vehicle,
             screen_pos,
             gcast(ctx.scale()),
-            sv.body.angle as f32,
+            ctx.w2c_angle(sv.body.angle),
             false,
             true,
         );
     }
 
+    if piloting || tracked {
+        draw_impact_point(canvas, state, id);
+    }
+
     let color = if !show_orbits {
         return None;
     } else if piloting {
@@ -826,12 +868,46 @@ fn draw_orbiter(canvas: &mut Canvas, state: &GameState, id: EntityId) -> Option<
     Some(())
 }
 
+fn draw_impact_point(canvas: &mut Canvas, state: &GameState, id: EntityId) -> Option<()> {
+    let ctx = &state.orbital_context;
+    let impact = state.universe.predict_impact(id)?;
+    let (_, parent, _, _) = state
+        .universe
+        .planets
+        .lookup(impact.parent, state.universe.stamp())?;
+    let p = ctx.w2c(parent.pos + impact.pos);
+    let color = if impact.is_safe() { GREEN } else { RED };
+    draw_x(&mut canvas.gizmos, p, 16.0, color);
+    draw_circle(&mut canvas.gizmos, p, 12.0, color.with_alpha(0.5));
+    Some(())
+}
+
 fn draw_scenario(canvas: &mut Canvas, state: &GameState) {
     let stamp = state.universe.stamp();
     let ctx = &state.orbital_context;
 
     draw_planets(canvas, &state.universe.planets, stamp, DVec2::ZERO, ctx);
 
+    if ctx.draw_mode == DrawMode::Sensors {
+        draw_sensor_tracks(canvas, state);
+        for id in radar_visible_ids(&state.universe) {
+            draw_orbiter(canvas, state, id);
+        }
+        return;
+    }
+
+    if ctx.draw_mode == DrawMode::Comms {
+        draw_comms_network(canvas, state);
+    }
+
+    if ctx.draw_mode == DrawMode::Conjunctions {
+        draw_conjunctions(canvas, state);
+    }
+
+    if ctx.draw_mode == DrawMode::ThreeBody {
+        draw_three_body(canvas, state);
+    }
+
     let sids = state.universe.surface_vehicles.iter().map(|(id, _)| id);
 
     sids.for_each(|id| {
@@ -839,6 +915,125 @@ fn draw_scenario(canvas: &mut Canvas, state: &GameState) {
     });
 }
 
+fn draw_comms_network(canvas: &mut Canvas, state: &GameState) {
+    let ctx = &state.orbital_context;
+    let universe = &state.universe;
+    let stamp = universe.stamp();
+
+    let node_pos = |n: &CommsNode| match n {
+        CommsNode::GroundStation(i) => universe
+            .ground_stations
+            .get(*i)?
+            .position(&universe.planets, stamp),
+        CommsNode::Vehicle(id) => Some(universe.pv(*id)?.pos),
+    };
+
+    for gs in &universe.ground_stations {
+        if let Some(p) = gs.position(&universe.planets, stamp) {
+            draw_triangle(&mut canvas.gizmos, ctx.w2c(p), 10.0, TEAL);
+        }
+    }
+
+    for (a, b) in universe.comms().links() {
+        if let Some((p, q)) = node_pos(a).zip(node_pos(b)) {
+            canvas
+                .gizmos
+                .line_2d(ctx.w2c(p), ctx.w2c(q), TEAL.with_alpha(0.3));
+        }
+    }
+
+    if let Some(route) = state.piloting().and_then(|id| universe.comms().route(id)) {
+        let points: Vec<_> = route
+            .iter()
+            .filter_map(|n| Some(ctx.w2c(node_pos(n)?)))
+            .collect();
+        canvas.gizmos.linestrip_2d(points, ORANGE);
+    }
+}
+
+fn draw_conjunctions(canvas: &mut Canvas, state: &GameState) {
+    let ctx = &state.orbital_context;
+    let universe = &state.universe;
+    let warning_distance = universe.conjunctions.warning_distance;
+
+    for c in universe.conjunctions.conjunctions() {
+        let color = if c.miss_distance < warning_distance {
+            RED
+        } else {
+            ORANGE
+        };
+
+        if let Some((p, q)) = universe.pv(c.a).zip(universe.pv(c.b)) {
+            canvas
+                .gizmos
+                .line_2d(ctx.w2c(p.pos), ctx.w2c(q.pos), color.with_alpha(0.2));
+        }
+
+        let tca = universe
+            .predicted_pos(c.a, c.tca)
+            .zip(universe.predicted_pos(c.b, c.tca));
+        if let Some((p, q)) = tca {
+            let (p, q) = (ctx.w2c(p), ctx.w2c(q));
+            draw_x(&mut canvas.gizmos, p, 12.0, color.with_alpha(0.8));
+            draw_x(&mut canvas.gizmos, q, 12.0, color.with_alpha(0.8));
+            canvas.gizmos.line_2d(p, q, color.with_alpha(0.8));
+        }
+    }
+}
+
+fn draw_three_body(canvas: &mut Canvas, state: &GameState) {
+    let ctx = &state.orbital_context;
+    let universe = &state.universe;
+    let stamp = universe.stamp();
+
+    for sys in ThreeBodySystem::all(&universe.planets, stamp) {
+        if let Some((_, pv, _, _)) = universe.planets.lookup(sys.primary, stamp) {
+            for (_, p) in sys.lagrange_points() {
+                draw_diamond(&mut canvas.gizmos, ctx.w2c(pv.pos + p), 14.0, YELLOW);
+            }
+        }
+    }
+
+    if let Some((sys, origin, c)) = state.piloting().and_then(|id| universe.three_body(id)) {
+        for (a, b) in sys.zero_velocity_curves(c, 160) {
+            canvas.gizmos.line_2d(
+                ctx.w2c(origin + a),
+                ctx.w2c(origin + b),
+                YELLOW.with_alpha(0.4),
+            );
+        }
+    }
+}
+
+fn draw_sensor_tracks(canvas: &mut Canvas, state: &GameState) {
+    let ctx = &state.orbital_context;
+    let stamp = state.universe.stamp();
+
+    for (id, sv) in &state.universe.surface_vehicles {
+        let range = match sv.vehicle.radar_range() {
+            Some(r) => r,
+            None => continue,
+        };
+        let pv = match state.universe.pv(*id) {
+            Some(pv) => pv,
+            None => continue,
+        };
+
+        draw_circle(
+            &mut canvas.gizmos,
+            ctx.w2c(pv.pos),
+            gcast(range * ctx.scale()),
+            GREEN.with_alpha(0.2),
+        );
+
+        for (_, track) in sv.tracks.iter() {
+            let alpha = 1.0 - track.age(stamp).to_secs() / SENSOR_TRACK_TIMEOUT.to_secs();
+            let p = ctx.w2c(track.estimated_position());
+            draw_square(&mut canvas.gizmos, p, 14.0, GREEN.with_alpha(alpha * 0.8));
+        }
+    }
+}
+
 fn draw_event_marker_at(gizmos: &mut Gizmos, wall_time: Nanotime, event: &EventType, p: Vec2) {
     let blinking = is_blinking(wall_time);
 
@@ -1003,7 +1198,13 @@ pub fn draw_notifications(gizmos: &mut Gizmos, state: &GameState) {
                 draw_square(gizmos, p, size, RED.with_alpha(a));
             }
             NotificationType::NotControllable(_) => (),
+            NotificationType::NoSignal(_) => {
+                draw_x(gizmos, p, size, YELLOW.with_alpha(a));
+            }
             NotificationType::OrbitChanged(_) => (),
+            NotificationType::ConjunctionWarning(..) => {
+                draw_square(gizmos, p, size, ORANGE.with_alpha(a));
+            }
             NotificationType::Notice(_) => (),
         }
     }
@@ -1099,11 +1300,13 @@ fn draw_rendezvous_info(canvas: &mut Canvas, state: &GameState) -> Option<()> {
 
     let po = pv.current_orbit()?;
 
-    let to = state
-        .universe
-        .surface_vehicles
-        .get(&target)?
-        .current_orbit()?;
+    // prefer what our own sensors can see over the true target orbit
+    let estimated = pv.estimated_target_orbit();
+    let to = state.universe.target_orbit(pilot)?;
+
+    if let Some(orbit) = &estimated {
+        draw_global_orbit(canvas, orbit, state, YELLOW.with_alpha(0.4));
+    }
 
     let target_pos = state.universe.pv(target)?;
 
@@ -1733,7 +1936,7 @@ pub fn draw_thrust_particles(
         canvas
             .sprite(
                 p,
-                particle.angle,
+                ctx.w2c_angle(particle.angle as f64),
                 "cloud",
                 ZOrdering::ThrustParticles,
                 Vec2::new(size * stretch * ramp_up, size * ramp_up) * gcast(ctx.scale()),
@@ -25,28 +25,21 @@ fn combo_just_pressed(input: &InputState, keys: &[KeyCode]) -> bool {
     }
 }
 
-fn gamepad_usage_system(gamepads: Query<(&Name, &Gamepad)>, mut state: ResMut<GameState>) {
-    for (_name, gamepad) in &gamepads {
-        for button in gamepad.get_just_pressed() {
-            dbg!((button, state.cursor_position, true));
-        }
-        for button in gamepad.get_just_released() {
-            dbg!((button, state.cursor_position, false));
-        }
-
-        if gamepad.just_pressed(GamepadButton::South) {
-            let wb = state.input.screen_bounds.span;
-            let n = state.ui.at(state.cursor_position, wb);
-            if let Some(event) = n
-                .map(|n| n.is_enabled().then(|| n.on_click()))
-                .flatten()
-                .flatten()
-                .cloned()
-            {
-                state.on_button_event(event);
-            }
+fn gamepad_usage_system(gamepads: Query<&Gamepad>, mut state: ResMut<GameState>) {
+    if state.input.action_just_pressed(InputAction::Click) {
+        let wb = state.input.screen_bounds.span;
+        let n = state.ui.at(state.cursor_position, wb);
+        if let Some(event) = n
+            .map(|n| n.is_enabled().then(|| n.on_click()))
+            .flatten()
+            .flatten()
+            .cloned()
+        {
+            state.on_button_event(event);
         }
+    }
 
+    for gamepad in &gamepads {
         let speed = state.settings.controller_cursor_speed;
 
         if let Some(left_stick_x) = gamepad.get(GamepadAxis::LeftStickX) {
@@ -109,6 +102,10 @@ fn init_system(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
 
     let mut g = GameState::new(args);
 
+    if let Some(path) = g.args.exec.clone() {
+        g.exec_file(&path);
+    }
+
     g.load_sprites(&mut images);
 
     commands.insert_resource(g);
@@ -176,15 +173,22 @@ pub struct GameState {
 
     pub editor_context: EditorContext,
 
+    pub controls_context: ControlsContext,
+
     /// Wall clock, i.e. time since program began.
     pub wall_time: Nanotime,
 
+    /// Wall time of the last autosave, or of startup
+    pub last_autosave: Nanotime,
+
     pub physics_duration: Nanotime,
     pub universe_ticks_per_game_tick: SimRate,
     pub paused: bool,
+    /// Sim time to run flat out until, ignoring the sim rate
+    pub warp_to: Option<Nanotime>,
     pub exec_time: std::time::Duration,
     pub actual_universe_ticks_per_game_tick: u32,
-    pub using_batch_mode: bool,
+    pub sim_mode: SimMode,
     pub force_batch_mode: bool,
 
     /// Map of names to parts to their definitions. Loaded from
@@ -251,8 +255,17 @@ impl GameState {
             }
         };
 
+        for (binding, actions) in settings.keybindings.conflicts() {
+            warn!(
+                "{} is bound to more than one action: {:?}",
+                binding, actions
+            );
+        }
+
+        let sim_rate = settings.default_sim_rate;
+
         let mut sounds = EnvironmentSounds::new();
-        sounds.play_loop("building.ogg", 0.1);
+        sounds.play_loop(SoundChannel::Ambient, "building.ogg", 0.1);
 
         let vehicle_names = match load_names_from_file(&args.names_path()) {
             Ok(n) => n,
@@ -313,14 +326,17 @@ impl GameState {
             console: DebugConsole::new(),
             orbital_context: OrbitalContext::new(EntityId(0)),
             telescope_context: TelescopeContext::new(),
-            editor_context: EditorContext::new(),
+            editor_context: EditorContext::new(planets.clone()),
+            controls_context: ControlsContext::new(),
             wall_time: Nanotime::zero(),
+            last_autosave: Nanotime::zero(),
             physics_duration: Nanotime::days(7),
-            universe_ticks_per_game_tick: SimRate::RealTime,
+            universe_ticks_per_game_tick: sim_rate,
             actual_universe_ticks_per_game_tick: 0,
-            using_batch_mode: false,
+            sim_mode: SimMode::Stepped,
             force_batch_mode: false,
             paused: false,
+            warp_to: None,
             exec_time: std::time::Duration::new(0, 0),
             part_database,
             starfield: generate_starfield(),
@@ -336,6 +352,8 @@ impl GameState {
             buttons,
         };
 
+        g.console.set_history_file(args.console_history_path());
+
         let earth_id = g.universe.lup_planet_by_name("Earth").unwrap();
         let luna_id = g.universe.lup_planet_by_name("Luna").unwrap();
 
@@ -476,6 +494,8 @@ impl Render for GameState {
             SceneType::Orbital => OrbitalContext::background_color(state),
             SceneType::Editor => EditorContext::background_color(state),
             SceneType::Telescope => TelescopeContext::background_color(state),
+            SceneType::Settings => SettingsContext::background_color(state),
+            SceneType::Controls => ControlsContext::background_color(state),
             SceneType::MainMenu => BLACK,
         }
     }
@@ -519,6 +539,8 @@ impl Render for GameState {
             SceneType::Editor => EditorContext::draw(canvas, state),
             SceneType::Telescope => TelescopeContext::draw(canvas, state),
             SceneType::MainMenu => MainMenuContext::draw(canvas, state),
+            SceneType::Settings => SettingsContext::draw(canvas, state),
+            SceneType::Controls => ControlsContext::draw(canvas, state),
         }
     }
 }
@@ -526,20 +548,24 @@ impl Render for GameState {
 fn keyboard_control_law(input: &InputState) -> VehicleControl {
     let mut ctrl = VehicleControl::NULLOPT;
 
-    let docking_mode = input.is_pressed(KeyCode::ControlLeft);
+    let docking_mode = input.action_pressed(InputAction::DockingMode);
+    let forward = input.action_pressed(InputAction::ThrustForward);
+    let backward = input.action_pressed(InputAction::ThrustBackward);
+    let left = input.action_pressed(InputAction::TurnLeft);
+    let right = input.action_pressed(InputAction::TurnRight);
 
     if docking_mode {
-        ctrl.plus_x.throttle = input.is_pressed(KeyCode::ArrowUp) as u8 as f32;
-        ctrl.plus_y.throttle = input.is_pressed(KeyCode::ArrowLeft) as u8 as f32;
-        ctrl.neg_x.throttle = input.is_pressed(KeyCode::ArrowDown) as u8 as f32;
-        ctrl.neg_y.throttle = input.is_pressed(KeyCode::ArrowRight) as u8 as f32;
+        ctrl.plus_x.throttle = forward as u8 as f32;
+        ctrl.plus_y.throttle = left as u8 as f32;
+        ctrl.neg_x.throttle = backward as u8 as f32;
+        ctrl.neg_y.throttle = right as u8 as f32;
     } else {
-        ctrl.plus_x.throttle = input.is_pressed(KeyCode::ArrowUp) as u8 as f32;
-        ctrl.neg_x.throttle = input.is_pressed(KeyCode::ArrowDown) as u8 as f32;
+        ctrl.plus_x.throttle = forward as u8 as f32;
+        ctrl.neg_x.throttle = backward as u8 as f32;
 
-        ctrl.attitude = if input.is_pressed(KeyCode::ArrowLeft) {
+        ctrl.attitude = if left {
             10.0
-        } else if input.is_pressed(KeyCode::ArrowRight) {
+        } else if right {
             -10.0
         } else {
             0.0
@@ -664,7 +690,7 @@ impl GameState {
         );
         let orbit = SparseOrbit::from_pv(pv_local + perturb, orbit.body, self.universe.stamp())?;
         self.universe
-            .add_orbital_vehicle(vehicle, GlobalOrbit(parent, orbit));
+            .add_orbital_vehicle(vehicle, GlobalOrbit(parent, orbit))?;
         Some(())
     }
 
@@ -675,7 +701,7 @@ impl GameState {
     }
 
     pub fn delete_orbiter(&mut self, id: EntityId) -> Option<()> {
-        let ov = self.universe.surface_vehicles.remove(&id)?;
+        let ov = self.universe.remove(id)?;
         let parent = ov.parent();
         let pv = ov.pv();
         self.notify(
@@ -750,6 +776,29 @@ impl GameState {
         Some(())
     }
 
+    /// Runs console commands from a file, one per line, skipping blank
+    /// lines and lines starting with '#'.
+    pub fn exec_file(&mut self, path: &Path) {
+        let script = match std::fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) => {
+                self.notice(format!("Failed to read {}: {}", path.display(), e));
+                return;
+            }
+        };
+
+        for line in script.lines().map(|l| l.trim()) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            self.console.echo(line);
+            match parse_command(line) {
+                Ok((decl, args)) => decl.execute(self, args),
+                Err(e) => self.console.print(e),
+            }
+        }
+    }
+
     pub fn notice(&mut self, s: impl Into<String>) {
         let s = s.into();
         info!("Notice: {s}");
@@ -772,7 +821,9 @@ impl GameState {
             kind,
         };
 
-        if self.notifications.iter().any(|e| notif.is_duplicate(e)) {
+        if !self.settings.is_shown(notif.kind.kind())
+            || self.notifications.iter().any(|e| notif.is_duplicate(e))
+        {
             return;
         }
 
@@ -799,7 +850,8 @@ impl GameState {
     }
 
     pub fn on_button_event(&mut self, id: OnClick) -> Option<()> {
-        self.sounds.play_once("button-up.ogg", 1.0);
+        self.sounds
+            .play_once(SoundChannel::Interface, "button-up.ogg", 1.0);
 
         match id {
             OnClick::CurrentBody(id) => self.orbital_context.following = Some(id),
@@ -860,6 +912,8 @@ impl GameState {
                 }
             }
             OnClick::SelectPart(name) => EditorContext::set_current_part(self, &name),
+            OnClick::SelectAssembly(path) => EditorContext::set_current_assembly(self, &path),
+            OnClick::SaveAssembly => _ = EditorContext::save_selection_as_assembly(self),
             OnClick::ToggleLayer(layer) => self.editor_context.toggle_layer(layer),
             OnClick::LoadVehicle(path) => _ = EditorContext::load_vehicle(&path, self),
             OnClick::ConfirmExitDialog => self.shutdown(),
@@ -884,6 +938,12 @@ impl GameState {
             OnClick::RotateCraft => {
                 self.editor_context.rotate_craft();
             }
+            OnClick::CycleSymmetry => {
+                self.editor_context.cycle_symmetry();
+            }
+            OnClick::Undo => _ = self.editor_context.undo(),
+            OnClick::Redo => _ = self.editor_context.redo(),
+            OnClick::RevertTo(n) => self.editor_context.revert_to(n),
             OnClick::ToggleVehicleInfo => {
                 self.editor_context.show_vehicle_info = !self.editor_context.show_vehicle_info;
             }
@@ -903,7 +963,7 @@ impl GameState {
             OnClick::SwapOwnshipTarget => _ = self.swap_ownship_target(),
             OnClick::ReloadGame => _ = self.reload(),
             OnClick::SetRecipe(id, recipe) => {
-                if self.editor_context.vehicle.set_recipe(id, recipe) {
+                if self.editor_context.set_recipe(id, recipe) {
                     self.notice(format!("Set recipe for part {:?} to {:?}", id, recipe));
                 } else {
                     self.notice(format!(
@@ -913,15 +973,38 @@ impl GameState {
                 }
             }
             OnClick::ClearContents(id) => {
-                if self.editor_context.vehicle.clear_contents(id) {
+                if self.editor_context.clear_contents(id) {
                     self.notice(format!("Cleared inventory for part {:?}", id));
                 } else {
                     self.notice(format!("Failed to clear inventory for part {:?}", id));
                 }
             }
+            OnClick::ToggleThruster(id) => match self.editor_context.toggle_thruster(id) {
+                Some(true) => self.notice(format!("Enabled thruster {:?}", id)),
+                Some(false) => self.notice(format!("Disabled thruster {:?}", id)),
+                None => self.notice(format!("Part {:?} is not a thruster", id)),
+            },
             OnClick::SetControllerPolicy(policy) => {
                 self.set_controller_policy(policy);
             }
+            OnClick::ChangeSetting(change) => {
+                // the starting sim rate only takes effect in the next game
+                self.settings.apply(change);
+                self.save_settings();
+            }
+            OnClick::CaptureBinding(action) => self.controls_context.start_capture(action),
+            OnClick::CancelCapture => self.controls_context.cancel_capture(),
+            OnClick::Unbind(action, binding) => {
+                self.settings.keybindings.unbind(action, binding);
+                self.save_settings();
+            }
+            OnClick::ResetBindings(action) => {
+                match action {
+                    Some(action) => self.settings.keybindings.reset(action),
+                    None => self.settings.keybindings = Keybindings::default(),
+                }
+                self.save_settings();
+            }
 
             // BOOKMARK unhandled event
             _ => info!("Unhandled button event: {id:?}"),
@@ -930,10 +1013,58 @@ impl GameState {
         Some(())
     }
 
+    /// Binds whatever was pressed to the action being rebound, if any.
+    pub fn on_binding_captured(&mut self, binding: Binding) {
+        let action = match self.controls_context.capturing() {
+            Some(a) => a,
+            None => return,
+        };
+        self.controls_context.cancel_capture();
+        if binding == Binding::Key(KeyCode::Escape) {
+            return;
+        }
+
+        self.settings.keybindings.bind(action, binding);
+        for (b, actions) in self.settings.keybindings.conflicts() {
+            if b == binding {
+                let others: Vec<_> = actions.iter().filter(|a| **a != action).collect();
+                self.notice(format!("{} is also bound to {:?}", binding, others));
+            }
+        }
+        self.save_settings();
+    }
+
+    pub fn save_settings(&mut self) {
+        if let Err(e) = write_settings_to_file(&self.args.settings_path(), &self.settings) {
+            self.notice(format!("Failed to save settings: {}", e));
+        }
+    }
+
     pub fn set_controller_policy(&mut self, policy: VehicleControlPolicy) -> Option<()> {
         let piloting = self.piloting()?;
+        if self.universe.set_vehicle_policy(piloting, policy).is_err() {
+            self.notify(
+                ObjectId::Orbiter(piloting),
+                NotificationType::NoSignal(piloting),
+                None,
+            );
+            return None;
+        }
+        Some(())
+    }
+
+    pub fn next_controller_mode(&mut self) -> Option<()> {
+        let piloting = self.piloting()?;
+        if !self.universe.has_comms(piloting) {
+            self.notify(
+                ObjectId::Orbiter(piloting),
+                NotificationType::NoSignal(piloting),
+                None,
+            );
+            return None;
+        }
         let sv = self.universe.surface_vehicles.get_mut(&piloting)?;
-        sv.controller.set_policy(policy);
+        sv.controller.go_to_next_mode();
         Some(())
     }
 
@@ -953,6 +1084,7 @@ impl GameState {
 
     pub fn set_current_scene(&mut self, s: SceneType) -> Option<()> {
         self.scene = s;
+        self.controls_context.cancel_capture();
         Some(())
     }
 
@@ -1105,8 +1237,14 @@ impl GameState {
             SceneType::Editor => {
                 EditorContext::on_render_tick(self);
             }
-            SceneType::MainMenu => (),
+            SceneType::MainMenu | SceneType::Settings | SceneType::Controls => (),
             SceneType::Orbital => {
+                if self
+                    .input
+                    .action_just_pressed(InputAction::NextControllerMode)
+                {
+                    self.next_controller_mode();
+                }
                 self.orbital_context.on_render_tick(
                     on_ui,
                     &self.input,
@@ -1123,6 +1261,12 @@ impl GameState {
     pub fn on_game_tick(&mut self) {
         self.game_ticks += 1;
 
+        let autosave = Nanotime::mins(self.settings.autosave_minutes as i64);
+        if autosave > Nanotime::zero() && self.wall_time - self.last_autosave >= autosave {
+            self.last_autosave = self.wall_time;
+            EditorContext::autosave(self);
+        }
+
         for button in &mut self.buttons {
             button.step();
         }
@@ -1138,8 +1282,18 @@ impl GameState {
 
         if !signals.is_empty() {
             self.universe_ticks_per_game_tick = SimRate::RealTime;
+            self.warp_to = None;
         }
 
+        let ticks = match self.warp_to {
+            Some(t) => {
+                let remaining =
+                    (t - self.universe.stamp()).inner() / PHYSICS_CONSTANT_DELTA_TIME.inner();
+                remaining.clamp(0, u32::MAX as i64) as u32
+            }
+            None => self.universe_ticks_per_game_tick.as_ticks(),
+        };
+
         // BOOKMARK gameloop
         self.actual_universe_ticks_per_game_tick = 0;
         self.exec_time = std::time::Duration::ZERO;
@@ -1147,16 +1301,36 @@ impl GameState {
             (
                 self.actual_universe_ticks_per_game_tick,
                 self.exec_time,
-                self.using_batch_mode,
-            ) = self.universe.on_sim_ticks(
-                self.universe_ticks_per_game_tick.as_ticks(),
-                &signals,
-                std::time::Duration::from_millis(10),
-            )
+                self.sim_mode,
+            ) = self
+                .universe
+                .on_sim_ticks(ticks, &signals, std::time::Duration::from_millis(10))
+        }
+
+        if self.warp_to.is_some_and(|t| self.universe.stamp() >= t) {
+            self.warp_to = None;
+            self.notice(format!("Warped to {}", self.universe.stamp()));
         }
 
         self.wall_time += PHYSICS_CONSTANT_DELTA_TIME;
 
+        for c in self.universe.conjunctions.take_warnings() {
+            let kind = NotificationType::ConjunctionWarning(c.a, c.b);
+            self.console.log(format!(
+                "{kind}: {:0.0} m at {} ({:0.1} m/s)",
+                c.miss_distance, c.tca, c.relative_speed
+            ));
+            self.notify(ObjectId::Orbiter(c.a), kind.clone(), None);
+            self.notify(ObjectId::Orbiter(c.b), kind, None);
+        }
+
+        for (id, impact) in self.universe.take_crashes() {
+            let kind = NotificationType::OrbiterCrashed(id);
+            self.console
+                .log(format!("{kind} at {:0.1} m/s", impact.speed()));
+            self.notify(ObjectId::Orbiter(id), kind, None);
+        }
+
         self.notifications.iter_mut().for_each(|n| n.jitter());
 
         self.notifications
@@ -1200,7 +1374,10 @@ fn process_interaction(
     window: &mut Window,
 ) -> Option<()> {
     match inter {
-        InteractionEvent::Delete => state.delete_objects(),
+        InteractionEvent::Delete => match state.scene {
+            SceneType::Editor => state.editor_context.delete_selection(),
+            _ => state.delete_objects(),
+        },
         InteractionEvent::CommitMission => {
             state.commit_mission();
         }
@@ -1232,6 +1409,9 @@ fn process_interaction(
         InteractionEvent::DrawMode => {
             state.orbital_context.draw_mode = next_cycle(&state.orbital_context.draw_mode);
         }
+        InteractionEvent::CameraFrame => {
+            state.orbital_context.frame = next_cycle(&state.orbital_context.frame);
+        }
         InteractionEvent::Orbits => {
             state.orbital_context.show_orbits = next_cycle(&state.orbital_context.show_orbits);
         }
@@ -1,9 +1,11 @@
 use crate::game::GameState;
+use crate::keybindings::InputAction;
 use bevy::input::keyboard::KeyboardInput;
 use bevy::input::mouse::MouseWheel;
 use bevy::prelude::*;
 use starling::nanotime::Nanotime;
 use starling::prelude::AABB;
+use std::collections::HashSet;
 
 const DOUBLE_CLICK_DURATION: Nanotime = Nanotime::millis(300);
 
@@ -107,6 +109,9 @@ pub struct InputState {
     buttons: ButtonInput<KeyCode>,
     pub keyboard_events: Vec<KeyboardInput>,
     scroll: ScrollDir,
+
+    actions: HashSet<InputAction>,
+    previous_actions: HashSet<InputAction>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -149,6 +154,18 @@ impl InputState {
         self.buttons = buttons;
     }
 
+    pub fn set_actions(&mut self, actions: HashSet<InputAction>) {
+        self.previous_actions = std::mem::replace(&mut self.actions, actions);
+    }
+
+    pub fn action_pressed(&self, action: InputAction) -> bool {
+        self.actions.contains(&action)
+    }
+
+    pub fn action_just_pressed(&self, action: InputAction) -> bool {
+        self.actions.contains(&action) && !self.previous_actions.contains(&action)
+    }
+
     pub fn set_scroll(&mut self, mut scroll: EventReader<MouseWheel>) {
         self.scroll = match scroll.read().next() {
             Some(m) => match m.y.partial_cmp(&0.0) {
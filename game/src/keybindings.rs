@@ -1,57 +1,499 @@
 use crate::game::GameState;
 use crate::sim_rate::SimRate;
 use crate::ui::InteractionEvent;
+use bevy::input::gamepad::{Gamepad, GamepadInput};
 use bevy::input::mouse::MouseWheel;
 use bevy::prelude::*;
+use enum_iterator::Sequence;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// How far a stick or trigger has to move before it counts as pressed
+const AXIS_THRESHOLD: f32 = 0.5;
+
+/// Something the player can do by pressing a key or button.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Sequence, Serialize, Deserialize,
+)]
+pub enum InputAction {
+    SimFaster,
+    SimSlower,
+    SimRealTime,
+    SimPause,
+    Delete,
+    CreateGroup,
+    ClearMissions,
+    CommitMission,
+    ZoomIn,
+    ZoomOut,
+    Reset,
+    Escape,
+    CursorMode,
+    DrawMode,
+    CameraFrame,
+    ToggleFullscreen,
+    ToggleDebugConsole,
+    Spawn,
+    ThrustForward,
+    ThrustBackward,
+    TurnLeft,
+    TurnRight,
+    /// Held to translate with RCS instead of turning
+    DockingMode,
+    /// Clicks whatever UI element is under the cursor
+    Click,
+    Copy,
+    Cut,
+    Paste,
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    NextControllerMode,
+    /// Builds the parts of the craft being edited while held
+    EditorBuild,
+    /// Picks up the part under the cursor, or puts down the one held
+    EditorPick,
+    EditorRotate,
+    EditorAtmosphereUp,
+    EditorAtmosphereDown,
+    /// Also redoes while Shift is held
+    Undo,
+    Redo,
+}
+
+/// Which scenes an action does anything in. Actions which are never live
+/// in the same scene can share a binding without conflicting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionScope {
+    Everywhere,
+    Orbital,
+    Editor,
+}
+
+impl ActionScope {
+    fn overlaps(&self, other: ActionScope) -> bool {
+        *self == ActionScope::Everywhere || other == ActionScope::Everywhere || *self == other
+    }
+}
+
+impl InputAction {
+    pub fn all() -> impl Iterator<Item = InputAction> {
+        enum_iterator::all::<InputAction>()
+    }
+
+    pub fn scope(&self) -> ActionScope {
+        match self {
+            InputAction::Reset | InputAction::NextControllerMode => ActionScope::Orbital,
+            InputAction::EditorBuild
+            | InputAction::EditorPick
+            | InputAction::EditorRotate
+            | InputAction::EditorAtmosphereUp
+            | InputAction::EditorAtmosphereDown
+            | InputAction::Undo
+            | InputAction::Redo => ActionScope::Editor,
+            _ => ActionScope::Everywhere,
+        }
+    }
+
+    /// Event sent once when the action is first pressed.
+    fn on_press(&self) -> Option<InteractionEvent> {
+        Some(match self {
+            InputAction::SimFaster => InteractionEvent::SimFaster,
+            InputAction::SimSlower => InteractionEvent::SimSlower,
+            InputAction::SimRealTime => InteractionEvent::SetSim(SimRate::RealTime),
+            InputAction::SimPause => InteractionEvent::SimPause,
+            InputAction::Delete => InteractionEvent::Delete,
+            InputAction::CreateGroup => InteractionEvent::CreateGroup,
+            InputAction::ClearMissions => InteractionEvent::ClearMissions,
+            InputAction::CommitMission => InteractionEvent::CommitMission,
+            InputAction::ZoomIn => InteractionEvent::ZoomIn,
+            InputAction::ZoomOut => InteractionEvent::ZoomOut,
+            InputAction::Reset => InteractionEvent::Reset,
+            InputAction::Escape => InteractionEvent::Escape,
+            InputAction::CursorMode => InteractionEvent::CursorMode,
+            InputAction::DrawMode => InteractionEvent::DrawMode,
+            InputAction::CameraFrame => InteractionEvent::CameraFrame,
+            InputAction::ToggleFullscreen => InteractionEvent::ToggleFullscreen,
+            InputAction::ToggleDebugConsole => InteractionEvent::ToggleDebugConsole,
+            _ => return None,
+        })
+    }
+
+    /// Event sent every frame the action is held.
+    fn while_held(&self, docking: bool) -> Option<InteractionEvent> {
+        Some(match (self, docking) {
+            (InputAction::Spawn, _) => InteractionEvent::Spawn,
+            (InputAction::ThrustForward, _) => InteractionEvent::Thrust(1),
+            (InputAction::ThrustBackward, _) => InteractionEvent::Thrust(-1),
+            (InputAction::TurnLeft, false) => InteractionEvent::TurnLeft,
+            (InputAction::TurnRight, false) => InteractionEvent::TurnRight,
+            (InputAction::TurnLeft, true) => InteractionEvent::StrafeLeft,
+            (InputAction::TurnRight, true) => InteractionEvent::StrafeRight,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AxisDirection {
+    Positive,
+    Negative,
+}
+
+impl AxisDirection {
+    fn sign(&self) -> f32 {
+        match self {
+            AxisDirection::Positive => 1.0,
+            AxisDirection::Negative => -1.0,
+        }
+    }
+}
+
+/// A single key, a key held with Ctrl, a gamepad button, or direction of
+/// a gamepad axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Ctrl(KeyCode),
+    Button(GamepadButton),
+    Axis(GamepadAxis, AxisDirection),
+}
+
+fn is_ctrl_key(k: KeyCode) -> bool {
+    matches!(k, KeyCode::ControlLeft | KeyCode::ControlRight)
+}
+
+fn ctrl_held(keys: &ButtonInput<KeyCode>) -> bool {
+    keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+}
+
+impl Binding {
+    pub fn is_pressed(&self, keys: &ButtonInput<KeyCode>, gamepads: &[&Gamepad]) -> bool {
+        match self {
+            Binding::Key(k) => keys.pressed(*k),
+            Binding::Ctrl(k) => keys.pressed(*k) && ctrl_held(keys),
+            Binding::Button(b) => gamepads.iter().any(|g| g.pressed(*b)),
+            Binding::Axis(a, dir) => gamepads
+                .iter()
+                .any(|g| g.get(*a).is_some_and(|v| v * dir.sign() > AXIS_THRESHOLD)),
+        }
+    }
+
+    /// The first binding to be pressed this frame, if any.
+    pub fn just_pressed(keys: &ButtonInput<KeyCode>, gamepads: &[&Gamepad]) -> Option<Binding> {
+        if let Some(k) = keys.get_just_pressed().next() {
+            if !is_ctrl_key(*k) && ctrl_held(keys) {
+                return Some(Binding::Ctrl(*k));
+            }
+            return Some(Binding::Key(*k));
+        }
+        for g in gamepads {
+            if let Some(b) = g.get_just_pressed().next() {
+                return Some(Binding::Button(*b));
+            }
+            for axis in g.get_analog_axes() {
+                let (GamepadInput::Axis(a), Some(v)) = (axis, g.get(*axis)) else {
+                    continue;
+                };
+                if v.abs() > AXIS_THRESHOLD {
+                    let dir = if v > 0.0 {
+                        AxisDirection::Positive
+                    } else {
+                        AxisDirection::Negative
+                    };
+                    return Some(Binding::Axis(*a, dir));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl std::fmt::Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Binding::Key(k) => write!(f, "{:?}", k),
+            Binding::Ctrl(k) => write!(f, "Ctrl+{:?}", k),
+            Binding::Button(b) => write!(f, "Pad {:?}", b),
+            Binding::Axis(a, AxisDirection::Positive) => write!(f, "Pad {:?}+", a),
+            Binding::Axis(a, AxisDirection::Negative) => write!(f, "Pad {:?}-", a),
+        }
+    }
+}
+
+/// Which keys and buttons trigger each action. Actions missing from a
+/// settings file keep their default bindings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "BTreeMap<InputAction, Vec<Binding>>")]
+pub struct Keybindings(BTreeMap<InputAction, Vec<Binding>>);
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        use Binding::*;
+        use GamepadButton as Pad;
+
+        let bindings = |action| match action {
+            InputAction::SimFaster => vec![Key(KeyCode::Period), Button(Pad::RightTrigger)],
+            InputAction::SimSlower => vec![Key(KeyCode::Comma), Button(Pad::LeftTrigger)],
+            InputAction::SimRealTime => vec![Key(KeyCode::Slash)],
+            InputAction::SimPause => vec![Key(KeyCode::Space), Button(Pad::Start)],
+            InputAction::Delete => vec![Key(KeyCode::Delete)],
+            InputAction::CreateGroup => vec![Key(KeyCode::KeyG)],
+            InputAction::ClearMissions => vec![Key(KeyCode::KeyC)],
+            InputAction::CommitMission => vec![Key(KeyCode::Enter)],
+            InputAction::ZoomIn => vec![Key(KeyCode::Equal), Button(Pad::DPadUp)],
+            InputAction::ZoomOut => vec![Key(KeyCode::Minus), Button(Pad::DPadDown)],
+            InputAction::Reset => vec![Key(KeyCode::KeyR)],
+            InputAction::Escape => vec![Key(KeyCode::Escape), Button(Pad::East)],
+            InputAction::CursorMode => vec![Key(KeyCode::KeyV)],
+            InputAction::DrawMode => vec![Key(KeyCode::KeyM), Button(Pad::North)],
+            InputAction::CameraFrame => vec![Key(KeyCode::KeyF)],
+            InputAction::ToggleFullscreen => vec![Key(KeyCode::F11)],
+            InputAction::ToggleDebugConsole => vec![Key(KeyCode::Backquote), Button(Pad::Select)],
+            InputAction::Spawn => vec![Key(KeyCode::KeyK)],
+            InputAction::ThrustForward => vec![Key(KeyCode::ArrowUp), Button(Pad::RightTrigger2)],
+            InputAction::ThrustBackward => vec![Key(KeyCode::ArrowDown), Button(Pad::LeftTrigger2)],
+            InputAction::TurnLeft => vec![
+                Key(KeyCode::ArrowLeft),
+                Axis(GamepadAxis::RightStickX, AxisDirection::Negative),
+            ],
+            InputAction::TurnRight => vec![
+                Key(KeyCode::ArrowRight),
+                Axis(GamepadAxis::RightStickX, AxisDirection::Positive),
+            ],
+            InputAction::DockingMode => vec![Key(KeyCode::ControlLeft), Button(Pad::West)],
+            InputAction::Click => vec![Button(Pad::South)],
+            InputAction::Copy => vec![Ctrl(KeyCode::KeyC)],
+            InputAction::Cut => vec![Ctrl(KeyCode::KeyX)],
+            InputAction::Paste => vec![Ctrl(KeyCode::KeyV)],
+            InputAction::PanUp => vec![Key(KeyCode::KeyW)],
+            InputAction::PanDown => vec![Key(KeyCode::KeyS)],
+            InputAction::PanLeft => vec![Key(KeyCode::KeyA)],
+            InputAction::PanRight => vec![Key(KeyCode::KeyD)],
+            InputAction::NextControllerMode => vec![Key(KeyCode::KeyN)],
+            InputAction::EditorBuild => vec![Key(KeyCode::KeyB)],
+            InputAction::EditorPick => vec![Key(KeyCode::KeyQ)],
+            InputAction::EditorRotate => vec![Key(KeyCode::KeyR)],
+            InputAction::EditorAtmosphereUp => vec![Key(KeyCode::KeyO)],
+            InputAction::EditorAtmosphereDown => vec![Key(KeyCode::KeyL)],
+            InputAction::Undo => vec![Ctrl(KeyCode::KeyZ)],
+            InputAction::Redo => vec![Ctrl(KeyCode::KeyY)],
+        };
+
+        Keybindings(InputAction::all().map(|a| (a, bindings(a))).collect())
+    }
+}
+
+impl From<BTreeMap<InputAction, Vec<Binding>>> for Keybindings {
+    fn from(map: BTreeMap<InputAction, Vec<Binding>>) -> Self {
+        let mut kb = Keybindings::default();
+        kb.0.extend(map);
+        kb
+    }
+}
+
+impl Keybindings {
+    pub fn get(&self, action: InputAction) -> &[Binding] {
+        self.0.get(&action).map(|v| v.as_slice()).unwrap_or(&[])
+    }
+
+    /// Adds a binding to an action, doing nothing if it's already there.
+    pub fn bind(&mut self, action: InputAction, binding: Binding) {
+        let bindings = self.0.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: InputAction, binding: Binding) {
+        if let Some(bindings) = self.0.get_mut(&action) {
+            bindings.retain(|b| *b != binding);
+        }
+    }
+
+    pub fn reset(&mut self, action: InputAction) {
+        if let Some(bindings) = Keybindings::default().0.remove(&action) {
+            self.0.insert(action, bindings);
+        }
+    }
+
+    /// Bindings shared by more than one action which can be live in the
+    /// same scene, along with those actions.
+    pub fn conflicts(&self) -> Vec<(Binding, Vec<InputAction>)> {
+        let mut ret: Vec<(Binding, Vec<InputAction>)> = Vec::new();
+        for (action, bindings) in &self.0 {
+            for b in bindings {
+                match ret.iter_mut().find(|(other, _)| other == b) {
+                    Some((_, actions)) => actions.push(*action),
+                    None => ret.push((*b, vec![*action])),
+                }
+            }
+        }
+        ret.retain(|(_, actions)| {
+            actions.iter().enumerate().any(|(i, a)| {
+                actions[i + 1..]
+                    .iter()
+                    .any(|b| a.scope().overlaps(b.scope()))
+            })
+        });
+        ret
+    }
+
+    pub fn is_conflicted(&self, binding: Binding) -> bool {
+        self.conflicts().iter().any(|(b, _)| *b == binding)
+    }
+
+    /// Actions whose bindings are held. A key held with Ctrl for one
+    /// action doesn't also count as the plain key for another, so Ctrl+C
+    /// copies without doing whatever C does.
+    pub fn pressed(
+        &self,
+        keys: &ButtonInput<KeyCode>,
+        gamepads: &[&Gamepad],
+    ) -> HashSet<InputAction> {
+        let chorded: HashSet<KeyCode> = self
+            .0
+            .values()
+            .flatten()
+            .filter_map(|b| match b {
+                Binding::Ctrl(k) if b.is_pressed(keys, gamepads) => Some(*k),
+                _ => None,
+            })
+            .collect();
+
+        let is_pressed = |b: &Binding| match b {
+            Binding::Key(k) if chorded.contains(k) => false,
+            _ => b.is_pressed(keys, gamepads),
+        };
+
+        self.0
+            .iter()
+            .filter(|(_, bindings)| bindings.iter().any(is_pressed))
+            .map(|(a, _)| *a)
+            .collect()
+    }
+}
 
 pub fn keyboard_input(
     keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
     mut state: ResMut<GameState>,
     scroll: EventReader<MouseWheel>,
     mut events: EventWriter<InteractionEvent>,
 ) {
+    let gamepads: Vec<&Gamepad> = gamepads.iter().collect();
+
     state.input.set_buttons(keys.clone());
     state.input.set_scroll(scroll);
 
-    let ctrl = keys.pressed(KeyCode::ControlLeft);
-    let shift = keys.pressed(KeyCode::ShiftLeft);
-
-    for key in keys.get_just_pressed() {
-        let e = match (ctrl, shift, key) {
-            (_, _, KeyCode::Period) => InteractionEvent::SimFaster,
-            (_, _, KeyCode::Comma) => InteractionEvent::SimSlower,
-            (_, _, KeyCode::Slash) => InteractionEvent::SetSim(SimRate::RealTime),
-            (_, _, KeyCode::Delete) => InteractionEvent::Delete,
-            (_, _, KeyCode::KeyG) => InteractionEvent::CreateGroup,
-            (_, _, KeyCode::KeyC) => InteractionEvent::ClearMissions,
-            (_, _, KeyCode::Enter) => InteractionEvent::CommitMission,
-            (_, _, KeyCode::Minus) => InteractionEvent::ZoomOut,
-            (_, _, KeyCode::Equal) => InteractionEvent::ZoomIn,
-            (_, _, KeyCode::KeyR) => InteractionEvent::Reset,
-            (_, _, KeyCode::Space) => InteractionEvent::SimPause,
-            (_, _, KeyCode::Escape) => InteractionEvent::Escape,
-            (_, _, KeyCode::KeyV) => InteractionEvent::CursorMode,
-            (_, _, KeyCode::KeyM) => InteractionEvent::DrawMode,
-            (_, _, KeyCode::F11) => InteractionEvent::ToggleFullscreen,
-            (_, _, KeyCode::Backquote) => InteractionEvent::ToggleDebugConsole,
-            _ => continue,
-        };
+    if state.controls_context.is_capturing() {
+        if let Some(b) = Binding::just_pressed(&keys, &gamepads) {
+            state.on_binding_captured(b);
+        }
+        state.input.set_actions(HashSet::new());
+        return;
+    }
+
+    let actions = state.settings.keybindings.pressed(&keys, &gamepads);
+    state.input.set_actions(actions);
 
-        events.send(e);
+    for action in InputAction::all() {
+        if state.input.action_just_pressed(action) {
+            if let Some(e) = action.on_press() {
+                events.send(e);
+            }
+        }
     }
 
-    for key in keys.get_pressed() {
-        let e = match (keys.pressed(KeyCode::ControlLeft), key) {
-            (_, KeyCode::KeyK) => InteractionEvent::Spawn,
-            (_, KeyCode::ArrowUp) => InteractionEvent::Thrust(1),
-            (_, KeyCode::ArrowDown) => InteractionEvent::Thrust(-1),
-            (false, KeyCode::ArrowLeft) => InteractionEvent::TurnLeft,
-            (false, KeyCode::ArrowRight) => InteractionEvent::TurnRight,
-            (true, KeyCode::ArrowLeft) => InteractionEvent::StrafeLeft,
-            (true, KeyCode::ArrowRight) => InteractionEvent::StrafeRight,
-            _ => continue,
-        };
+    let docking = state.input.action_pressed(InputAction::DockingMode);
+    for action in InputAction::all() {
+        if state.input.action_pressed(action) {
+            if let Some(e) = action.while_held(docking) {
+                events.send(e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bindings_dont_conflict() {
+        let kb = Keybindings::default();
+        assert_eq!(kb.conflicts(), vec![]);
+        assert!(InputAction::all().all(|a| !kb.get(a).is_empty()));
+    }
+
+    #[test]
+    fn detects_conflicts() {
+        let mut kb = Keybindings::default();
+        kb.bind(InputAction::Spawn, Binding::Key(KeyCode::KeyG));
+        assert_eq!(
+            kb.conflicts(),
+            vec![(
+                Binding::Key(KeyCode::KeyG),
+                vec![InputAction::CreateGroup, InputAction::Spawn]
+            )]
+        );
+
+        kb.reset(InputAction::Spawn);
+        assert!(kb.conflicts().is_empty());
+    }
+
+    #[test]
+    fn scene_actions_share_keys() {
+        let mut kb = Keybindings::default();
+        assert_eq!(
+            kb.get(InputAction::EditorRotate),
+            kb.get(InputAction::Reset)
+        );
+        assert!(!kb.is_conflicted(Binding::Key(KeyCode::KeyR)));
+
+        kb.bind(InputAction::Spawn, Binding::Key(KeyCode::KeyR));
+        assert_eq!(
+            kb.conflicts(),
+            vec![(
+                Binding::Key(KeyCode::KeyR),
+                vec![
+                    InputAction::Reset,
+                    InputAction::Spawn,
+                    InputAction::EditorRotate
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn ctrl_bindings_shadow_plain_keys() {
+        let kb = Keybindings::default();
+        let mut keys = ButtonInput::<KeyCode>::default();
+        keys.press(KeyCode::KeyC);
+        assert!(kb.pressed(&keys, &[]).contains(&InputAction::ClearMissions));
+
+        keys.press(KeyCode::ControlLeft);
+        let actions = kb.pressed(&keys, &[]);
+        assert!(actions.contains(&InputAction::Copy));
+        assert!(!actions.contains(&InputAction::ClearMissions));
+    }
+
+    #[test]
+    fn missing_actions_keep_defaults() {
+        let yaml = "ThrustForward:\n- !Key KeyW\n";
+        let kb: Keybindings = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            kb.get(InputAction::ThrustForward),
+            &[Binding::Key(KeyCode::KeyW)]
+        );
+        assert_eq!(
+            kb.get(InputAction::TurnLeft),
+            Keybindings::default().get(InputAction::TurnLeft)
+        );
 
-        events.send(e);
+        let round_trip: Keybindings =
+            serde_yaml::from_str(&serde_yaml::to_string(&kb).unwrap()).unwrap();
+        assert_eq!(round_trip, kb);
     }
 }
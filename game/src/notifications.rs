@@ -1,3 +1,5 @@
+use enum_iterator::Sequence;
+use serde::{Deserialize, Serialize};
 use starling::prelude::*;
 
 #[derive(Debug, Clone)]
@@ -28,7 +30,9 @@ impl Notification {
             NotificationType::ManeuverComplete(_) => self.extra_time + Nanotime::secs(7),
             NotificationType::ManeuverFailed(_) => self.extra_time + Nanotime::secs(3),
             NotificationType::NotControllable(_) => self.extra_time + Nanotime::secs(5),
+            NotificationType::NoSignal(_) => self.extra_time + Nanotime::secs(5),
             NotificationType::OrbitChanged(_) => self.extra_time + Nanotime::secs(2),
+            NotificationType::ConjunctionWarning(..) => self.extra_time + Nanotime::secs(15),
             NotificationType::Notice(_) => Nanotime::secs(7),
         }
     }
@@ -54,9 +58,55 @@ pub enum NotificationType {
     ManeuverFailed(EntityId),
     OrbitChanged(EntityId),
     NotControllable(EntityId),
+    NoSignal(EntityId),
+    ConjunctionWarning(EntityId, EntityId),
     Notice(String),
 }
 
+/// Notification types without their details, for filtering.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Sequence, Serialize, Deserialize,
+)]
+pub enum NotificationKind {
+    OrbiterCrashed,
+    OrbiterEscaped,
+    NumericalError,
+    OrbiterDeleted,
+    ManeuverStarted,
+    ManeuverComplete,
+    ManeuverFailed,
+    OrbitChanged,
+    NotControllable,
+    NoSignal,
+    ConjunctionWarning,
+    Notice,
+}
+
+impl NotificationKind {
+    pub fn all() -> impl Iterator<Item = NotificationKind> {
+        enum_iterator::all::<NotificationKind>()
+    }
+}
+
+impl NotificationType {
+    pub fn kind(&self) -> NotificationKind {
+        match self {
+            Self::OrbiterCrashed(_) => NotificationKind::OrbiterCrashed,
+            Self::OrbiterEscaped(_) => NotificationKind::OrbiterEscaped,
+            Self::NumericalError(_) => NotificationKind::NumericalError,
+            Self::OrbiterDeleted(_) => NotificationKind::OrbiterDeleted,
+            Self::ManeuverStarted(_) => NotificationKind::ManeuverStarted,
+            Self::ManeuverComplete(_) => NotificationKind::ManeuverComplete,
+            Self::ManeuverFailed(_) => NotificationKind::ManeuverFailed,
+            Self::OrbitChanged(_) => NotificationKind::OrbitChanged,
+            Self::NotControllable(_) => NotificationKind::NotControllable,
+            Self::NoSignal(_) => NotificationKind::NoSignal,
+            Self::ConjunctionWarning(..) => NotificationKind::ConjunctionWarning,
+            Self::Notice(_) => NotificationKind::Notice,
+        }
+    }
+}
+
 impl std::fmt::Display for NotificationType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -87,6 +137,12 @@ impl std::fmt::Display for NotificationType {
             Self::NotControllable(id) => {
                 write!(f, "Orbiter {id} is not controllable")
             }
+            Self::NoSignal(id) => {
+                write!(f, "Orbiter {id} has no connection to a ground station")
+            }
+            Self::ConjunctionWarning(a, b) => {
+                write!(f, "Orbiters {a} and {b} are on course for a close approach")
+            }
             Self::Notice(str) => {
                 write!(f, "Notice: {str}")
             }
@@ -1,5 +1,7 @@
+use crate::keybindings::{Binding, InputAction};
 use crate::scenes::CursorMode;
 use crate::scenes::SceneType;
+use crate::settings::SettingsChange;
 use crate::sim_rate::SimRate;
 use starling::prelude::*;
 use std::path::PathBuf;
@@ -38,6 +40,8 @@ pub enum OnClick {
     PinObject(EntityId),
     UnpinObject(EntityId),
     SelectPart(String),
+    SelectAssembly(PathBuf),
+    SaveAssembly,
     ToggleLayer(PartLayer),
     LoadVehicle(PathBuf),
     DismissExitDialog,
@@ -51,12 +55,23 @@ pub enum OnClick {
     OpenNewCraft,
     WriteVehicleToImage,
     RotateCraft,
+    CycleSymmetry,
+    Undo,
+    Redo,
+    /// Undo or redo editor actions until this many are in effect
+    RevertTo(usize),
     NormalizeCraft,
-    ToggleThruster(usize),
+    ToggleThruster(PartId),
     ReloadGame,
     SetRecipe(PartId, RecipeListing),
     ClearContents(PartId),
     GoToSurface(EntityId),
     SetControllerPolicy(VehicleControlPolicy),
+    CaptureBinding(InputAction),
+    CancelCapture,
+    Unbind(InputAction, Binding),
+    /// Resets one action's bindings, or all of them
+    ResetBindings(Option<InputAction>),
+    ChangeSetting(SettingsChange),
     Nullopt,
 }
@@ -19,7 +19,8 @@ pub use crate::onclick::*;
 pub use crate::scenes::orbital::*;
 pub use crate::scenes::TextLabel;
 pub use crate::scenes::{
-    MainMenuContext, Render, SceneType, StaticSpriteDescriptor, TelescopeContext,
+    ControlsContext, MainMenuContext, Render, SceneType, SettingsContext, StaticSpriteDescriptor,
+    TelescopeContext,
 };
 pub use crate::settings::*;
 pub use crate::sim_rate::*;
@@ -0,0 +1,133 @@
+use crate::game::GameState;
+use crate::keybindings::InputAction;
+use crate::onclick::OnClick;
+use crate::scenes::Render;
+use crate::ui::*;
+use bevy::color::palettes::css::*;
+use bevy::prelude::*;
+use layout::layout::{Node, Size, TextJustify, Tree};
+
+/// Screen for changing which keys and gamepad buttons do what.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ControlsContext {
+    /// Action waiting for the next key or button press
+    capturing: Option<InputAction>,
+}
+
+impl ControlsContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn capturing(&self) -> Option<InputAction> {
+        self.capturing
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capturing.is_some()
+    }
+
+    pub fn start_capture(&mut self, action: InputAction) {
+        self.capturing = Some(action);
+    }
+
+    pub fn cancel_capture(&mut self) {
+        self.capturing = None;
+    }
+}
+
+const CONFLICT_COLOR: [f32; 4] = [0.6, 0.15, 0.15, 1.0];
+
+fn action_row(state: &GameState, action: InputAction, height: f32) -> Node<OnClick> {
+    let kb = &state.settings.keybindings;
+    let capturing = state.controls_context.capturing() == Some(action);
+
+    let label = Node::text(220, height, format!("{:?}", action))
+        .with_justify(TextJustify::Left)
+        .enabled(false);
+
+    let bindings = kb.get(action).iter().map(|b| {
+        let node = Node::button(b.to_string(), OnClick::Unbind(action, *b), 150, height);
+        if kb.is_conflicted(*b) {
+            node.with_color(CONFLICT_COLOR)
+        } else {
+            node
+        }
+    });
+
+    let add = if capturing {
+        Node::button("Press...", OnClick::CancelCapture, 150, height)
+    } else {
+        Node::button("+", OnClick::CaptureBinding(action), 50, height)
+    };
+
+    Node::row(height)
+        .with_color(UI_BACKGROUND_COLOR)
+        .with_child(label)
+        .with_children(bindings)
+        .with_child(add)
+        .with_child(Node::grow().invisible())
+        .with_child(Node::button(
+            "Reset",
+            OnClick::ResetBindings(Some(action)),
+            80,
+            height,
+        ))
+}
+
+impl Render for ControlsContext {
+    fn background_color(_state: &GameState) -> Srgba {
+        GRAY.with_luminance(0.1)
+    }
+
+    fn ui(state: &GameState) -> Option<Tree<OnClick>> {
+        let vb = state.input.screen_bounds;
+        if vb.span.x == 0.0 || vb.span.y == 0.0 {
+            return None;
+        }
+
+        let height = state.settings.ui_button_height * 0.8;
+
+        let hint = match state.controls_context.capturing() {
+            Some(action) => format!(
+                "Press a key or gamepad button for {:?}, or click Press... to cancel",
+                action
+            ),
+            None => "Click a binding to remove it, or + to add one".to_string(),
+        };
+
+        let conflicts = state.settings.keybindings.conflicts().len();
+        let status = if conflicts > 0 {
+            Node::text(
+                Size::Grow,
+                height,
+                format!("{} conflicting bindings", conflicts),
+            )
+            .with_color(CONFLICT_COLOR)
+            .enabled(false)
+        } else {
+            Node::text(Size::Grow, height, hint).enabled(false)
+        };
+
+        let list = Node::structural(900, Size::Fit)
+            .down()
+            .invisible()
+            .with_child(status)
+            .with_children(InputAction::all().map(|a| action_row(state, a, height)))
+            .with_child(Node::button(
+                "Reset All",
+                OnClick::ResetBindings(None),
+                Size::Grow,
+                height,
+            ));
+
+        let layout = Node::new(vb.span.x, vb.span.y)
+            .tight()
+            .invisible()
+            .down()
+            .with_child(top_bar(state))
+            .with_child(list);
+
+        Some(Tree::new().with_layout(layout, Vec2::ZERO))
+    }
+}
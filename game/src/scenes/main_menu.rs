@@ -51,17 +51,21 @@ impl Render for MainMenuContext {
     }
 
     fn ui(state: &GameState) -> Option<Tree<OnClick>> {
-        let buttons = ["Load Save File", "Settings", "Exit"];
+        let buttons = [
+            ("Load Save File", OnClick::Nullopt),
+            ("Settings", OnClick::GoToScene(SceneType::Settings)),
+            ("Exit", OnClick::Nullopt),
+        ];
         let button_color = [0.2, 0.2, 0.2, 0.7];
         let bg_color = [0.0, 0.0, 0.0, 0.0];
 
         let wrapper = Node::structural(250, Size::Fit)
             .down()
             .with_color(bg_color)
-            .with_children(buttons.iter().map(|s| {
+            .with_children(buttons.iter().map(|(s, onclick)| {
                 Node::button(
                     s.to_string(),
-                    OnClick::Nullopt,
+                    onclick.clone(),
                     Size::Grow,
                     state.settings.ui_button_height,
                 )
@@ -1,13 +1,17 @@
+pub mod controls;
 pub mod craft_editor;
 pub mod main_menu;
 pub mod orbital;
 pub mod render;
 pub mod scene;
+pub mod settings;
 pub mod telescope;
 
+pub use controls::ControlsContext;
 pub use craft_editor::*;
 pub use main_menu::MainMenuContext;
 pub use orbital::*;
 pub use render::*;
 pub use scene::SceneType;
+pub use settings::SettingsContext;
 pub use telescope::TelescopeContext;
@@ -4,7 +4,7 @@ use crate::game::GameState;
 use crate::input::{FrameId, InputState, MouseButt};
 use crate::onclick::OnClick;
 use crate::scenes::{Render, TextLabel};
-use crate::sounds::EnvironmentSounds;
+use crate::sounds::{EnvironmentSounds, SoundChannel};
 use crate::ui::*;
 use bevy::color::palettes::css::*;
 use bevy::prelude::*;
@@ -39,6 +39,20 @@ pub enum DrawMode {
     Constellations,
     Stability,
     Occlusion,
+    Sensors,
+    Comms,
+    Conjunctions,
+    ThreeBody,
+}
+
+/// Frame of reference the orbital camera is drawn in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Sequence)]
+pub enum CameraFrame {
+    #[default]
+    Inertial,
+    /// Turns along with the body the camera is centered on, or the body
+    /// which the followed vehicle is orbiting
+    BodyFixed,
 }
 
 #[allow(unused)]
@@ -53,6 +67,7 @@ pub struct OrbitalContext {
     pub show_orbits: ShowOrbitsState,
     pub show_animations: bool,
     pub draw_mode: DrawMode,
+    pub frame: CameraFrame,
     pub piloting: Option<EntityId>,
     pub hovered_entity: Option<EntityId>,
 }
@@ -73,6 +88,10 @@ impl CameraProjection for OrbitalContext {
     fn parent(&self) -> EntityId {
         self.camera.parent()
     }
+
+    fn rotation(&self) -> f64 {
+        self.camera.rotation()
+    }
 }
 
 pub const SPACECRAFT_HOVER_RADIUS: f64 = 30.0;
@@ -89,6 +108,7 @@ impl OrbitalContext {
             show_orbits: ShowOrbitsState::Focus,
             show_animations: true,
             draw_mode: DrawMode::Default,
+            frame: CameraFrame::Inertial,
             piloting: None,
             hovered_entity: None,
         }
@@ -186,6 +206,13 @@ impl OrbitalContext {
             }
         }
 
+        let rotation = match self.frame {
+            CameraFrame::Inertial => None,
+            CameraFrame::BodyFixed => frame_body(universe, self.camera.parent())
+                .map(|body| body.rotation_angle(universe.stamp())),
+        };
+        self.camera.set_rotation(rotation.unwrap_or(0.0));
+
         self.camera.on_game_tick();
 
         let mut track_list = self.selected.clone();
@@ -202,14 +229,6 @@ impl OrbitalContext {
     ) {
         self.camera.handle_input(input);
 
-        if input.just_pressed(KeyCode::KeyN) {
-            if let Some(id) = self.piloting {
-                if let Some(sv) = universe.surface_vehicles.get_mut(&id) {
-                    sv.controller.go_to_next_mode();
-                }
-            }
-        }
-
         if on_ui {
             return;
         }
@@ -229,10 +248,10 @@ impl OrbitalContext {
             } else {
                 if let Some(h) = self.hovered_entity {
                     self.piloting = Some(h);
-                    sounds.play_once("soft-pulse-higher.ogg", 0.3);
+                    sounds.play_once(SoundChannel::Interface, "soft-pulse-higher.ogg", 0.3);
                 } else {
                     self.piloting = None;
-                    sounds.play_once("soft-pulse.ogg", 0.3);
+                    sounds.play_once(SoundChannel::Interface, "soft-pulse.ogg", 0.3);
                 }
             }
         }
@@ -254,6 +273,16 @@ impl OrbitalContext {
     }
 }
 
+/// The body whose surface a camera centered on the given object would
+/// turn along with in a body-fixed frame.
+fn frame_body(universe: &Universe, id: EntityId) -> Option<Body> {
+    let planet = match universe.surface_vehicles.get(&id) {
+        Some(sv) => sv.parent(),
+        None => id,
+    };
+    universe.lup_planet(planet)?.body()
+}
+
 pub fn get_orbital_labels(state: &GameState) -> Vec<TextLabel> {
     let mut ret = Vec::new();
 
@@ -311,17 +340,66 @@ pub fn get_orbital_labels(state: &GameState) -> Vec<TextLabel> {
 
 pub fn date_info(state: &GameState) -> String {
     let date = state.universe.stamp().to_date();
+    let rate = state.universe_ticks_per_game_tick;
     format!(
-        "{}({}) {} (x{}/{} {} us)",
+        "{}{}({}) {} (x{}/{} {:.0}% of {} {} us)",
         if state.paused { "[PAUSED] " } else { "" },
-        if state.using_batch_mode { "B" } else { "S" },
+        match state.orbital_context.frame {
+            CameraFrame::Inertial => "",
+            CameraFrame::BodyFixed => "[BODY-FIXED] ",
+        },
+        match state.sim_mode {
+            SimMode::Batch => "B",
+            SimMode::PhysicsWarp => "W",
+            SimMode::Stepped => "S",
+        },
         date,
         state.actual_universe_ticks_per_game_tick,
-        state.universe_ticks_per_game_tick.as_ticks(),
+        rate.as_ticks(),
+        rate.achieved_fraction(state.actual_universe_ticks_per_game_tick) * 100.0,
+        rate.as_str(),
         state.exec_time.as_micros()
     )
 }
 
+fn three_body_labels(state: &GameState) -> Vec<TextLabel> {
+    let universe = &state.universe;
+    let ctx = &state.orbital_context;
+    let stamp = universe.stamp();
+    let piloted = state.piloting().and_then(|id| universe.three_body(id));
+    let mut ret = vec![];
+
+    for sys in ThreeBodySystem::all(&universe.planets, stamp) {
+        let origin = match universe.planets.lookup(sys.primary, stamp) {
+            Some((_, pv, _, _)) => pv.pos,
+            None => continue,
+        };
+        // note whether the piloted vehicle has the energy to pass each point
+        let jacobi = piloted
+            .filter(|(p, _, _)| p.secondary == sys.secondary)
+            .map(|(_, _, c)| c);
+        for (point, pos) in sys.lagrange_points() {
+            let text = match jacobi {
+                Some(c) if sys.is_reachable(pos, c) => format!("{point} (open)"),
+                Some(_) => format!("{point} (closed)"),
+                None => point.to_string(),
+            };
+            let p = ctx.w2c(origin + pos) + Vec2::Y * 20.0;
+            ret.push(TextLabel::new(text, p, 0.7));
+        }
+    }
+
+    if let Some((id, (_, _, c))) = state.piloting().zip(piloted) {
+        if let Some(pv) = universe.pv(id) {
+            let t = format!("C = {:0.4} km2/s2", c / 1E6);
+            let p = ctx.w2c(pv.pos) + Vec2::Y * 40.0;
+            ret.push(TextLabel::new(t, p, 0.7));
+        }
+    }
+
+    ret
+}
+
 fn text_labels(state: &GameState) -> Vec<TextLabel> {
     let mut text_labels: Vec<TextLabel> = get_orbital_labels(state);
 
@@ -334,6 +412,39 @@ fn text_labels(state: &GameState) -> Vec<TextLabel> {
         }
     }
 
+    if state.orbital_context.draw_mode == DrawMode::Conjunctions {
+        let universe = &state.universe;
+        for c in universe.conjunctions.conjunctions() {
+            let tca = universe
+                .predicted_pos(c.a, c.tca)
+                .zip(universe.predicted_pos(c.b, c.tca));
+            if let Some((p, q)) = tca {
+                let middle = state.orbital_context.w2c((p + q) / 2.0) + Vec2::Y * 20.0;
+                let t = format!("{:0.0} m in {}", c.miss_distance, c.tca - universe.stamp());
+                text_labels.push(TextLabel::new(t, middle, 0.8));
+            }
+        }
+    }
+
+    if let Some(id) = state.piloting() {
+        if let Some(impact) = state.universe.predict_impact(id) {
+            let stamp = state.universe.stamp();
+            if let Some((_, parent, _, _)) = state.universe.planets.lookup(impact.parent, stamp) {
+                let p = state.orbital_context.w2c(parent.pos + impact.pos) + Vec2::Y * 30.0;
+                let t = format!(
+                    "Impact in {} at {:0.1} m/s",
+                    impact.stamp - stamp,
+                    impact.speed()
+                );
+                text_labels.push(TextLabel::new(t, p, 0.8));
+            }
+        }
+    }
+
+    if state.orbital_context.draw_mode == DrawMode::ThreeBody {
+        text_labels.extend(three_body_labels(state));
+    }
+
     if let Some((c, a, b)) = state.protractor() {
         for (a, b) in [(c, Some(a)), (c, b)] {
             if let Some(b) = b {
@@ -364,6 +475,10 @@ impl Render for OrbitalContext {
             DrawMode::Constellations => GRAY.with_luminance(0.1),
             DrawMode::Stability => GRAY.with_luminance(0.13),
             DrawMode::Occlusion => GRAY.with_luminance(0.04),
+            DrawMode::Sensors => DARK_GREEN.with_luminance(0.03),
+            DrawMode::Comms => NAVY.with_luminance(0.03),
+            DrawMode::Conjunctions => MAROON.with_luminance(0.03),
+            DrawMode::ThreeBody => INDIGO.with_luminance(0.03),
         }
     }
 
@@ -5,6 +5,8 @@ pub enum SceneType {
     Orbital,
     Telescope,
     Editor,
+    Settings,
+    Controls,
     MainMenu,
 }
 
@@ -0,0 +1,168 @@
+use crate::game::GameState;
+use crate::notifications::NotificationKind;
+use crate::onclick::OnClick;
+use crate::scenes::{Render, SceneType};
+use crate::settings::SettingsChange;
+use crate::sim_rate::SimRate;
+use crate::sounds::SoundChannel;
+use crate::ui::*;
+use bevy::color::palettes::css::*;
+use bevy::prelude::*;
+use layout::layout::{Node, Size, TextJustify, Tree};
+
+/// Screen for changing the contents of settings.yaml, which takes
+/// effect straight away.
+pub struct SettingsContext;
+
+const LABEL_WIDTH: f32 = 250.0;
+
+fn label(s: impl Into<String>, height: f32) -> Node<OnClick> {
+    Node::text(LABEL_WIDTH, height, s)
+        .with_justify(TextJustify::Left)
+        .enabled(false)
+}
+
+/// Row showing a value with buttons to step it down and up.
+fn stepper_row(
+    name: &str,
+    value: String,
+    change: impl Fn(f32) -> SettingsChange,
+    step: f32,
+    height: f32,
+) -> Node<OnClick> {
+    Node::row(height)
+        .with_child(label(name, height))
+        .with_child(Node::text(Size::Grow, height, value).enabled(false))
+        .with_child(left_right_arrows(
+            120,
+            height,
+            OnClick::ChangeSetting(change(-step)),
+            OnClick::ChangeSetting(change(step)),
+        ))
+}
+
+fn toggle_row(name: &str, on: bool, change: SettingsChange, height: f32) -> Node<OnClick> {
+    let s = if on { "On" } else { "Off" };
+    Node::row(height)
+        .with_child(label(name, height))
+        .with_child(Node::button(
+            s,
+            OnClick::ChangeSetting(change),
+            Size::Grow,
+            height,
+        ))
+}
+
+fn volume_row(state: &GameState, channel: Option<SoundChannel>, height: f32) -> Node<OnClick> {
+    let s = &state.settings;
+    let (name, v) = match channel {
+        None => ("Master volume", s.master_volume),
+        Some(SoundChannel::Ambient) => ("Ambient volume", s.ambient_volume),
+        Some(SoundChannel::Interface) => ("Interface volume", s.interface_volume),
+    };
+    stepper_row(
+        name,
+        format!("{:0.0}%", v * 100.0),
+        move |d| SettingsChange::Volume(channel, d),
+        0.1,
+        height,
+    )
+}
+
+impl Render for SettingsContext {
+    fn background_color(_state: &GameState) -> Srgba {
+        GRAY.with_luminance(0.1)
+    }
+
+    fn ui(state: &GameState) -> Option<Tree<OnClick>> {
+        let vb = state.input.screen_bounds;
+        if vb.span.x == 0.0 || vb.span.y == 0.0 {
+            return None;
+        }
+
+        let s = &state.settings;
+        let height = s.ui_button_height;
+
+        let autosave = match s.autosave_minutes {
+            0 => "Off".to_string(),
+            n => format!("Every {} min", n),
+        };
+
+        let sim_rates = Node::row(height)
+            .with_child(label("Starting sim rate", height))
+            .with_children(SimRate::all().map(|r| {
+                let onclick = OnClick::ChangeSetting(SettingsChange::DefaultSimRate(r));
+                Node::button(r.as_str(), onclick, Size::Grow, height)
+                    .enabled(s.default_sim_rate != r)
+            }));
+
+        let notifications = Node::structural(Size::Grow, Size::Fit)
+            .down()
+            .with_child(label("Notifications", height))
+            .with_children(NotificationKind::all().map(|kind| {
+                toggle_row(
+                    &format!("  {:?}", kind),
+                    s.is_shown(kind),
+                    SettingsChange::ToggleNotification(kind),
+                    height * 0.8,
+                )
+            }));
+
+        let list = Node::structural(700, Size::Fit)
+            .down()
+            .with_color(UI_BACKGROUND_COLOR)
+            .with_child(stepper_row(
+                "UI scale",
+                format!("{:0.0}", s.ui_button_height),
+                SettingsChange::UiScale,
+                1.0,
+                height,
+            ))
+            .with_child(stepper_row(
+                "Gamepad cursor speed",
+                format!("{:0.0}", s.controller_cursor_speed),
+                SettingsChange::CursorSpeed,
+                1.0,
+                height,
+            ))
+            .with_child(volume_row(state, None, height))
+            .with_child(volume_row(state, Some(SoundChannel::Ambient), height))
+            .with_child(volume_row(state, Some(SoundChannel::Interface), height))
+            .with_child(toggle_row(
+                "Bloom",
+                s.bloom,
+                SettingsChange::ToggleBloom,
+                height,
+            ))
+            .with_child(toggle_row(
+                "Draw transform tree",
+                s.draw_transform_tree,
+                SettingsChange::ToggleTransformTree,
+                height,
+            ))
+            .with_child(sim_rates)
+            .with_child(stepper_row(
+                "Autosave craft",
+                autosave,
+                |d| SettingsChange::AutosaveMinutes(d as i32),
+                5.0,
+                height,
+            ))
+            .with_child(notifications)
+            .with_child(Node::button(
+                "Controls",
+                OnClick::GoToScene(SceneType::Controls),
+                Size::Grow,
+                height,
+            ));
+
+        let layout = Node::new(vb.span.x, vb.span.y)
+            .tight()
+            .invisible()
+            .down()
+            .with_child(top_bar(state))
+            .with_child(list);
+
+        Some(Tree::new().with_layout(layout, Vec2::ZERO))
+    }
+}
@@ -1,12 +1,31 @@
+use crate::keybindings::Keybindings;
+use crate::notifications::NotificationKind;
+use crate::sim_rate::SimRate;
+use crate::sounds::SoundChannel;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 use std::error::Error;
 use std::path::Path;
 
-#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+/// Anything missing from a settings file takes its default value.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
 pub struct Settings {
     pub ui_button_height: f32,
     pub controller_cursor_speed: f32,
     pub draw_transform_tree: bool,
+    /// Scales every channel's volume
+    pub master_volume: f32,
+    pub ambient_volume: f32,
+    pub interface_volume: f32,
+    pub bloom: bool,
+    /// Sim rate the game starts at
+    pub default_sim_rate: SimRate,
+    /// Minutes between saves of the craft open in the editor, if it has a
+    /// file already. Zero turns autosave off.
+    pub autosave_minutes: u32,
+    pub hidden_notifications: BTreeSet<NotificationKind>,
+    pub keybindings: Keybindings,
 }
 
 impl Default for Settings {
@@ -15,6 +34,77 @@ impl Default for Settings {
             ui_button_height: 32.0,
             controller_cursor_speed: 6.0,
             draw_transform_tree: false,
+            master_volume: 1.0,
+            ambient_volume: 1.0,
+            interface_volume: 1.0,
+            bloom: true,
+            default_sim_rate: SimRate::RealTime,
+            autosave_minutes: 0,
+            hidden_notifications: BTreeSet::new(),
+            keybindings: Keybindings::default(),
+        }
+    }
+}
+
+/// A single adjustment made from the settings screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SettingsChange {
+    UiScale(f32),
+    CursorSpeed(f32),
+    /// Changes a channel's volume, or the master volume if there's no channel
+    Volume(Option<SoundChannel>, f32),
+    ToggleBloom,
+    ToggleTransformTree,
+    DefaultSimRate(SimRate),
+    AutosaveMinutes(i32),
+    ToggleNotification(NotificationKind),
+}
+
+impl Settings {
+    /// Volume sounds on the given channel play at, relative to how loud
+    /// they asked to be.
+    pub fn volume(&self, channel: SoundChannel) -> f32 {
+        let v = match channel {
+            SoundChannel::Ambient => self.ambient_volume,
+            SoundChannel::Interface => self.interface_volume,
+        };
+        self.master_volume * v
+    }
+
+    pub fn is_shown(&self, kind: NotificationKind) -> bool {
+        !self.hidden_notifications.contains(&kind)
+    }
+
+    pub fn apply(&mut self, change: SettingsChange) {
+        match change {
+            SettingsChange::UiScale(d) => {
+                self.ui_button_height = (self.ui_button_height + d).clamp(3.0, 40.0)
+            }
+            SettingsChange::CursorSpeed(d) => {
+                self.controller_cursor_speed = (self.controller_cursor_speed + d).clamp(1.0, 20.0)
+            }
+            SettingsChange::Volume(channel, d) => {
+                let v = match channel {
+                    None => &mut self.master_volume,
+                    Some(SoundChannel::Ambient) => &mut self.ambient_volume,
+                    Some(SoundChannel::Interface) => &mut self.interface_volume,
+                };
+                // rounded so repeated steps of 0.1 land on round numbers
+                *v = ((*v + d) * 100.0).round().clamp(0.0, 100.0) / 100.0;
+            }
+            SettingsChange::ToggleBloom => self.bloom = !self.bloom,
+            SettingsChange::ToggleTransformTree => {
+                self.draw_transform_tree = !self.draw_transform_tree
+            }
+            SettingsChange::DefaultSimRate(r) => self.default_sim_rate = r,
+            SettingsChange::AutosaveMinutes(d) => {
+                self.autosave_minutes = (self.autosave_minutes as i32 + d).clamp(0, 120) as u32
+            }
+            SettingsChange::ToggleNotification(kind) => {
+                if !self.hidden_notifications.remove(&kind) {
+                    self.hidden_notifications.insert(kind);
+                }
+            }
         }
     }
 }
@@ -28,3 +118,41 @@ pub fn write_settings_to_file(path: &Path, settings: &Settings) -> Result<(), Bo
     let s = serde_yaml::to_string(settings)?;
     Ok(std::fs::write(path, s)?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn old_settings_files_still_load() {
+        let s: Settings =
+            serde_yaml::from_str("ui_button_height: 20.0\ncontroller_cursor_speed: 6.0\n").unwrap();
+        assert_eq!(s.ui_button_height, 20.0);
+        assert!(s.bloom);
+        assert_eq!(s.default_sim_rate, SimRate::RealTime);
+    }
+
+    #[test]
+    fn changes_stay_in_range() {
+        let mut s = Settings::default();
+        for _ in 0..15 {
+            s.apply(SettingsChange::Volume(Some(SoundChannel::Ambient), -0.1));
+            s.apply(SettingsChange::AutosaveMinutes(-5));
+        }
+        assert_eq!(s.ambient_volume, 0.0);
+        assert_eq!(s.autosave_minutes, 0);
+        assert_eq!(s.volume(SoundChannel::Interface), 1.0);
+
+        s.apply(SettingsChange::Volume(None, -0.3));
+        assert_eq!(s.volume(SoundChannel::Interface), 0.7);
+
+        s.apply(SettingsChange::ToggleNotification(
+            NotificationKind::OrbitChanged,
+        ));
+        assert!(!s.is_shown(NotificationKind::OrbitChanged));
+        s.apply(SettingsChange::ToggleNotification(
+            NotificationKind::OrbitChanged,
+        ));
+        assert!(s.is_shown(NotificationKind::OrbitChanged));
+    }
+}
@@ -1,6 +1,7 @@
 use enum_iterator::{all, Sequence};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Sequence)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Sequence, Serialize, Deserialize)]
 pub enum SimRate {
     RealTime,
     ThreeSecondsPerSecond,
@@ -45,6 +46,12 @@ impl SimRate {
         }
     }
 
+    /// Fraction of this rate achieved by a game tick which only managed
+    /// to simulate the given number of universe ticks.
+    pub fn achieved_fraction(&self, actual_ticks: u32) -> f32 {
+        actual_ticks as f32 / self.as_ticks() as f32
+    }
+
     pub fn all() -> impl Iterator<Item = Self> {
         all::<Self>()
     }
@@ -1,14 +1,33 @@
 use crate::game::GameState;
 use bevy::audio::*;
 use bevy::prelude::*;
+use enum_iterator::Sequence;
+use serde::{Deserialize, Serialize};
+
+/// Groups of sounds whose volume can be set separately.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Sequence, Serialize, Deserialize,
+)]
+pub enum SoundChannel {
+    /// Background loops
+    Ambient,
+    /// Clicks and other feedback from the UI
+    Interface,
+}
+
+/// Channel a playing sound belongs to, and the volume it was asked to
+/// play at before the channel's volume was applied.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ChannelVolume(SoundChannel, f32);
 
 pub fn sound_system(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut state: ResMut<GameState>,
+    sinks: Query<(&ChannelVolume, &AudioSink)>,
 ) {
-    for (s, v, do_loop) in state.sounds.sounds() {
-        let handle = match std::fs::canonicalize(state.args.audio_dir().join(s)) {
+    for (channel, s, v, do_loop) in state.sounds.sounds() {
+        let handle = match std::fs::canonicalize(state.args.audio_dir().join(&s)) {
             Ok(path) => asset_server.load(path),
             Err(e) => {
                 error!("Failed to play sound: {}", e);
@@ -16,16 +35,25 @@ pub fn sound_system(
             }
         };
         let player = AudioPlayer::new(handle);
-        let mut settings = PlaybackSettings::default().with_volume(Volume::new(v));
+        let volume = v * state.settings.volume(channel);
+        let mut settings = PlaybackSettings::default().with_volume(Volume::new(volume));
         if do_loop {
             settings.mode = PlaybackMode::Loop;
         }
-        commands.spawn((player, settings));
+        commands.spawn((player, settings, ChannelVolume(channel, v)));
+    }
+
+    // keeps sounds which are already playing in line with the settings
+    for (ChannelVolume(channel, v), sink) in &sinks {
+        let volume = v * state.settings.volume(*channel);
+        if sink.volume() != volume {
+            sink.set_volume(volume);
+        }
     }
 }
 
 pub struct EnvironmentSounds {
-    sounds: Vec<(String, f32, bool)>,
+    sounds: Vec<(SoundChannel, String, f32, bool)>,
 }
 
 impl EnvironmentSounds {
@@ -33,15 +61,15 @@ impl EnvironmentSounds {
         Self { sounds: Vec::new() }
     }
 
-    pub fn play_loop(&mut self, name: impl Into<String>, volume: f32) {
-        self.sounds.push((name.into(), volume, true));
+    pub fn play_loop(&mut self, channel: SoundChannel, name: impl Into<String>, volume: f32) {
+        self.sounds.push((channel, name.into(), volume, true));
     }
 
-    pub fn play_once(&mut self, name: impl Into<String>, volume: f32) {
-        self.sounds.push((name.into(), volume, false));
+    pub fn play_once(&mut self, channel: SoundChannel, name: impl Into<String>, volume: f32) {
+        self.sounds.push((channel, name.into(), volume, false));
     }
 
-    pub fn sounds(&mut self) -> Vec<(String, f32, bool)> {
+    pub fn sounds(&mut self) -> Vec<(SoundChannel, String, f32, bool)> {
         let r = self.sounds.clone();
         self.sounds.clear();
         r
@@ -39,6 +39,7 @@ pub enum InteractionEvent {
     CreateGroup,
     CursorMode,
     DrawMode,
+    CameraFrame,
     RedrawGui,
     ToggleFullscreen,
 
@@ -71,6 +72,11 @@ impl Plugin for UiPlugin {
 }
 
 fn set_bloom(state: Res<GameState>, mut bloom: Single<&mut Bloom>) {
+    if !state.settings.bloom {
+        bloom.intensity = 0.0;
+        return;
+    }
+
     bloom.intensity = match state.scene {
         SceneType::MainMenu => 0.6,
         SceneType::Orbital => match state.orbital_context.draw_mode {
@@ -492,6 +498,8 @@ pub fn layout(state: &GameState) -> Tree<OnClick> {
         SceneType::Telescope => TelescopeContext::ui(state),
         SceneType::Orbital => OrbitalContext::ui(state),
         SceneType::Editor => EditorContext::ui(state),
+        SceneType::Settings => SettingsContext::ui(state),
+        SceneType::Controls => ControlsContext::ui(state),
     }
     .unwrap_or(Tree::new())
 }
@@ -56,10 +56,122 @@ fn criterion_benchmark(c: &mut Criterion) {
         })
     });
 
+    let hyperbolic = SparseOrbit::from_pv(
+        PV::from_f64((1500.0, 0.0), (0.0, 150.0)),
+        Body::with_mass(1.0, 1000.0, 100000.0),
+        Nanotime::zero(),
+    )
+    .unwrap();
+
+    let eccentric = SparseOrbit::new(
+        40000.0,
+        500.0,
+        0.3,
+        Body::with_mass(1.0, 1000.0, 100000.0),
+        Nanotime::zero(),
+        false,
+    )
+    .unwrap();
+
+    s.bench_function("pv_universal_hyperbolic", |b| {
+        b.iter(|| {
+            let t = black_box(Nanotime::secs_f32(32.5));
+            hyperbolic.pv_universal(t).unwrap();
+        })
+    });
+
+    s.bench_function("pv_lut_hyperbolic", |b| {
+        b.iter(|| {
+            let t = black_box(Nanotime::secs_f32(32.5));
+            hyperbolic.pv_lut(t).unwrap();
+        })
+    });
+
+    s.bench_function("pv_universal_eccentric", |b| {
+        b.iter(|| {
+            let t = black_box(Nanotime::secs_f32(32.5));
+            eccentric.pv_universal(t).unwrap();
+        })
+    });
+
+    s.bench_function("pv_lut_eccentric", |b| {
+        b.iter(|| {
+            let t = black_box(Nanotime::secs_f32(32.5));
+            eccentric.pv_lut(t).unwrap();
+        })
+    });
+
+    s.bench_function("eval_lut_hyperbolic", |b| {
+        lookup_ta_from_ma(0.0, 2.0);
+        b.iter(|| {
+            let ma = black_box(-3.7);
+            let ecc = black_box(1.8);
+            lookup_ta_from_ma(ma, ecc);
+        })
+    });
+
     s.finish();
 
     // g.finish();
 }
 
-criterion_group!(benches, criterion_benchmark);
+fn fleet_universe(count: usize) -> Universe {
+    let mut universe = Universe::new(PlanetarySystem::new(EntityId(0), "fleet", Body::LUNA));
+    let r = Body::LUNA.radius * 2.0;
+    let v = (Body::LUNA.mu / r).sqrt();
+    for i in 0..count {
+        let thruster = ThrusterModel::main_thruster(40_000.0, 3000.0);
+        let mut vehicle = Vehicle::from_parts(
+            "burner".into(),
+            "burner".into(),
+            vec![(
+                IVec2::ZERO,
+                Rotation::East,
+                PartPrototype::Thruster(thruster),
+            )],
+            Default::default(),
+        );
+        vehicle.build_all();
+        let id = universe
+            .add_surface_vehicle(EntityId(0), vehicle, 0.0, 1000.0)
+            .unwrap();
+        let angle = i as f64 * 2.0 * PI_64 / count as f64;
+        let sv = universe.surface_vehicles.get_mut(&id).unwrap();
+        sv.body = RigidBody {
+            pv: PV::from_f64(
+                rotate_f64(DVec2::X * r, angle),
+                rotate_f64(DVec2::Y * v, angle),
+            ),
+            angle: angle + PI_64 / 2.0,
+            angular_velocity: 0.0,
+        };
+        sv.controller.set_policy(VehicleControlPolicy::BurnPrograde);
+    }
+    universe
+}
+
+fn fleet_benchmark(c: &mut Criterion) {
+    let mut g = c.benchmark_group("Fleet");
+
+    g.measurement_time(std::time::Duration::from_secs(3));
+
+    for count in [100, 300, 600] {
+        let mut universe = fleet_universe(count);
+        let signals = ControlSignals::new();
+        g.bench_function(format!("thrusting_{count}"), |b| {
+            b.iter(|| universe.on_sim_tick(black_box(&signals)))
+        });
+    }
+
+    let mut universe = fleet_universe(3000);
+    universe.update_spatial_index();
+    let cursor = DVec2::new(Body::LUNA.radius * 2.0, 1000.0);
+    g.bench_function("nearest_orbiter_3000", |b| {
+        b.iter(|| nearest_orbiter_or_planet(&universe, black_box(cursor), 5000.0))
+    });
+
+    g.finish();
+}
+
+criterion_group!(benches, criterion_benchmark, fleet_benchmark);
 criterion_main!(benches);
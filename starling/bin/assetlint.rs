@@ -0,0 +1,35 @@
+use clap::Parser;
+use starling::prelude::*;
+use std::path::PathBuf;
+
+/// Checks part definitions and vehicle files for mistakes
+#[derive(Parser, Debug, Default, Clone)]
+#[command(version, about, long_about = None)]
+pub struct Args {
+    /// Folder containing part definitions
+    #[arg(long, short)]
+    pub parts_dir: PathBuf,
+
+    /// Folder containing .vehicle files
+    #[arg(long, short)]
+    pub vehicles_dir: PathBuf,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let (parts, mut issues) = lint_parts_dir(&args.parts_dir);
+    issues.extend(lint_vehicles_dir(&args.vehicles_dir, &parts));
+
+    for issue in &issues {
+        eprintln!("{}", issue);
+    }
+
+    if !issues.is_empty() {
+        return Err(format!("{} problems found", issues.len()).into());
+    }
+
+    println!("{} parts OK", parts.len());
+
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use clap::Parser;
+use starling::prelude::*;
+
+/// Reports how far N-body propagation diverges from patched conics
+#[derive(Parser, Debug, Clone)]
+#[command(version, about, long_about = None)]
+pub struct Args {
+    /// Name of the body the test vehicle orbits
+    #[arg(long, short, default_value = "Earth")]
+    pub body: String,
+
+    /// Altitude of the test vehicle's circular orbit, in km
+    #[arg(long, short, default_value = "20000")]
+    pub altitude: f64,
+
+    /// How long to propagate for, in days
+    #[arg(long, short, default_value = "30")]
+    pub days: i64,
+
+    /// Time between reported samples, in hours
+    #[arg(long, short('i'), default_value = "24")]
+    pub interval: i64,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let planets = default_example();
+    let parent = planets
+        .planet_ids()
+        .into_iter()
+        .find(|id| {
+            planets
+                .lookup(*id, Nanotime::zero())
+                .map(|(_, _, _, sys)| sys.name.eq_ignore_ascii_case(&args.body))
+                .unwrap_or(false)
+        })
+        .ok_or("No such body")?;
+
+    let (body, _, _, _) = planets
+        .lookup(parent, Nanotime::zero())
+        .ok_or("No such body")?;
+    let orbit = SparseOrbit::circular(
+        body.radius + args.altitude * 1000.0,
+        body,
+        Nanotime::zero(),
+        false,
+    );
+
+    let samples = patched_conics_divergence(
+        &planets,
+        GlobalOrbit(parent, orbit),
+        Nanotime::zero(),
+        Nanotime::days(args.days),
+        Nanotime::hours(args.interval),
+    )
+    .ok_or("Failed to propagate")?;
+
+    for s in samples {
+        let vehicle = match s.vehicle {
+            Some(d) => format!("{:>14.1} km", d / 1000.0),
+            None => format!("{:>17}", "escaped"),
+        };
+        let planets: Vec<_> = s
+            .planets
+            .iter()
+            .map(|(id, d)| format!("{id}: {:.1} km", d / 1000.0))
+            .collect();
+        println!(
+            "{} vehicle {vehicle}  {}",
+            s.stamp.to_date(),
+            planets.join("  ")
+        );
+    }
+
+    Ok(())
+}
@@ -29,6 +29,10 @@ pub struct Args {
     /// Multiplier to scale down by
     #[arg(long, short('x'), default_value = "0")]
     pub scale: f32,
+
+    /// Fail without writing an image if the design has errors
+    #[arg(long)]
+    pub strict: bool,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -40,6 +44,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let vehicle = load_vehicle(&args.ship_path, String::new(), &parts)?;
 
+    let issues = vehicle.validate();
+    for issue in &issues {
+        eprintln!("{:?}: {}", issue.severity(), issue);
+    }
+
+    if args.strict && issues.iter().any(|i| i.severity() == Severity::Error) {
+        return Err("Vehicle design has errors".into());
+    }
+
     let mut img =
         generate_image(&vehicle, &args.parts_dir, args.schematic).ok_or("Empty vehicle")?;
 
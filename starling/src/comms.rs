@@ -0,0 +1,298 @@
+use crate::prelude::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Height of ground station antennas above the surface, in meters. Keeps
+/// the station itself from being occluded by its own body.
+const GROUND_STATION_ALTITUDE: f64 = 100.0;
+
+/// Range of the ground stations which are placed by default on every body.
+pub const DEFAULT_GROUND_STATION_RANGE: f64 = 1_000_000_000.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct GroundStation {
+    pub planet_id: EntityId,
+    /// Angle of the station around the body, in radians
+    pub angle: f64,
+    pub range: f64,
+}
+
+impl GroundStation {
+    pub fn new(planet_id: EntityId, angle: f64, range: f64) -> Self {
+        Self {
+            planet_id,
+            angle,
+            range,
+        }
+    }
+
+    /// Position of the station relative to the center of its body, which
+    /// turns with the body's rotation.
+    pub fn local_position(&self, body: &Body, stamp: Nanotime) -> DVec2 {
+        let fixed = rotate_f64(
+            DVec2::X * (body.radius + GROUND_STATION_ALTITUDE),
+            self.angle,
+        );
+        body.fixed_to_inertial(fixed, stamp)
+    }
+
+    pub fn position(&self, planets: &PlanetarySystem, stamp: Nanotime) -> Option<DVec2> {
+        let (body, pv, _, _) = planets.lookup(self.planet_id, stamp)?;
+        Some(pv.pos + self.local_position(&body, stamp))
+    }
+}
+
+/// Three evenly spaced ground stations on every body in the system.
+pub fn default_ground_stations(planets: &PlanetarySystem) -> Vec<GroundStation> {
+    planets
+        .planet_ids()
+        .into_iter()
+        .flat_map(|id| {
+            (0..3).map(move |i| {
+                GroundStation::new(
+                    id,
+                    i as f64 * 2.0 * PI_64 / 3.0,
+                    DEFAULT_GROUND_STATION_RANGE,
+                )
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CommsNode {
+    GroundStation(usize),
+    Vehicle(EntityId),
+}
+
+struct NodeInfo {
+    node: CommsNode,
+    pos: DVec2,
+    range: f64,
+    /// Center of the body a ground station sits on
+    surface_of: Option<DVec2>,
+}
+
+/// Connectivity between vehicles and ground stations, recomputed every
+/// tick. Each connected vehicle knows the next hop on its shortest route
+/// back to a ground station.
+#[derive(Debug, Clone, Default)]
+pub struct CommsNetwork {
+    links: Vec<(CommsNode, CommsNode)>,
+    next_hop: HashMap<CommsNode, CommsNode>,
+    connected: HashSet<EntityId>,
+}
+
+fn is_above_horizon(station: DVec2, center: DVec2, other: DVec2) -> bool {
+    (other - station).dot(station - center) > 0.0
+}
+
+fn has_line_of_sight(a: &NodeInfo, b: &NodeInfo, bodies: &[(PV, Body)]) -> bool {
+    for (n, m) in [(a, b), (b, a)] {
+        if let Some(center) = n.surface_of {
+            if !is_above_horizon(n.pos, center, m.pos) {
+                return false;
+            }
+        }
+    }
+
+    // positions are taken relative to one endpoint before narrowing
+    // to f32, to keep precision at interplanetary distances
+    !bodies.iter().any(|(pv, body)| {
+        if a.surface_of == Some(pv.pos) || b.surface_of == Some(pv.pos) {
+            return false;
+        }
+        is_occluded(
+            Vec2::ZERO,
+            aabb_stopgap_cast(b.pos - a.pos),
+            aabb_stopgap_cast(pv.pos - a.pos),
+            body.radius as f32,
+        )
+    })
+}
+
+impl CommsNetwork {
+    pub fn new(universe: &Universe) -> Self {
+        let stamp = universe.stamp();
+        let bodies: Vec<_> = universe.planets.bodies(stamp, None).collect();
+
+        let mut nodes: Vec<NodeInfo> = universe
+            .ground_stations
+            .iter()
+            .enumerate()
+            .filter_map(|(i, gs)| {
+                let (body, pv, _, _) = universe.planets.lookup(gs.planet_id, stamp)?;
+                Some(NodeInfo {
+                    node: CommsNode::GroundStation(i),
+                    pos: pv.pos + gs.local_position(&body, stamp),
+                    range: gs.range,
+                    surface_of: Some(pv.pos),
+                })
+            })
+            .collect();
+
+        for (id, sv) in &universe.surface_vehicles {
+            let range = match sv.vehicle.comms_range() {
+                Some(r) => r,
+                None => continue,
+            };
+            if let Some(pv) = universe.pv(*id) {
+                nodes.push(NodeInfo {
+                    node: CommsNode::Vehicle(*id),
+                    pos: pv.pos,
+                    range,
+                    surface_of: None,
+                });
+            }
+        }
+
+        let mut links = vec![];
+        let mut adjacency: HashMap<CommsNode, Vec<CommsNode>> = HashMap::new();
+
+        for (i, a) in nodes.iter().enumerate() {
+            for b in &nodes[i + 1..] {
+                if let (CommsNode::GroundStation(_), CommsNode::GroundStation(_)) = (a.node, b.node)
+                {
+                    continue;
+                }
+                if a.pos.distance(b.pos) > a.range.min(b.range) {
+                    continue;
+                }
+                if !has_line_of_sight(a, b, &bodies) {
+                    continue;
+                }
+                links.push((a.node, b.node));
+                adjacency.entry(a.node).or_default().push(b.node);
+                adjacency.entry(b.node).or_default().push(a.node);
+            }
+        }
+
+        // breadth-first search outward from every ground station at once
+        let mut next_hop = HashMap::new();
+        let mut visited: HashSet<CommsNode> = HashSet::new();
+        let mut queue = VecDeque::new();
+        for n in &nodes {
+            if let CommsNode::GroundStation(_) = n.node {
+                visited.insert(n.node);
+                queue.push_back(n.node);
+            }
+        }
+
+        while let Some(n) = queue.pop_front() {
+            for m in adjacency.get(&n).into_iter().flatten() {
+                if visited.insert(*m) {
+                    next_hop.insert(*m, n);
+                    queue.push_back(*m);
+                }
+            }
+        }
+
+        let connected = visited
+            .into_iter()
+            .filter_map(|n| match n {
+                CommsNode::Vehicle(id) => Some(id),
+                _ => None,
+            })
+            .collect();
+
+        Self {
+            links,
+            next_hop,
+            connected,
+        }
+    }
+
+    pub fn is_connected(&self, id: EntityId) -> bool {
+        self.connected.contains(&id)
+    }
+
+    pub fn links(&self) -> impl Iterator<Item = &(CommsNode, CommsNode)> + use<'_> {
+        self.links.iter()
+    }
+
+    /// Sequence of nodes from the given vehicle to the nearest ground
+    /// station, by hop count.
+    pub fn route(&self, id: EntityId) -> Option<Vec<CommsNode>> {
+        let mut n = CommsNode::Vehicle(id);
+        let mut ret = vec![n];
+        while let Some(next) = self.next_hop.get(&n) {
+            ret.push(*next);
+            n = *next;
+        }
+        matches!(n, CommsNode::GroundStation(_)).then_some(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relay(range: f64) -> Vehicle {
+        let antenna = Antenna::new(
+            "antenna".into(),
+            UVec2::new(4, 4),
+            Mass::kilograms(10),
+            range,
+        );
+        Vehicle::from_parts(
+            "relay".into(),
+            "relay".into(),
+            vec![(IVec2::ZERO, Rotation::East, PartPrototype::Antenna(antenna))],
+            Default::default(),
+        )
+    }
+
+    #[test]
+    fn relay_around_the_horizon() {
+        let mut universe = Universe::new(PlanetarySystem::new(EntityId(0), "test", Body::LUNA));
+        let r = Body::LUNA.radius;
+        universe.ground_stations = vec![GroundStation::new(EntityId(0), 0.0, 1E9)];
+
+        // directly above the station, and on the far side of the body
+        let near = place_vehicle(&mut universe, relay(1E8), DVec2::new(r * 2.0, 0.0));
+        let far = place_vehicle(&mut universe, relay(1E8), DVec2::new(-r * 2.0, 0.0));
+
+        let net = CommsNetwork::new(&universe);
+        assert!(net.is_connected(near));
+        assert!(!net.is_connected(far));
+
+        // a relay off to the side can see both
+        let side = place_vehicle(&mut universe, relay(1E8), DVec2::new(r * 2.0, r * 3.0));
+
+        let net = CommsNetwork::new(&universe);
+        assert!(net.is_connected(far));
+        assert!(net.is_connected(side));
+        assert_eq!(
+            net.route(far).unwrap(),
+            vec![
+                CommsNode::Vehicle(far),
+                CommsNode::Vehicle(side),
+                CommsNode::GroundStation(0)
+            ]
+        );
+    }
+
+    #[test]
+    fn no_antenna_no_comms() {
+        let mut universe = Universe::new(PlanetarySystem::new(EntityId(0), "test", Body::LUNA));
+        let r = Body::LUNA.radius;
+        universe.ground_stations = vec![GroundStation::new(EntityId(0), 0.0, 1E9)];
+        let dumb = Vehicle::from_parts("dumb".into(), "dumb".into(), vec![], Default::default());
+        let id = place_vehicle(&mut universe, dumb, DVec2::new(r * 2.0, 0.0));
+        assert!(!universe.comms().is_connected(id));
+        assert!(!universe.has_comms(id));
+        assert!(universe
+            .set_vehicle_policy(id, VehicleControlPolicy::Idle)
+            .is_err());
+    }
+
+    #[test]
+    fn connected_as_soon_as_added() {
+        let mut universe = Universe::new(PlanetarySystem::new(EntityId(0), "test", Body::LUNA));
+        universe.ground_stations = vec![GroundStation::new(EntityId(0), 0.0, 1E9)];
+        let planet = universe.planets.id;
+        let id = universe
+            .add_surface_vehicle(planet, relay(1E8), 0.0, 1000.0)
+            .unwrap();
+        assert!(universe.comms().is_connected(id));
+    }
+}
@@ -0,0 +1,304 @@
+use crate::prelude::*;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// A predicted close approach between two vehicles orbiting the same body.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Conjunction {
+    /// The lesser of the two vehicle ids
+    pub a: EntityId,
+    /// The greater of the two vehicle ids
+    pub b: EntityId,
+    pub parent: EntityId,
+    /// Time of closest approach
+    pub tca: Nanotime,
+    /// Distance between the two vehicles at the time of closest approach
+    pub miss_distance: f64,
+    /// Relative speed of the two vehicles at the time of closest approach
+    pub relative_speed: f64,
+}
+
+impl Conjunction {
+    pub fn involves(&self, id: EntityId) -> bool {
+        self.a == id || self.b == id
+    }
+
+    pub fn other(&self, id: EntityId) -> Option<EntityId> {
+        if self.a == id {
+            Some(self.b)
+        } else if self.b == id {
+            Some(self.a)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether two orbits' ranges of radii come within some distance of each
+/// other. If they don't, neither can the orbiters on them.
+fn shells_overlap(a: &SparseOrbit, b: &SparseOrbit, distance: f64) -> bool {
+    let apoapsis = |o: &SparseOrbit| {
+        if o.ecc() >= 1.0 {
+            f64::INFINITY
+        } else {
+            o.apoapsis_r()
+        }
+    };
+    a.periapsis_r() - apoapsis(b) <= distance && b.periapsis_r() - apoapsis(a) <= distance
+}
+
+/// Periodically predicts close approaches between every pair of vehicles
+/// under the same parent body, and raises a warning the first time a pair
+/// is predicted to pass within the warning distance of each other.
+#[derive(Debug, Clone)]
+pub struct ConjunctionScreener {
+    /// How far into the future to look for close approaches
+    pub horizon: Nanotime,
+    /// How often to repeat the screening, in sim time
+    pub interval: Nanotime,
+    /// Approaches closer than this are listed as conjunctions, in meters
+    pub report_distance: f64,
+    /// Approaches closer than this raise a warning, in meters
+    pub warning_distance: f64,
+    last_screened: Option<Nanotime>,
+    conjunctions: Vec<Conjunction>,
+    warned: HashSet<(EntityId, EntityId)>,
+    warnings: Vec<Conjunction>,
+}
+
+impl Default for ConjunctionScreener {
+    fn default() -> Self {
+        Self {
+            horizon: Nanotime::hours(6),
+            interval: Nanotime::mins(1),
+            report_distance: 20_000.0,
+            warning_distance: 1_000.0,
+            last_screened: None,
+            conjunctions: Vec::new(),
+            warned: HashSet::new(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl ConjunctionScreener {
+    pub fn is_due(&self, stamp: Nanotime) -> bool {
+        match self.last_screened {
+            Some(t) => stamp < t || stamp - t >= self.interval,
+            None => true,
+        }
+    }
+
+    pub fn last_screened(&self) -> Option<Nanotime> {
+        self.last_screened
+    }
+
+    /// Forces the next call to `screen_if_due` to run a screening.
+    pub fn invalidate(&mut self) {
+        self.last_screened = None;
+    }
+
+    pub fn screen_if_due(&mut self, universe: &Universe) {
+        if self.is_due(universe.stamp()) {
+            self.screen(universe);
+        }
+    }
+
+    /// Screens every pair of vehicles in the universe which are coasting
+    /// about the same body.
+    pub fn screen(&mut self, universe: &Universe) {
+        let orbits = universe
+            .surface_vehicles
+            .iter()
+            .filter_map(|(id, sv)| Some((*id, sv.parent(), sv.orbit?)));
+        self.screen_orbits(orbits, universe.stamp());
+    }
+
+    /// Screens the given orbits, identified by vehicle id and parent id.
+    pub fn screen_orbits(
+        &mut self,
+        orbits: impl IntoIterator<Item = (EntityId, EntityId, SparseOrbit)>,
+        stamp: Nanotime,
+    ) {
+        let mut by_parent: HashMap<EntityId, Vec<(EntityId, SparseOrbit)>> = HashMap::new();
+        for (id, parent, orbit) in orbits {
+            by_parent.entry(parent).or_default().push((id, orbit));
+        }
+
+        let (horizon, report) = (self.horizon, self.report_distance);
+
+        let mut conjunctions: Vec<Conjunction> = vec![];
+        for (parent, mut members) in by_parent {
+            members.sort_by_key(|(id, _)| *id);
+
+            let mut pairs = vec![];
+            for i in 0..members.len() {
+                for j in i + 1..members.len() {
+                    if shells_overlap(&members[i].1, &members[j].1, report) {
+                        pairs.push((i, j));
+                    }
+                }
+            }
+
+            conjunctions.par_extend(pairs.par_iter().filter_map(|(i, j)| {
+                let (a, oa) = &members[*i];
+                let (b, ob) = &members[*j];
+                let info = oa.timed_approach_info(*ob, stamp, horizon, report)?;
+                info.intersections()?;
+                let tca = info.approaches().0;
+                let (pa, pb) = (oa.pv(tca).ok()?, ob.pv(tca).ok()?);
+                Some(Conjunction {
+                    a: *a,
+                    b: *b,
+                    parent,
+                    tca,
+                    miss_distance: pa.pos.distance(pb.pos),
+                    relative_speed: pa.vel.distance(pb.vel),
+                })
+            }));
+        }
+
+        conjunctions.sort_by(|c, d| {
+            c.miss_distance
+                .total_cmp(&d.miss_distance)
+                .then(c.tca.cmp(&d.tca))
+                .then((c.a, c.b).cmp(&(d.a, d.b)))
+        });
+
+        // pairs are only warned about again once they've dropped out of
+        // the warning distance and come back in
+        let dangerous: HashSet<_> = conjunctions
+            .iter()
+            .filter(|c| c.miss_distance < self.warning_distance)
+            .map(|c| (c.a, c.b))
+            .collect();
+
+        for c in &conjunctions {
+            if dangerous.contains(&(c.a, c.b)) && !self.warned.contains(&(c.a, c.b)) {
+                self.warnings.push(*c);
+            }
+        }
+
+        self.warned = dangerous;
+        self.conjunctions = conjunctions;
+        self.last_screened = Some(stamp);
+    }
+
+    /// Conjunctions found by the most recent screening, closest first.
+    pub fn conjunctions(&self) -> &[Conjunction] {
+        &self.conjunctions
+    }
+
+    /// Conjunctions involving the given vehicle, closest first.
+    pub fn conjunctions_for(&self, id: EntityId) -> impl Iterator<Item = &Conjunction> + use<'_> {
+        self.conjunctions.iter().filter(move |c| c.involves(id))
+    }
+
+    /// Takes the warnings raised since the last call, closest first.
+    pub fn take_warnings(&mut self) -> Vec<Conjunction> {
+        std::mem::take(&mut self.warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body() -> Body {
+        Body::with_mass(63.0, 1000.0, 15000.0)
+    }
+
+    #[test]
+    fn crossing_orbits_closest_approach() {
+        let t0 = Nanotime::zero();
+        // a circular orbit and an eccentric one which crosses it twice
+        let a = SparseOrbit::new(500.0, 500.0, 0.0, body(), t0, false).unwrap();
+        let b = SparseOrbit::new(1200.0, 400.0, 0.3, body(), t0, false).unwrap();
+
+        let dur = a.period().unwrap() * 3;
+        let info = a.timed_approach_info(b, t0, dur, 10.0).unwrap();
+        let tca = info.approaches().0;
+        let miss = a.pv(tca).unwrap().pos.distance(b.pv(tca).unwrap().pos);
+
+        // brute force the separation at a fine resolution
+        let samples = tspace(t0, t0 + a.period().unwrap() * 3, 20000);
+        let (bt, bd) = samples
+            .iter()
+            .map(|t| (*t, a.pv(*t).unwrap().pos.distance(b.pv(*t).unwrap().pos)))
+            .min_by(|x, y| x.1.total_cmp(&y.1))
+            .unwrap();
+
+        assert!(miss <= bd + 1E-3, "{} {}", miss, bd);
+        assert!((miss - bd).abs() < 1.0, "{} {}", miss, bd);
+        assert!((tca - bt).to_secs_f64().abs() < 5.0, "{:?} {:?}", tca, bt);
+
+        // a little further out, they're inside the distance either side of it
+        let distance = bd + 50.0;
+        let info = a.timed_approach_info(b, t0, dur, distance).unwrap();
+        let (rising, falling) = info.intersections().unwrap();
+        assert!(falling < tca && tca < rising, "{:?}", info);
+        for t in [falling, rising] {
+            let d = a.pv(t).unwrap().pos.distance(b.pv(t).unwrap().pos);
+            assert!((d - distance).abs() < 1.0, "{} {}", d, distance);
+        }
+    }
+
+    #[test]
+    fn distant_shells_are_skipped() {
+        let t0 = Nanotime::zero();
+        let a = SparseOrbit::new(500.0, 400.0, 0.0, body(), t0, false).unwrap();
+        let b = SparseOrbit::new(3000.0, 2000.0, 1.0, body(), t0, false).unwrap();
+        assert!(!shells_overlap(&a, &b, 100.0));
+        assert!(!shells_overlap(&b, &a, 100.0));
+        assert!(shells_overlap(&a, &b, 2000.0));
+    }
+
+    #[test]
+    fn screener_sorts_and_warns_once() {
+        let t0 = Nanotime::zero();
+        let orbit = |ra, rp, argp| SparseOrbit::new(ra, rp, argp, body(), t0, false).unwrap();
+
+        let orbits = vec![
+            (EntityId(1), EntityId(0), orbit(500.0, 500.0, 0.0)),
+            // same circular orbit, trailing slightly behind
+            (EntityId(2), EntityId(0), {
+                let o = orbit(500.0, 500.0, 0.0);
+                SparseOrbit::from_pv(o.pv(Nanotime::millis(-50)).unwrap(), body(), t0).unwrap()
+            }),
+            // crossing orbit
+            (EntityId(3), EntityId(0), orbit(900.0, 300.0, 2.0)),
+            // same as 1, but about a different body
+            (EntityId(4), EntityId(9), orbit(500.0, 500.0, 0.0)),
+        ];
+
+        let mut screener = ConjunctionScreener {
+            horizon: Nanotime::mins(2),
+            report_distance: 1E6,
+            warning_distance: 100.0,
+            ..Default::default()
+        };
+
+        screener.screen_orbits(orbits.clone(), t0);
+
+        let conj = screener.conjunctions();
+        assert!(!conj.is_empty());
+        assert!(conj.iter().all(|c| c.parent == EntityId(0)));
+        assert!(conj.iter().all(|c| c.a < c.b));
+        for w in conj.windows(2) {
+            assert!(w[0].miss_distance <= w[1].miss_distance);
+        }
+
+        let warnings = screener.take_warnings();
+        assert!(warnings
+            .iter()
+            .any(|c| (c.a, c.b) == (EntityId(1), EntityId(2))));
+        assert!(warnings.iter().all(|c| c.miss_distance < 100.0));
+        assert!(screener.take_warnings().is_empty());
+
+        // screening again doesn't repeat warnings for the same pairs
+        screener.screen_orbits(orbits, t0 + Nanotime::secs(1));
+        assert!(screener.take_warnings().is_empty());
+        assert!(!screener.is_due(t0 + Nanotime::secs(2)));
+        assert!(screener.is_due(t0 + Nanotime::mins(2)));
+    }
+}
@@ -1,5 +1,14 @@
 use crate::prelude::*;
 
+/// Largest rotation, in radians, a vehicle may undergo in a single
+/// physics warp step.
+const MAX_WARP_ROTATION: f64 = 0.01;
+
+/// Largest change in velocity, in meters per second, a vehicle may make in
+/// a single physics warp step, so that its controller or mission gets to
+/// stop a burn close to where it means to.
+const MAX_WARP_DV: f64 = 1.0;
+
 #[derive(Debug)]
 pub struct SurfaceSpacecraftEntity {
     pub planet_id: EntityId,
@@ -12,7 +21,11 @@ pub struct SurfaceSpacecraftEntity {
     orbiter: Option<Orbiter>,
     altitude: Option<f64>,
     clamped_to_ground: bool,
+    /// Set when the vehicle comes down onto the surface, until taken
+    touchdown: Option<Impact>,
     pub target_relative_pv: Option<PV>,
+    pub tracks: SensorTracks,
+    mission: Option<Mission>,
 }
 
 impl SurfaceSpacecraftEntity {
@@ -22,6 +35,8 @@ impl SurfaceSpacecraftEntity {
         body: RigidBody,
         controller: VehicleController,
     ) -> Self {
+        // vehicles carrying a script start on it straight away
+        let mission = vehicle.mission().cloned().map(Mission::new);
         Self {
             planet_id,
             vehicle,
@@ -33,7 +48,10 @@ impl SurfaceSpacecraftEntity {
             orbiter: None,
             altitude: None,
             clamped_to_ground: false,
+            touchdown: None,
             target_relative_pv: None,
+            tracks: SensorTracks::default(),
+            mission,
         }
     }
 
@@ -49,6 +67,22 @@ impl SurfaceSpacecraftEntity {
         self.vehicle = vehicle;
     }
 
+    /// Puts the vehicle somewhere else entirely, relative to a new parent,
+    /// taking it off the ground if it was landed.
+    pub fn teleport(&mut self, parent: EntityId, parent_body: Body, pv: PV, stamp: Nanotime) {
+        self.planet_id = parent;
+        self.body.pv = pv;
+        self.clamped_to_ground = false;
+        self.touchdown = None;
+        let altitude = pv.pos.length() - parent_body.radius;
+        self.altitude = Some(altitude);
+        self.orbit = if altitude > 2_000.0 {
+            SparseOrbit::from_pv(pv, parent_body, stamp)
+        } else {
+            None
+        };
+    }
+
     pub fn parent(&self) -> EntityId {
         self.planet_id
     }
@@ -65,6 +99,38 @@ impl SurfaceSpacecraftEntity {
         self.target = id.into();
     }
 
+    /// The orbit of the current target as estimated from this vehicle's
+    /// sensor track, if there is one.
+    pub fn estimated_target_orbit(&self) -> Option<GlobalOrbit> {
+        let target = ObjectId::Orbiter(self.target?);
+        self.tracks.get(&target)?.estimate()?.global_orbit()
+    }
+
+    pub fn is_landed(&self) -> bool {
+        self.clamped_to_ground
+    }
+
+    /// The most recent touchdown on the surface, if it hasn't been taken
+    /// already.
+    pub fn take_touchdown(&mut self) -> Option<Impact> {
+        self.touchdown.take()
+    }
+
+    pub fn mission(&self) -> Option<&Mission> {
+        self.mission.as_ref()
+    }
+
+    pub fn set_mission(&mut self, script: impl Into<Option<MissionScript>>) {
+        self.mission = script.into().map(Mission::new);
+    }
+
+    pub fn update_mission(&mut self, ctx: &MissionContext) {
+        if let Some(mut mission) = self.mission.take() {
+            mission.update(self, ctx);
+            self.mission = Some(mission);
+        }
+    }
+
     pub fn props(&self) -> impl Iterator<Item = &Propagator> + use<'_> {
         self.orbiter.iter().flat_map(|o| o.props())
     }
@@ -75,24 +141,49 @@ impl SurfaceSpacecraftEntity {
         stamp: Nanotime,
         planets: &PlanetarySystem,
     ) {
-        if let Some(pv) = &self.orbit.map(|o| o.pv(stamp).ok()).flatten() {
+        let (parent_body, parent_pv) = match planets.lookup(self.planet_id, stamp) {
+            Some((body, pv, _, _)) => (body, pv),
+            // nothing to move relative to, so leave the vehicle where it is
+            None => return,
+        };
+
+        // on rails, large steps would carry a suborbital vehicle straight
+        // through the ground
+        let impact = self
+            .current_orbit()
+            .filter(|o| o.1.is_suborbital())
+            .and_then(|o| predict_impact(&o, stamp - delta_time))
+            .filter(|i| i.stamp <= stamp);
+
+        if self.clamped_to_ground {
+            // landed vehicles are carried around by the surface
+            let theta = parent_body.rotation_rate * delta_time.to_secs_f64();
+            self.body.pv.pos = rotate_f64(self.body.pv.pos, theta);
+            self.body.pv.vel = parent_body.surface_velocity(self.body.pv.pos);
+        } else if let Some(impact) = impact {
+            let theta = parent_body.rotation_rate * (stamp - impact.stamp).to_secs_f64();
+            let pos = rotate_f64(impact.pos.normalize_or_zero() * parent_body.radius, theta);
+            self.body.pv = PV::from_f64(pos, parent_body.surface_velocity(pos));
+            self.clamped_to_ground = true;
+            self.touchdown = Some(impact);
+        } else if let Some(pv) = &self.orbit.and_then(|o| o.pv(stamp).ok()) {
             self.body.pv = *pv;
         } else {
             let accel = BodyFrameAccel {
                 linear: DVec2::ZERO,
                 angular: 0.0,
             };
-            self.body.on_sim_tick(accel, DVec2::ZERO, delta_time);
+            self.body.on_sim_tick(
+                accel,
+                |_| DVec2::ZERO,
+                delta_time,
+                PhysicsSettings::default(),
+            );
         }
 
         self.body.angle += self.body.angular_velocity * delta_time.to_secs_f64();
         self.body.angle = wrap_0_2pi_f64(self.body.angle);
 
-        let (parent_body, parent_pv) = match planets.lookup(self.planet_id, stamp) {
-            Some((body, pv, _, _)) => (body, pv),
-            None => todo!(),
-        };
-
         if self.clamped_to_ground {
             self.body.angle = self.body.pv.pos.to_angle();
         }
@@ -144,13 +235,92 @@ impl SurfaceSpacecraftEntity {
         Some(())
     }
 
-    pub fn step(&mut self, planets: &PlanetarySystem, stamp: Nanotime, ext: VehicleControl) {
+    pub fn step(
+        &mut self,
+        planets: &PlanetarySystem,
+        stamp: Nanotime,
+        ext: VehicleControl,
+        physics: PhysicsSettings,
+    ) {
+        self.step_ticks(planets, stamp, ext, physics, 1);
+    }
+
+    /// Largest number of ticks, up to `limit`, which this vehicle can be
+    /// advanced by in a single physics warp step. Controls are held for the
+    /// whole step, so this is bounded by how far the vehicle would rotate,
+    /// how much it could burn, when it would run dry, and how soon it could
+    /// hit the ground.
+    pub fn max_warp_ticks(&self, planets: &PlanetarySystem, stamp: Nanotime, limit: u32) -> u32 {
+        if self.can_be_on_rails() {
+            return limit;
+        }
+        let dt = PHYSICS_CONSTANT_DELTA_TIME.to_secs_f64();
+        let to_ticks = |secs: f64| (secs / dt).clamp(1.0, u32::MAX as f64) as u32;
+        let omega = self.body.angular_velocity.abs();
+        let alpha = self.vehicle.body_frame_accel().angular.abs();
+        let mut ticks = limit.max(1);
+
+        let accel = self.vehicle.max_forward_thrust() / self.vehicle.total_mass().to_kg_f64();
+        if accel > 0.0 {
+            let dv = match self.controller.mode() {
+                VehicleControlPolicy::MatchVelocity(vel) => {
+                    (*vel - self.body.pv.vel).length().min(MAX_WARP_DV)
+                }
+                _ => MAX_WARP_DV,
+            };
+            ticks = ticks.min(to_ticks(dv / accel));
+        }
+
+        let flow = self.vehicle.max_fuel_consumption_rate();
+        let fuel = self.vehicle.fuel_mass().to_kg_f64();
+        if flow > 0.0 && fuel > 0.0 {
+            ticks = ticks.min(to_ticks(fuel / flow));
+        }
+
+        if let (Some(h), Some((body, _, _, _))) =
+            (self.altitude, planets.lookup(self.planet_id, stamp))
+        {
+            if !self.clamped_to_ground {
+                // time to fall to the surface from here, under gravity alone
+                let pos = self.body.pv.pos;
+                let g = body.mu / pos.length_squared();
+                let vr = pos.normalize_or_zero().dot(self.body.pv.vel);
+                let fall = (vr + (vr * vr + 2.0 * g * h.max(0.0)).sqrt()) / g;
+                ticks = ticks.min(to_ticks(fall / 2.0));
+            }
+        }
+
+        while ticks > 1 {
+            let dt = (PHYSICS_CONSTANT_DELTA_TIME * ticks).to_secs_f64();
+            if omega * dt + 0.5 * alpha * dt * dt <= MAX_WARP_ROTATION {
+                break;
+            }
+            ticks /= 2;
+        }
+        ticks
+    }
+
+    /// Advances the vehicle by the given number of ticks, holding the
+    /// output of the control law fixed over the whole interval. A single
+    /// tick is integrated with fixed substeps; longer intervals use
+    /// adaptive steps bounded by the warp tolerance.
+    pub fn step_ticks(
+        &mut self,
+        planets: &PlanetarySystem,
+        stamp: Nanotime,
+        ext: VehicleControl,
+        physics: PhysicsSettings,
+        ticks: u32,
+    ) {
         let (parent_body, parent_pv) = match planets.lookup(self.planet_id, stamp) {
             Some((body, pv, _, _)) => (body, pv),
-            None => todo!(),
+            // nothing to move relative to, so leave the vehicle where it is
+            None => return,
         };
 
-        let gravity = parent_body.gravity(self.body.pv.pos);
+        let field = GravityField::new(planets, self.planet_id, stamp, physics.gravity)
+            .unwrap_or(GravityField::Single(parent_body));
+        let gravity = field.accel(self.body.pv.pos);
 
         match self.controller.mode() {
             VehicleControlPolicy::HoldAttitude(None) => {
@@ -192,6 +362,15 @@ impl SurfaceSpacecraftEntity {
             (VehicleControlPolicy::PositionHold(_), _) => {
                 (VehicleControl::NULLOPT, VehicleControlStatus::Idling)
             }
+            (VehicleControlPolicy::MatchVelocity(vel), _) => {
+                match_velocity_control_law(*vel, &self.body, &self.vehicle)
+            }
+            (VehicleControlPolicy::Land, _) if self.clamped_to_ground => {
+                (VehicleControl::NULLOPT, VehicleControlStatus::Done)
+            }
+            (VehicleControlPolicy::Land, _) => {
+                landing_control_law(&parent_body, &self.body, &self.vehicle)
+            }
         };
 
         self.controller.set_status(status);
@@ -211,16 +390,45 @@ impl SurfaceSpacecraftEntity {
         self.controller
             .check_target_achieved(&self.body, gravity.length() > 0.0);
         self.vehicle.set_thrust_control(&ctrl);
-        self.vehicle.on_sim_tick();
+        for _ in 0..ticks {
+            self.vehicle.on_sim_tick();
+        }
 
         let alt = self.body.pv.pos.length() - parent_body.radius;
         self.altitude = Some(alt);
 
         let accel = self.vehicle.body_frame_accel();
-        self.body
-            .on_sim_tick(accel, gravity, PHYSICS_CONSTANT_DELTA_TIME);
+        if ticks > 1 {
+            self.body.on_sim_tick_adaptive(
+                accel,
+                |p| field.accel(p),
+                PHYSICS_CONSTANT_DELTA_TIME * ticks,
+                physics.integrator,
+                physics.warp_tolerance,
+            );
+        } else {
+            self.body.on_sim_tick(
+                accel,
+                |p| field.accel(p),
+                PHYSICS_CONSTANT_DELTA_TIME,
+                physics,
+            );
+        }
 
-        self.clamped_to_ground = self.body.clamp_with_elevation(parent_body.radius);
+        let was_landed = self.clamped_to_ground;
+        let before_clamp = self.body.pv;
+        self.clamped_to_ground = self
+            .body
+            .clamp_with_elevation(parent_body.radius, parent_body.rotation_rate);
+
+        if self.clamped_to_ground && !was_landed {
+            self.touchdown = Some(Impact::new(
+                self.planet_id,
+                &parent_body,
+                before_clamp,
+                stamp,
+            ));
+        }
 
         if self.clamped_to_ground {
             self.body.angle = self.body.pv.pos.to_angle();
@@ -43,10 +43,12 @@ pub fn consistency_orbits(body: Body) -> Vec<SparseOrbit> {
 
 pub fn rss() -> PlanetarySystem {
     let mut id = ObjectIdTracker::new();
-    let earth_body = Body::with_mu(EARTH_RADIUS, EARTH_MU, EARTH_SOI);
+    let earth_body =
+        Body::with_mu(EARTH_RADIUS, EARTH_MU, EARTH_SOI).with_rotation_rate(EARTH_ROTATION_RATE);
     let mut earth = PlanetarySystem::new(id.next(), "Earth", earth_body);
 
-    let luna_body = Body::with_mu(LUNA_RADIUS, LUNA_MU, LUNA_SOI);
+    let luna_body =
+        Body::with_mu(LUNA_RADIUS, LUNA_MU, LUNA_SOI).with_rotation_rate(LUNA_ROTATION_RATE);
     let luna = PlanetarySystem::new(id.next(), "Luna", luna_body);
     let luna_orbit = SparseOrbit::circular(
         LUNA_ORBITAL_RADIUS as f64,
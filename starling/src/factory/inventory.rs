@@ -93,6 +93,11 @@ impl Item {
             Item::Power => false,
         }
     }
+
+    /// Whether thrusters can burn this.
+    pub fn is_propellant(&self) -> bool {
+        matches!(self, Item::Methane | Item::H2 | Item::O2)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,7 +11,7 @@ impl std::fmt::Display for EntityId {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ObjectId {
     Orbiter(EntityId),
     Planet(EntityId),
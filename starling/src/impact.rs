@@ -0,0 +1,181 @@
+use crate::prelude::*;
+use crate::propagator::search_condition;
+
+/// Touchdowns faster than this relative to the ground, in meters per
+/// second, are crashes.
+pub const SAFE_LANDING_SPEED: f64 = 10.0;
+
+/// Where and when a vehicle meets the surface of its parent body.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Impact {
+    pub parent: EntityId,
+    pub stamp: Nanotime,
+    /// Position relative to the parent, in the inertial frame
+    pub pos: DVec2,
+    /// Longitude of the impact point in the parent's body-fixed frame, as
+    /// used for landing sites
+    pub angle: f64,
+    /// Inertial velocity
+    pub vel: DVec2,
+    /// Velocity relative to the rotating surface
+    pub ground_vel: DVec2,
+}
+
+impl Impact {
+    pub fn new(parent: EntityId, body: &Body, pv: PV, stamp: Nanotime) -> Self {
+        Impact {
+            parent,
+            stamp,
+            pos: pv.pos,
+            angle: body.inertial_to_fixed(pv.pos, stamp).to_angle(),
+            vel: pv.vel,
+            ground_vel: pv.vel - body.surface_velocity(pv.pos),
+        }
+    }
+
+    /// Speed relative to the ground.
+    pub fn speed(&self) -> f64 {
+        self.ground_vel.length()
+    }
+
+    /// Speed towards the ground.
+    pub fn vertical_speed(&self) -> f64 {
+        -self.ground_vel.dot(self.pos.normalize_or_zero())
+    }
+
+    pub fn horizontal_speed(&self) -> f64 {
+        self.ground_vel
+            .dot(self.pos.normalize_or_zero().perp())
+            .abs()
+    }
+
+    pub fn is_safe(&self) -> bool {
+        self.speed() <= SAFE_LANDING_SPEED
+    }
+}
+
+/// Time of the next periapsis passage. Unlike `SparseOrbit::t_next_p`,
+/// this is also known for the very thin orbits typical of suborbital hops.
+pub(crate) fn next_periapsis(orbit: &SparseOrbit, now: Nanotime) -> Option<Nanotime> {
+    let m = orbit.kepler_mean_anomaly(now)?;
+    let n = orbit.mean_motion();
+    let dt = if orbit.ecc() < 1.0 {
+        (2.0 * PI_64 - m.rem_euclid(2.0 * PI_64)) / n
+    } else if m < 0.0 {
+        -m / n
+    } else {
+        return None;
+    };
+    Some(now + Nanotime::secs_f64(dt))
+}
+
+/// Predicts where a coasting vehicle will hit the surface of its parent
+/// body, if it does so before its next periapsis. Bodies are treated as
+/// smooth spheres without atmosphere.
+pub fn predict_impact(orbit: &GlobalOrbit, now: Nanotime) -> Option<Impact> {
+    predict_impact_over_terrain(orbit, now, |_| 0.0)
+}
+
+/// As `predict_impact`, over terrain given as the height above the body's
+/// radius at each body-fixed longitude.
+pub fn predict_impact_over_terrain(
+    orbit: &GlobalOrbit,
+    now: Nanotime,
+    elevation: impl Fn(f64) -> f64,
+) -> Option<Impact> {
+    let GlobalOrbit(parent, orbit) = orbit;
+    let body = orbit.body;
+
+    let above_ground = |t: Nanotime| match orbit.pv(t) {
+        Ok(pv) => {
+            let angle = body.inertial_to_fixed(pv.pos, t).to_angle();
+            pv.pos.length() > body.radius + elevation(angle)
+        }
+        Err(_) => true,
+    };
+
+    // anything which clears the ground at periapsis clears it everywhere
+    let tp = next_periapsis(orbit, now)?;
+    if above_ground(tp) {
+        return None;
+    }
+
+    // the radius only falls between apoapsis and periapsis, so the search
+    // starts at whichever of apoapsis or now is later
+    let pv = orbit.pv(now).ok()?;
+    let start = match orbit.period() {
+        Some(period) if pv.pos.dot(pv.vel) > 0.0 => tp - period / 2,
+        _ => now,
+    };
+
+    let t = search_condition(start.max(now), tp, Nanotime::millis(1), &above_ground).ok()??;
+    let pv = orbit.pv(t).ok()?;
+    Some(Impact::new(*parent, &body, pv, t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suborbital(body: Body) -> SparseOrbit {
+        // a ballistic hop straight up and a little sideways
+        let pos = DVec2::X * (body.radius + 10_000.0);
+        let vel = DVec2::new(500.0, 300.0);
+        SparseOrbit::from_pv(PV::from_f64(pos, vel), body, Nanotime::zero()).unwrap()
+    }
+
+    #[test]
+    fn predicts_ballistic_impact() {
+        let body = Body::with_mu(LUNA_RADIUS, LUNA_MU, LUNA_SOI);
+        let orbit = suborbital(body);
+        assert!(orbit.is_suborbital());
+
+        let go = GlobalOrbit(EntityId(0), orbit);
+        let impact = predict_impact(&go, Nanotime::zero()).unwrap();
+
+        // the vehicle is above ground just before and below just after
+        let before = orbit.pv(impact.stamp - Nanotime::millis(10)).unwrap();
+        let after = orbit.pv(impact.stamp + Nanotime::millis(10)).unwrap();
+        assert!(before.pos.length() > body.radius);
+        assert!(after.pos.length() < body.radius);
+        assert!((impact.pos.length() - body.radius).abs() < 5.0);
+
+        // energy is conserved on the way down
+        let v0 = orbit.pv(Nanotime::zero()).unwrap().vel.length();
+        let expected = (v0.powi(2)
+            + 2.0 * body.mu * (1.0 / body.radius - 1.0 / (body.radius + 10_000.0)))
+            .sqrt();
+        assert!((impact.vel.length() - expected).abs() < 0.1);
+        assert!(impact.vertical_speed() > 0.0);
+        assert!(!impact.is_safe());
+
+        // the prediction is the same from later in the flight
+        let later = predict_impact(&go, impact.stamp - Nanotime::secs(30)).unwrap();
+        assert!((later.stamp - impact.stamp).to_secs_f64().abs() < 0.01);
+
+        // and there's nothing to predict after the impact or in orbit
+        assert!(predict_impact(&go, impact.stamp + Nanotime::secs(1)).is_none());
+        let circular = SparseOrbit::circular(body.radius * 2.0, body, Nanotime::zero(), false);
+        assert!(predict_impact(&GlobalOrbit(EntityId(0), circular), Nanotime::zero()).is_none());
+    }
+
+    #[test]
+    fn impact_angle_is_body_fixed() {
+        let spinning = Body::with_mu(LUNA_RADIUS, LUNA_MU, LUNA_SOI).with_rotation_rate(1E-3);
+        let go = GlobalOrbit(EntityId(0), suborbital(spinning));
+        let impact = predict_impact(&go, Nanotime::zero()).unwrap();
+
+        let site = spinning.fixed_to_inertial(
+            rotate_f64(DVec2::X * spinning.radius, impact.angle),
+            impact.stamp,
+        );
+        assert!(site.distance(impact.pos) < 1.0);
+        let expected = impact.vel - spinning.surface_velocity(impact.pos);
+        assert!(impact.ground_vel.distance(expected) < 1E-9);
+
+        // high terrain is hit sooner
+        let high = predict_impact_over_terrain(&go, Nanotime::zero(), |_| 5_000.0).unwrap();
+        assert!(high.stamp < impact.stamp);
+        assert!((high.pos.length() - spinning.radius - 5_000.0).abs() < 5.0);
+    }
+}
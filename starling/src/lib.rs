@@ -2,6 +2,8 @@ pub mod aabb;
 pub mod belts;
 pub mod bezier;
 pub mod casts;
+pub mod comms;
+pub mod conjunctions;
 pub mod construction_bot;
 pub mod control;
 pub mod control_signals;
@@ -10,9 +12,13 @@ pub mod examples;
 pub mod factory;
 pub mod file_export;
 pub mod id;
+pub mod impact;
 pub mod lpf;
 pub mod math;
+pub mod mission;
 pub mod nanotime;
+pub mod nbody;
+pub mod orbit_determination;
 pub mod orbital_luts;
 pub mod orbiter;
 pub mod orbits;
@@ -26,8 +32,11 @@ pub mod pv;
 pub mod quantities;
 pub mod region;
 pub mod scenario;
+pub mod sensors;
+pub mod spatial_index;
 pub mod surface;
 pub mod take;
+pub mod three_body;
 pub mod thrust_particles;
 pub mod universe;
 pub mod vehicle;
@@ -16,13 +16,32 @@ pub fn rand(min: f32, max: f32) -> f32 {
     rand::thread_rng().gen_range(min..max)
 }
 
+/// Samples a normal distribution using the Box-Muller transform.
+pub fn randn(mean: f64, std_dev: f64) -> f64 {
+    randn_from(&mut rand::thread_rng(), mean, std_dev)
+}
+
+/// Like [`randn`], but drawing from the given generator, so the result can
+/// be reproduced.
+pub fn randn_from(rng: &mut impl Rng, mean: f64, std_dev: f64) -> f64 {
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen_range(0.0..1.0);
+    let z = (-2.0 * u1.ln()).sqrt() * (2.0 * PI_64 * u2).cos();
+    mean + std_dev * z
+}
+
 pub fn randint(min: i32, max: i32) -> i32 {
     rand::thread_rng().gen_range(min..max)
 }
 
 pub fn randvec(min: f32, max: f32) -> Vec2 {
-    let rot = Vec2::from_angle(rand(0.0, std::f32::consts::PI * 2.0));
-    let mag = rand(min, max);
+    randvec_from(&mut rand::thread_rng(), min, max)
+}
+
+/// Like [`randvec`], but drawing from the given generator.
+pub fn randvec_from(rng: &mut impl Rng, min: f32, max: f32) -> Vec2 {
+    let rot = Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::PI * 2.0));
+    let mag = rng.gen_range(min..max);
     rot.rotate(Vec2::new(mag, 0.0))
 }
 
@@ -0,0 +1,1014 @@
+use crate::impact::next_periapsis;
+use crate::planning::phasing_plan;
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Within this distance of its target, in meters, a vehicle flies straight
+/// at it rather than planning transfers.
+const APPROACH_RANGE: f64 = 20_000.0;
+
+/// A rendezvous is complete inside this distance of the target, in meters.
+const RENDEZVOUS_RANGE: f64 = 100.0;
+
+/// Fastest a vehicle closes in on its target, in meters per second.
+const MAX_APPROACH_SPEED: f64 = 50.0;
+
+/// Transfers attempted before a rendezvous is given up on.
+const MAX_TRANSFERS: u32 = 4;
+
+/// How far short of a landing site, in radians of longitude, a vehicle in
+/// orbit starts its deorbit burn.
+const DEORBIT_LEAD: f64 = 2.0;
+
+/// Width of the window after the deorbit lead in which a burn may start.
+/// Vehicles which get there any later go round again.
+const DEORBIT_WINDOW: f64 = 0.5;
+
+/// How many times per orbit a coasting vehicle waiting on the distance to
+/// its target checks back, since the target moves as well.
+const DISTANCE_CHECKS_PER_ORBIT: f64 = 64.0;
+
+/// Something about a vehicle's state which a mission can wait on or burn
+/// until.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantity {
+    /// Altitude of the apoapsis above the surface
+    Apoapsis,
+    /// Altitude of the periapsis above the surface
+    Periapsis,
+    Altitude,
+    /// Fuel remaining, in percent
+    Fuel,
+    /// Speed relative to the parent body
+    Speed,
+    /// Distance to the current target
+    Distance,
+}
+
+impl Quantity {
+    const ALL: [Quantity; 6] = [
+        Quantity::Apoapsis,
+        Quantity::Periapsis,
+        Quantity::Altitude,
+        Quantity::Fuel,
+        Quantity::Speed,
+        Quantity::Distance,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Quantity::Apoapsis => "apoapsis",
+            Quantity::Periapsis => "periapsis",
+            Quantity::Altitude => "altitude",
+            Quantity::Fuel => "fuel",
+            Quantity::Speed => "speed",
+            Quantity::Distance => "distance",
+        }
+    }
+
+    fn parse_value(&self, s: &str) -> Result<f64, String> {
+        match self {
+            Quantity::Fuel => parse_percent(s),
+            Quantity::Speed => parse_speed(s),
+            _ => parse_distance(s),
+        }
+    }
+
+    fn format_value(&self, x: f64) -> String {
+        match self {
+            Quantity::Fuel => format!("{}%", x),
+            Quantity::Speed => format!("{}m/s", x),
+            _ => format_distance(x),
+        }
+    }
+
+    fn value(&self, sv: &SurfaceSpacecraftEntity, ctx: &MissionContext) -> Option<f64> {
+        let pv = sv.pv();
+        let orbit = || SparseOrbit::from_pv(pv, ctx.body, ctx.stamp);
+        match self {
+            Quantity::Apoapsis => {
+                let orbit = orbit()?;
+                if orbit.ecc() >= 1.0 {
+                    Some(f64::INFINITY)
+                } else {
+                    Some(orbit.apoapsis_r() - ctx.body.radius)
+                }
+            }
+            Quantity::Periapsis => Some(orbit()?.periapsis_r() - ctx.body.radius),
+            Quantity::Altitude => Some(pv.pos.length() - ctx.body.radius),
+            Quantity::Fuel => Some(sv.vehicle().fuel_percentage() * 100.0),
+            Quantity::Speed => Some(pv.vel.length()),
+            Quantity::Distance => Some((pv - ctx.target?).pos.length()),
+        }
+    }
+
+    /// Earliest time at which this quantity might pass through `x` for a
+    /// coasting vehicle. The apsides and fuel don't change while coasting,
+    /// so they never do.
+    fn next_crossing(&self, x: f64, orbit: &SparseOrbit, stamp: Nanotime) -> Option<Nanotime> {
+        match self {
+            Quantity::Altitude => orbit.t_next_radius(orbit.body.radius + x, stamp),
+            Quantity::Speed => {
+                // by vis-viva, each speed is reached at a single radius
+                let r = 2.0 / (x.powi(2) / orbit.body.mu() + 1.0 / orbit.semi_major_axis);
+                orbit.t_next_radius(r, stamp)
+            }
+            Quantity::Distance => {
+                let dt = 2.0 * PI_64 / orbit.mean_motion() / DISTANCE_CHECKS_PER_ORBIT;
+                Some(stamp + Nanotime::secs_f64(dt))
+            }
+            Quantity::Apoapsis | Quantity::Periapsis | Quantity::Fuel => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Quantity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// Passing through apoapsis
+    AtApoapsis,
+    /// Passing through periapsis
+    AtPeriapsis,
+    Landed,
+    /// On a closed orbit which clears the surface
+    InOrbit,
+    Above(Quantity, f64),
+    Below(Quantity, f64),
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+}
+
+impl Condition {
+    /// Whether the condition holds, given the vehicle's radial velocity as
+    /// of the previous check, if there was one, and now.
+    fn eval(
+        &self,
+        sv: &SurfaceSpacecraftEntity,
+        ctx: &MissionContext,
+        radial: (Option<f64>, f64),
+    ) -> bool {
+        match self {
+            Condition::AtApoapsis => radial.0.is_some_and(|r| r > 0.0) && radial.1 <= 0.0,
+            Condition::AtPeriapsis => radial.0.is_some_and(|r| r < 0.0) && radial.1 >= 0.0,
+            Condition::Landed => sv.is_landed(),
+            Condition::InOrbit => SparseOrbit::from_pv(sv.pv(), ctx.body, ctx.stamp)
+                .is_some_and(|o| o.ecc() < 1.0 && !o.is_suborbital()),
+            Condition::Above(q, x) => q.value(sv, ctx).is_some_and(|v| v > *x),
+            Condition::Below(q, x) => q.value(sv, ctx).is_some_and(|v| v < *x),
+            Condition::All(c) => c.iter().all(|c| c.eval(sv, ctx, radial)),
+            Condition::Any(c) => c.iter().any(|c| c.eval(sv, ctx, radial)),
+        }
+    }
+
+    /// Earliest time at which a coasting vehicle might meet this condition,
+    /// if it can be predicted.
+    fn next_event(&self, orbit: &SparseOrbit, stamp: Nanotime) -> Option<Nanotime> {
+        match self {
+            Condition::AtApoapsis => {
+                let tp = next_periapsis(orbit, stamp)?;
+                let half = orbit.period()? / 2;
+                Some(if tp - half > stamp {
+                    tp - half
+                } else {
+                    tp + half
+                })
+            }
+            Condition::AtPeriapsis => next_periapsis(orbit, stamp),
+            Condition::Above(q, x) | Condition::Below(q, x) => q.next_crossing(*x, orbit, stamp),
+            Condition::All(c) | Condition::Any(c) => {
+                c.iter().filter_map(|c| c.next_event(orbit, stamp)).min()
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_term(s: &str) -> Result<Self, String> {
+        match s {
+            "apoapsis" => return Ok(Condition::AtApoapsis),
+            "periapsis" => return Ok(Condition::AtPeriapsis),
+            "landed" => return Ok(Condition::Landed),
+            "in orbit" => return Ok(Condition::InOrbit),
+            _ => (),
+        }
+
+        let (i, above) = s
+            .find('>')
+            .map(|i| (i, true))
+            .or_else(|| s.find('<').map(|i| (i, false)))
+            .ok_or_else(|| format!("unknown condition \"{}\"", s))?;
+
+        let name = s[..i].trim();
+        let q = Quantity::ALL
+            .into_iter()
+            .find(|q| q.name() == name)
+            .ok_or_else(|| format!("unknown quantity \"{}\"", name))?;
+        let x = q.parse_value(&s[i + 1..])?;
+        Ok(if above {
+            Condition::Above(q, x)
+        } else {
+            Condition::Below(q, x)
+        })
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    /// Conditions are joined with "and" and "or", with "and" binding more
+    /// tightly.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut any = s
+            .split(" or ")
+            .map(|s| {
+                let mut all = s
+                    .split(" and ")
+                    .map(|s| Condition::parse_term(s.trim()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(match all.len() {
+                    1 => all.remove(0),
+                    _ => Condition::All(all),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(match any.len() {
+            1 => any.remove(0),
+            _ => Condition::Any(any),
+        })
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let join = |f: &mut std::fmt::Formatter<'_>, c: &[Condition], sep: &str| {
+            let s: Vec<_> = c.iter().map(|c| c.to_string()).collect();
+            write!(f, "{}", s.join(sep))
+        };
+        match self {
+            Condition::AtApoapsis => write!(f, "apoapsis"),
+            Condition::AtPeriapsis => write!(f, "periapsis"),
+            Condition::Landed => write!(f, "landed"),
+            Condition::InOrbit => write!(f, "in orbit"),
+            Condition::Above(q, x) => write!(f, "{} > {}", q, q.format_value(*x)),
+            Condition::Below(q, x) => write!(f, "{} < {}", q, q.format_value(*x)),
+            Condition::All(c) => join(f, c, " and "),
+            Condition::Any(c) => join(f, c, " or "),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MissionStep {
+    /// Launch into an orbit at the given altitude
+    Launch(f64),
+    Wait(Nanotime),
+    WaitUntil(Condition),
+    Burn {
+        prograde: bool,
+        until: Condition,
+    },
+    SetTarget(EntityId),
+    /// Match orbits with the target and close in on it
+    Rendezvous,
+    /// Come down onto the surface, at a longitude in the parent's
+    /// body-fixed frame if one is given
+    Land(Option<f64>),
+}
+
+impl FromStr for MissionStep {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+        let s = s.to_lowercase();
+
+        if let Some(rest) = s.strip_prefix("launch to ") {
+            Ok(MissionStep::Launch(parse_distance(rest)?))
+        } else if let Some(rest) = s.strip_prefix("wait until ") {
+            Ok(MissionStep::WaitUntil(rest.parse()?))
+        } else if let Some(rest) = s.strip_prefix("wait ") {
+            Ok(MissionStep::Wait(parse_duration(rest)?))
+        } else if let Some(rest) = s.strip_prefix("burn ") {
+            let (direction, until) = rest
+                .split_once(" until ")
+                .ok_or("burns need a condition to stop at")?;
+            let prograde = match direction {
+                "prograde" => true,
+                "retrograde" => false,
+                _ => return Err(format!("unknown burn direction \"{}\"", direction)),
+            };
+            Ok(MissionStep::Burn {
+                prograde,
+                until: until.parse()?,
+            })
+        } else if let Some(rest) = s
+            .strip_prefix("set target ")
+            .or_else(|| s.strip_prefix("target "))
+        {
+            let id = rest
+                .parse()
+                .map_err(|_| format!("bad vehicle id \"{}\"", rest))?;
+            Ok(MissionStep::SetTarget(EntityId(id)))
+        } else if s == "rendezvous" {
+            Ok(MissionStep::Rendezvous)
+        } else if s == "land" {
+            Ok(MissionStep::Land(None))
+        } else if let Some(rest) = s.strip_prefix("land at ") {
+            Ok(MissionStep::Land(Some(parse_angle(rest)?)))
+        } else {
+            Err(format!("unknown step \"{}\"", s))
+        }
+    }
+}
+
+impl std::fmt::Display for MissionStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MissionStep::Launch(alt) => write!(f, "launch to {}", format_distance(*alt)),
+            MissionStep::Wait(dt) => write!(f, "wait {}", format_duration(*dt)),
+            MissionStep::WaitUntil(c) => write!(f, "wait until {}", c),
+            MissionStep::Burn { prograde, until } => write!(
+                f,
+                "burn {} until {}",
+                if *prograde { "prograde" } else { "retrograde" },
+                until
+            ),
+            MissionStep::SetTarget(id) => write!(f, "target {}", id),
+            MissionStep::Rendezvous => write!(f, "rendezvous"),
+            MissionStep::Land(None) => write!(f, "land"),
+            MissionStep::Land(Some(angle)) => write!(f, "land at {}", format_angle(*angle)),
+        }
+    }
+}
+
+/// A sequence of steps for a vehicle to carry out on its own, written one
+/// per line, for example:
+///
+/// ```text
+/// launch to 300km
+/// target 1005
+/// rendezvous
+/// wait 10min
+/// burn retrograde until periapsis < 20km
+/// land at 45deg
+/// ```
+///
+/// Anything after a `#` is a comment.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct MissionScript {
+    pub steps: Vec<MissionStep>,
+}
+
+impl FromStr for MissionScript {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut steps = vec![];
+        for (i, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let step = line.parse().map_err(|e| format!("line {}: {}", i + 1, e))?;
+            steps.push(step);
+        }
+        Ok(MissionScript { steps })
+    }
+}
+
+impl std::fmt::Display for MissionScript {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{}", step)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for MissionScript {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<MissionScript> for String {
+    fn from(script: MissionScript) -> Self {
+        script.to_string()
+    }
+}
+
+fn split_unit(s: &str) -> Result<(f64, &str), String> {
+    let s = s.trim();
+    let i = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+        .unwrap_or(s.len());
+    let x = s[..i]
+        .parse()
+        .map_err(|_| format!("bad number \"{}\"", s))?;
+    Ok((x, s[i..].trim()))
+}
+
+/// Parses a distance in meters, such as "250km" or "40m". Bare numbers
+/// are meters.
+pub fn parse_distance(s: &str) -> Result<f64, String> {
+    match split_unit(s)? {
+        (x, "" | "m") => Ok(x),
+        (x, "km") => Ok(x * 1000.0),
+        (_, unit) => Err(format!("unknown distance unit \"{}\"", unit)),
+    }
+}
+
+fn format_distance(x: f64) -> String {
+    if x != 0.0 && x % 1000.0 == 0.0 {
+        format!("{}km", x / 1000.0)
+    } else {
+        format!("{}m", x)
+    }
+}
+
+/// Parses a duration such as "90s", "2h" or "1.5d". Bare numbers are
+/// seconds.
+pub fn parse_duration(s: &str) -> Result<Nanotime, String> {
+    let (x, unit) = split_unit(s)?;
+    let scale = match unit {
+        "ms" => Nanotime::PER_MILLI,
+        "" | "s" => Nanotime::PER_SEC,
+        "min" => Nanotime::PER_MINUTE,
+        "h" => Nanotime::PER_HOUR,
+        "d" => Nanotime::PER_DAY,
+        _ => return Err(format!("unknown duration unit \"{}\"", unit)),
+    };
+    Ok(Nanotime::nanos((x * scale as f64).round() as i64))
+}
+
+fn format_duration(dt: Nanotime) -> String {
+    let ns = dt.inner();
+    [
+        (Nanotime::PER_DAY, "d"),
+        (Nanotime::PER_HOUR, "h"),
+        (Nanotime::PER_MINUTE, "min"),
+        (Nanotime::PER_SEC, "s"),
+    ]
+    .into_iter()
+    .find(|(scale, _)| ns != 0 && ns % scale == 0)
+    .map(|(scale, unit)| format!("{}{}", ns / scale, unit))
+    .unwrap_or_else(|| format!("{}ms", ns as f64 / Nanotime::PER_MILLI as f64))
+}
+
+/// Parses an angle into radians, such as "45deg" or "1.2rad". Bare
+/// numbers are degrees.
+pub fn parse_angle(s: &str) -> Result<f64, String> {
+    match split_unit(s)? {
+        (x, "" | "deg" | "°") => Ok(x.to_radians()),
+        (x, "rad") => Ok(x),
+        (_, unit) => Err(format!("unknown angle unit \"{}\"", unit)),
+    }
+}
+
+fn format_angle(x: f64) -> String {
+    format!("{}deg", (x.to_degrees() * 1E6).round() / 1E6)
+}
+
+fn parse_speed(s: &str) -> Result<f64, String> {
+    match split_unit(s)? {
+        (x, "" | "m/s") => Ok(x),
+        (x, "km/s") => Ok(x * 1000.0),
+        (_, unit) => Err(format!("unknown speed unit \"{}\"", unit)),
+    }
+}
+
+fn parse_percent(s: &str) -> Result<f64, String> {
+    match split_unit(s)? {
+        (x, "" | "%") => Ok(x),
+        (_, unit) => Err(format!("unknown fuel unit \"{}\"", unit)),
+    }
+}
+
+/// What a running mission needs to know about the world beyond its own
+/// vehicle.
+#[derive(Debug, Clone, Copy)]
+pub struct MissionContext {
+    pub stamp: Nanotime,
+    /// The vehicle's parent body
+    pub body: Body,
+    /// Position and velocity of the vehicle's target relative to the same
+    /// parent, if it has one and they share a parent. Taken from the
+    /// vehicle's own sensor track where it has one
+    pub target: Option<PV>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MissionStatus {
+    Running,
+    Complete,
+    Failed(&'static str),
+}
+
+/// Progress through the steps which take more than one stage.
+#[derive(Debug, Clone, Default)]
+enum Stage {
+    #[default]
+    Start,
+    /// Flying a transfer plan, with the index of the next impulse and
+    /// whether it's being burned
+    Transfer(Box<ManeuverPlan>, usize, bool),
+    /// Closing in on a nearby target
+    Approach,
+    /// Coasting round to the deorbit burn, or burning if set
+    Deorbit(bool),
+    Descent,
+}
+
+enum StepResult {
+    Running,
+    Done,
+    Failed(&'static str),
+}
+
+/// A mission script being carried out by a vehicle.
+#[derive(Debug, Clone)]
+pub struct Mission {
+    script: MissionScript,
+    step: usize,
+    /// When the current step began, if it has
+    started: Option<Nanotime>,
+    stage: Stage,
+    transfers: u32,
+    /// Radial velocity as of the last update, for spotting apsides
+    radial_velocity: Option<f64>,
+    status: MissionStatus,
+}
+
+impl Mission {
+    pub fn new(script: MissionScript) -> Self {
+        Mission {
+            script,
+            step: 0,
+            started: None,
+            stage: Stage::Start,
+            transfers: 0,
+            radial_velocity: None,
+            status: MissionStatus::Running,
+        }
+    }
+
+    pub fn script(&self) -> &MissionScript {
+        &self.script
+    }
+
+    pub fn status(&self) -> &MissionStatus {
+        &self.status
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.status == MissionStatus::Running
+    }
+
+    /// Index of the step being carried out.
+    pub fn step_index(&self) -> usize {
+        self.step
+    }
+
+    pub fn current_step(&self) -> Option<&MissionStep> {
+        self.is_running()
+            .then(|| self.script.steps.get(self.step))
+            .flatten()
+    }
+
+    /// Advances the mission as far as it can go given the vehicle's
+    /// current state, handing the vehicle's controller whatever policy the
+    /// current step needs.
+    pub fn update(&mut self, sv: &mut SurfaceSpacecraftEntity, ctx: &MissionContext) {
+        if !self.is_running() {
+            return;
+        }
+
+        if *sv.controller.mode() == VehicleControlPolicy::External {
+            self.status = MissionStatus::Failed("Interrupted by pilot");
+            return;
+        }
+
+        let pv = sv.pv();
+        let radial = pv.vel.dot(pv.pos.normalize_or_zero());
+
+        // steps which finish immediately run straight into the next one
+        while let Some(step) = self.script.steps.get(self.step).cloned() {
+            let first = self.started.is_none();
+            if first {
+                self.started = Some(ctx.stamp);
+            }
+
+            // events seen before a step began don't count towards it
+            let previous = if first { None } else { self.radial_velocity };
+
+            match self.run_step(&step, first, sv, ctx, (previous, radial)) {
+                StepResult::Running => break,
+                StepResult::Done => {
+                    self.step += 1;
+                    self.started = None;
+                    self.stage = Stage::Start;
+                    self.transfers = 0;
+                    // the context doesn't know about a new target until the
+                    // next update
+                    if let MissionStep::SetTarget(_) = step {
+                        break;
+                    }
+                }
+                StepResult::Failed(reason) => {
+                    self.status = MissionStatus::Failed(reason);
+                    sv.controller.set_idle();
+                    break;
+                }
+            }
+        }
+
+        if self.step >= self.script.steps.len() {
+            self.status = MissionStatus::Complete;
+        }
+
+        self.radial_velocity = Some(radial);
+    }
+
+    fn run_step(
+        &mut self,
+        step: &MissionStep,
+        first: bool,
+        sv: &mut SurfaceSpacecraftEntity,
+        ctx: &MissionContext,
+        radial: (Option<f64>, f64),
+    ) -> StepResult {
+        let out_of_fuel = sv.vehicle().tank_count() > 0 && sv.vehicle().fuel_percentage() <= 0.0;
+
+        match step {
+            MissionStep::Launch(altitude) => {
+                if first {
+                    sv.controller
+                        .set_policy(VehicleControlPolicy::LaunchToOrbit(*altitude));
+                    StepResult::Running
+                } else if sv.controller.is_idle() {
+                    StepResult::Done
+                } else if out_of_fuel {
+                    StepResult::Failed("Out of fuel")
+                } else {
+                    StepResult::Running
+                }
+            }
+            MissionStep::Wait(duration) => {
+                if first {
+                    sv.controller.set_idle();
+                }
+                match self.started {
+                    Some(t) if ctx.stamp >= t + *duration => StepResult::Done,
+                    _ => StepResult::Running,
+                }
+            }
+            MissionStep::WaitUntil(condition) => {
+                if first {
+                    sv.controller.set_idle();
+                }
+                if condition.eval(sv, ctx, radial) {
+                    StepResult::Done
+                } else {
+                    StepResult::Running
+                }
+            }
+            MissionStep::Burn { prograde, until } => {
+                if until.eval(sv, ctx, radial) {
+                    sv.controller.set_idle();
+                    return StepResult::Done;
+                }
+                if out_of_fuel {
+                    return StepResult::Failed("Out of fuel");
+                }
+                let policy = if *prograde {
+                    VehicleControlPolicy::BurnPrograde
+                } else {
+                    VehicleControlPolicy::BurnRetrograde
+                };
+                if *sv.controller.mode() != policy {
+                    sv.controller.set_policy(policy);
+                }
+                StepResult::Running
+            }
+            MissionStep::SetTarget(id) => {
+                sv.set_target(*id);
+                StepResult::Done
+            }
+            MissionStep::Rendezvous => {
+                if out_of_fuel {
+                    return StepResult::Failed("Out of fuel");
+                }
+                self.rendezvous(sv, ctx)
+            }
+            MissionStep::Land(angle) => {
+                if sv.is_landed() && !first {
+                    sv.controller.set_idle();
+                    return StepResult::Done;
+                }
+                if out_of_fuel {
+                    return StepResult::Failed("Out of fuel");
+                }
+                self.land(*angle, sv, ctx)
+            }
+        }
+    }
+
+    fn rendezvous(&mut self, sv: &mut SurfaceSpacecraftEntity, ctx: &MissionContext) -> StepResult {
+        if sv.target().is_none() {
+            return StepResult::Failed("No target");
+        }
+        let target = match ctx.target {
+            Some(pv) => pv,
+            None => return StepResult::Failed("Target isn't orbiting the same body"),
+        };
+
+        let rel = sv.pv() - target;
+        let distance = rel.pos.length();
+        if distance < RENDEZVOUS_RANGE && rel.vel.length() < 0.5 {
+            sv.controller.set_idle();
+            return StepResult::Done;
+        }
+
+        if let Stage::Transfer(plan, next, burning) = &mut self.stage {
+            if *burning && sv.controller.is_idle() {
+                *burning = false;
+                *next += 1;
+            }
+
+            let impulse = plan.dvs().nth(*next);
+            if let Some((t, _)) = impulse {
+                if ctx.stamp >= t {
+                    // aim for the velocity on the orbit after the impulse,
+                    // which moves on as the burn goes
+                    let after = plan
+                        .segments
+                        .get(*next + 1)
+                        .map(|s| s.orbit)
+                        .unwrap_or(plan.terminal);
+                    if let Ok(pv) = after.pv(ctx.stamp) {
+                        sv.controller
+                            .set_policy(VehicleControlPolicy::MatchVelocity(pv.vel));
+                        *burning = true;
+                    }
+                } else if !sv.controller.is_idle() {
+                    sv.controller.set_idle();
+                }
+                return StepResult::Running;
+            }
+
+            self.stage = Stage::Start;
+        }
+
+        if distance <= APPROACH_RANGE {
+            self.stage = Stage::Approach;
+            let closing = if distance > RENDEZVOUS_RANGE {
+                -rel.pos.normalize_or_zero() * (distance / 60.0).min(MAX_APPROACH_SPEED)
+            } else {
+                DVec2::ZERO
+            };
+            sv.controller
+                .set_policy(VehicleControlPolicy::MatchVelocity(target.vel + closing));
+            return StepResult::Running;
+        }
+
+        if self.transfers >= MAX_TRANSFERS {
+            return StepResult::Failed("Couldn't reach target");
+        }
+
+        let orbits = SparseOrbit::from_pv(sv.pv(), ctx.body, ctx.stamp)
+            .zip(SparseOrbit::from_pv(target, ctx.body, ctx.stamp));
+        let (current, destination) = match orbits {
+            Some(o) => o,
+            None => return StepResult::Failed("Not in orbit"),
+        };
+
+        // once on the target's orbit, all that's left is catching it up
+        let plan = if same_shape(&current, &destination) {
+            phasing_plan(&current, &destination, ctx.stamp)
+        } else {
+            best_maneuver_plan(&current, &destination, ctx.stamp).ok()
+        };
+
+        match plan {
+            Some(plan) => {
+                self.transfers += 1;
+                self.stage = Stage::Transfer(Box::new(plan), 0, false);
+                sv.controller.set_idle();
+                StepResult::Running
+            }
+            None => StepResult::Failed("No transfer to target"),
+        }
+    }
+
+    fn land(
+        &mut self,
+        site: Option<f64>,
+        sv: &mut SurfaceSpacecraftEntity,
+        ctx: &MissionContext,
+    ) -> StepResult {
+        let pv = sv.pv();
+        let orbit = SparseOrbit::from_pv(pv, ctx.body, ctx.stamp);
+
+        if let Stage::Start = self.stage {
+            self.stage = match orbit {
+                Some(o) if !o.is_suborbital() => Stage::Deorbit(site.is_none()),
+                _ => Stage::Descent,
+            };
+        }
+
+        match self.stage {
+            Stage::Deorbit(false) => {
+                let site = site.unwrap_or(0.0);
+                if deorbit_lead(pv, site, ctx) <= DEORBIT_LEAD {
+                    sv.controller
+                        .set_policy(VehicleControlPolicy::BurnRetrograde);
+                    self.stage = Stage::Deorbit(true);
+                } else if !sv.controller.is_idle() {
+                    sv.controller.set_idle();
+                }
+            }
+            Stage::Deorbit(true) => {
+                if *sv.controller.mode() != VehicleControlPolicy::BurnRetrograde {
+                    sv.controller
+                        .set_policy(VehicleControlPolicy::BurnRetrograde);
+                }
+                // the impact point starts out beyond the site and walks
+                // back towards it as the burn goes on
+                let arrived = match site {
+                    Some(site) => {
+                        let vehicle = sv.vehicle();
+                        let max_accel =
+                            vehicle.max_forward_thrust() / vehicle.total_mass().to_kg_f64();
+                        predict_landing(&ctx.body, pv, ctx.stamp, max_accel).is_some_and(
+                            |(t, touchdown)| {
+                                let angle = ctx.body.inertial_to_fixed(touchdown.pos, t).to_angle();
+                                let dir = pv.pos.perp_dot(pv.vel).signum();
+                                wrap_pi_npi_f64(dir * (angle - site)) <= 0.0
+                            },
+                        )
+                    }
+                    None => orbit.is_some_and(|o| {
+                        predict_impact(&GlobalOrbit(sv.parent(), o), ctx.stamp).is_some()
+                    }),
+                };
+                if arrived {
+                    self.stage = Stage::Descent;
+                }
+            }
+            _ => (),
+        }
+
+        if let Stage::Descent = self.stage {
+            if *sv.controller.mode() != VehicleControlPolicy::Land {
+                sv.controller.set_policy(VehicleControlPolicy::Land);
+            }
+        }
+
+        StepResult::Running
+    }
+
+    /// Earliest time at which the mission might need to act, assuming
+    /// its vehicle coasts until then. The universe won't advance vehicles
+    /// on rails past this in one go.
+    pub fn next_event(
+        &self,
+        sv: &SurfaceSpacecraftEntity,
+        ctx: &MissionContext,
+    ) -> Option<Nanotime> {
+        let step = self.current_step()?;
+        let started = match self.started {
+            Some(t) => t,
+            None => return Some(ctx.stamp),
+        };
+
+        let orbit = SparseOrbit::from_pv(sv.pv(), ctx.body, ctx.stamp);
+
+        match (step, &self.stage) {
+            (MissionStep::Wait(duration), _) => Some(started + *duration),
+            (MissionStep::WaitUntil(c), _) => c.next_event(&orbit?, ctx.stamp),
+            (MissionStep::Rendezvous, Stage::Transfer(plan, next, false)) => {
+                plan.dvs().nth(*next).map(|(t, _)| t)
+            }
+            (MissionStep::Land(site), Stage::Deorbit(false)) => {
+                let orbit = orbit?;
+                let pv = sv.pv();
+                let lead = deorbit_lead(pv, site.unwrap_or(0.0), ctx);
+                let rate = pv.pos.perp_dot(pv.vel).abs() / pv.pos.length_squared()
+                    - pv.pos.perp_dot(pv.vel).signum() * ctx.body.rotation_rate;
+                if rate <= 0.0 {
+                    return None;
+                }
+                // the orbit may not be circular, so check back regularly
+                let dt = Nanotime::secs_f64((lead - DEORBIT_LEAD) / rate);
+                Some(ctx.stamp + dt.min(orbit.period()? / 16))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Whether two orbits only differ by where along them things are. Unlike
+/// `SparseOrbit::is_similar` this doesn't care where the apsides of a
+/// nearly circular orbit happen to point.
+fn same_shape(a: &SparseOrbit, b: &SparseOrbit) -> bool {
+    let tolerance = 0.02 * a.semi_major_axis.min(b.semi_major_axis);
+    a.is_retrograde() == b.is_retrograde()
+        && (a.apoapsis_r() - b.apoapsis_r()).abs() < tolerance
+        && (a.periapsis_r() - b.periapsis_r()).abs() < tolerance
+}
+
+/// Angle in the direction of travel from a vehicle to the point where it
+/// should start its deorbit burn for a landing site. Points already
+/// passed by more than the deorbit window wrap round to the next orbit.
+fn deorbit_lead(pv: PV, site: f64, ctx: &MissionContext) -> f64 {
+    let longitude = ctx.body.inertial_to_fixed(pv.pos, ctx.stamp).to_angle();
+    let dir = pv.pos.perp_dot(pv.vel).signum();
+    let ahead = wrap_0_2pi_f64(dir * (site - longitude));
+    if ahead < DEORBIT_LEAD - DEORBIT_WINDOW {
+        ahead + 2.0 * PI_64
+    } else {
+        ahead
+    }
+}
+
+impl std::fmt::Display for Mission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.status, self.current_step()) {
+            (MissionStatus::Running, Some(step)) => {
+                write!(f, "{}/{} {}", self.step + 1, self.script.steps.len(), step)
+            }
+            (MissionStatus::Failed(reason), _) => {
+                write!(f, "Failed at step {}: {}", self.step + 1, reason)
+            }
+            _ => write!(f, "Complete"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_round_trip() {
+        let text = "
+            # up, over and back down again
+            Launch to 300 km
+            wait until apoapsis
+            burn prograde until periapsis > 290km and fuel < 20%
+            wait 90s
+            wait until altitude < 12500m or landed
+            target 1005
+            rendezvous
+            wait 1.5h
+            burn retrograde until speed < 1.2km/s
+            land at 45deg
+            land
+        ";
+
+        let script: MissionScript = text.parse().unwrap();
+        assert_eq!(script.steps.len(), 11);
+        assert_eq!(script.steps[0], MissionStep::Launch(300_000.0));
+        assert_eq!(
+            script.steps[2],
+            MissionStep::Burn {
+                prograde: true,
+                until: Condition::All(vec![
+                    Condition::Above(Quantity::Periapsis, 290_000.0),
+                    Condition::Below(Quantity::Fuel, 20.0),
+                ]),
+            }
+        );
+        assert_eq!(script.steps[7], MissionStep::Wait(Nanotime::mins(90)));
+        assert_eq!(
+            script.steps[8].to_string(),
+            "burn retrograde until speed < 1200m/s"
+        );
+
+        let again: MissionScript = script.to_string().parse().unwrap();
+        assert_eq!(again, script);
+
+        let yaml = serde_yaml::to_string(&script).unwrap();
+        let back: MissionScript = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(back, script);
+    }
+
+    #[test]
+    fn bad_scripts_say_where() {
+        let err = "wait 5s\nfly to the moon"
+            .parse::<MissionScript>()
+            .unwrap_err();
+        assert!(err.starts_with("line 2"), "{err}");
+        assert!("burn sideways until landed".parse::<MissionStep>().is_err());
+        assert!("wait until fuel > lots".parse::<MissionStep>().is_err());
+        assert!("launch to 300 furlongs".parse::<MissionStep>().is_err());
+    }
+}
@@ -35,7 +35,7 @@ impl Nanotime {
         Nanotime(ns)
     }
 
-    pub fn secs(s: i64) -> Self {
+    pub const fn secs(s: i64) -> Self {
         Nanotime(s * Nanotime::PER_SEC)
     }
 
@@ -0,0 +1,433 @@
+use crate::prelude::*;
+
+/// Longest step taken when integrating the planets, in seconds.
+const MAX_PLANET_STEP: f64 = 60.0;
+
+/// How gravity is modeled for vehicles and planets.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GravityModel {
+    /// Vehicles feel only their parent body, and planets follow fixed
+    /// Keplerian orbits
+    #[default]
+    PatchedConics,
+    /// Off-rails vehicles feel the summed gravity of every body; planets
+    /// still follow fixed Keplerian orbits
+    NBodyVehicles,
+    /// As above, and the planets are integrated under each other's
+    /// gravity as well
+    NBody,
+}
+
+impl GravityModel {
+    pub fn is_n_body(&self) -> bool {
+        *self != GravityModel::PatchedConics
+    }
+}
+
+/// Gravitational acceleration felt by a vehicle, expressed in the frame of
+/// its parent body.
+#[derive(Debug, Clone)]
+pub enum GravityField {
+    /// Only the parent body
+    Single(Body),
+    /// Every body in the system. Positions are relative to the root of the
+    /// system; since the parent frame itself accelerates, its acceleration
+    /// is subtracted, leaving the tidal influence of the other bodies.
+    /// That's the Keplerian acceleration when planets are on rails, and
+    /// the pull of every other body when they're integrated.
+    Summed {
+        sources: Vec<(DVec2, Body)>,
+        origin: DVec2,
+        frame_accel: DVec2,
+    },
+}
+
+impl GravityField {
+    pub fn new(
+        planets: &PlanetarySystem,
+        parent: EntityId,
+        stamp: Nanotime,
+        model: GravityModel,
+    ) -> Option<Self> {
+        let (body, pv, _, _) = planets.lookup(parent, stamp)?;
+        if !model.is_n_body() {
+            return Some(GravityField::Single(body));
+        }
+
+        let sources: Vec<_> = planets
+            .bodies(stamp, None)
+            .map(|(pv, body)| (pv.pos, body))
+            .collect();
+
+        let frame_accel = match model {
+            GravityModel::NBody => {
+                // the parent's own term vanishes at its center
+                sources.iter().map(|(p, b)| b.gravity(pv.pos - p)).sum()
+            }
+            _ => keplerian_accel(planets, parent, stamp)?,
+        };
+
+        Some(GravityField::Summed {
+            sources,
+            origin: pv.pos,
+            frame_accel,
+        })
+    }
+
+    pub fn accel(&self, pos: DVec2) -> DVec2 {
+        match self {
+            GravityField::Single(body) => body.gravity(pos),
+            GravityField::Summed {
+                sources,
+                origin,
+                frame_accel,
+            } => {
+                let world = pos + origin;
+                let total: DVec2 = sources.iter().map(|(p, b)| b.gravity(world - p)).sum();
+                total - frame_accel
+            }
+        }
+    }
+}
+
+/// Acceleration of a body relative to the root of the system, as it's
+/// carried along its chain of Keplerian orbits.
+fn keplerian_accel(planets: &PlanetarySystem, id: EntityId, stamp: Nanotime) -> Option<DVec2> {
+    if planets.id == id {
+        return Some(DVec2::ZERO);
+    }
+    planets.subsystems.iter().find_map(|(orbit, sub)| {
+        let inner = keplerian_accel(sub, id, stamp)?;
+        let rel = orbit.pv(stamp).ok()?.pos;
+        Some(inner + orbit.body.gravity(rel))
+    })
+}
+
+/// State of a planetary system integrated as an N-body problem. Positions
+/// and velocities are relative to the root body, which stays at the origin
+/// of the world frame; the equations of motion include the indirect terms
+/// for the root's own acceleration.
+#[derive(Debug, Clone)]
+pub struct NBodyPlanets {
+    stamp: Nanotime,
+    ids: Vec<EntityId>,
+    /// Index of each body's parent in the planetary hierarchy
+    parents: Vec<Option<usize>>,
+    bodies: Vec<Body>,
+    pvs: Vec<PV>,
+}
+
+impl NBodyPlanets {
+    /// Starts an integration from the Keplerian state of the planets.
+    pub fn new(planets: &PlanetarySystem, stamp: Nanotime) -> Self {
+        let mut ret = NBodyPlanets {
+            stamp,
+            ids: vec![],
+            parents: vec![],
+            bodies: vec![],
+            pvs: vec![],
+        };
+        ret.add_system(planets, None, PV::ZERO, stamp);
+        ret
+    }
+
+    fn add_system(
+        &mut self,
+        sys: &PlanetarySystem,
+        parent: Option<usize>,
+        pv: PV,
+        stamp: Nanotime,
+    ) {
+        let index = self.ids.len();
+        self.ids.push(sys.id);
+        self.parents.push(parent);
+        self.bodies.push(sys.body);
+        self.pvs.push(pv);
+        for (orbit, sub) in &sys.subsystems {
+            if let Ok(p) = orbit.pv(stamp) {
+                self.add_system(sub, Some(index), pv + p, stamp);
+            }
+        }
+    }
+
+    /// Adds a massless particle, which feels every body but doesn't pull
+    /// on anything, at a position relative to the root body.
+    pub fn add_particle(&mut self, id: EntityId, pv: PV) {
+        self.ids.push(id);
+        self.parents.push(None);
+        self.bodies.push(Body::with_mu(0.0, 0.0, 0.0));
+        self.pvs.push(pv);
+    }
+
+    pub fn stamp(&self) -> Nanotime {
+        self.stamp
+    }
+
+    /// State relative to the root body.
+    pub fn pv(&self, id: EntityId) -> Option<PV> {
+        let i = self.ids.iter().position(|e| *e == id)?;
+        Some(self.pvs[i])
+    }
+
+    fn accelerations(&self) -> Vec<DVec2> {
+        let mu_root = self.bodies[0].mu;
+        let accel = |i: usize| {
+            if i == 0 {
+                return DVec2::ZERO;
+            }
+            let ri = self.pvs[i].pos;
+            let mut a = -(mu_root + self.bodies[i].mu) * ri / ri.length().powi(3);
+            for j in 1..self.pvs.len() {
+                let mu = self.bodies[j].mu;
+                if i == j || mu == 0.0 {
+                    continue;
+                }
+                let rj = self.pvs[j].pos;
+                let d = rj - ri;
+                if d == DVec2::ZERO {
+                    continue;
+                }
+                a += mu * (d / d.length().powi(3) - rj / rj.length().powi(3));
+            }
+            a
+        };
+        (0..self.pvs.len()).map(accel).collect()
+    }
+
+    /// Kick-drift-kick leapfrog step.
+    fn step(&mut self, dt: f64) {
+        let a = self.accelerations();
+        for (pv, a) in self.pvs.iter_mut().zip(&a).skip(1) {
+            pv.vel += a * dt / 2.0;
+            pv.pos += pv.vel * dt;
+        }
+        let a = self.accelerations();
+        for (pv, a) in self.pvs.iter_mut().zip(&a).skip(1) {
+            pv.vel += a * dt / 2.0;
+        }
+    }
+
+    /// Integrates forward to the given time.
+    pub fn propagate_to(&mut self, stamp: Nanotime) {
+        let total = (stamp - self.stamp).to_secs_f64();
+        if total <= 0.0 {
+            return;
+        }
+        let steps = (total / MAX_PLANET_STEP).ceil().max(1.0);
+        let dt = total / steps;
+        for _ in 0..steps as u64 {
+            self.step(dt);
+        }
+        self.stamp = stamp;
+    }
+
+    /// Replaces the orbits of every moon in the system with the osculating
+    /// orbits of the integrated state, so that lookups at the current time
+    /// return the integrated positions. Each orbit uses the combined mu of
+    /// the pair, as the integration does, so it predicts the moon's motion
+    /// about its parent until other bodies perturb it.
+    pub fn apply_to(&self, planets: &mut PlanetarySystem) {
+        for (orbit, sub) in &mut planets.subsystems {
+            self.apply_inner(planets.body, orbit, sub);
+        }
+    }
+
+    fn apply_inner(&self, parent_body: Body, orbit: &mut SparseOrbit, sys: &mut PlanetarySystem) {
+        if let Some(i) = self.ids.iter().position(|e| *e == sys.id) {
+            if let Some(p) = self.parents[i] {
+                let rel = self.pvs[i] - self.pvs[p];
+                let pair = Body {
+                    mu: parent_body.mu + sys.body.mu,
+                    ..parent_body
+                };
+                if let Some(o) = SparseOrbit::from_pv(rel, pair, self.stamp) {
+                    *orbit = o;
+                }
+            }
+        }
+        for (orbit, sub) in &mut sys.subsystems {
+            self.apply_inner(sys.body, orbit, sub);
+        }
+    }
+}
+
+/// Distances between where patched conics and N-body propagation place
+/// things at some point in time, in meters.
+#[derive(Debug, Clone)]
+pub struct DivergenceSample {
+    pub stamp: Nanotime,
+    /// Divergence of each moon's position
+    pub planets: Vec<(EntityId, f64)>,
+    /// Divergence of the test vehicle's position relative to its parent,
+    /// unless it's left the parent's sphere of influence under patched
+    /// conics
+    pub vehicle: Option<f64>,
+}
+
+/// Propagates a planetary system and a coasting vehicle with both patched
+/// conics and full N-body gravity, and reports how far apart the two
+/// predictions are at regular intervals.
+pub fn patched_conics_divergence(
+    planets: &PlanetarySystem,
+    vehicle: GlobalOrbit,
+    start: Nanotime,
+    duration: Nanotime,
+    interval: Nanotime,
+) -> Option<Vec<DivergenceSample>> {
+    const VEHICLE_ID: EntityId = EntityId(-1);
+
+    let (_, parent_pv, _, _) = planets.lookup(vehicle.0, start)?;
+    let mut nbody = NBodyPlanets::new(planets, start);
+    nbody.add_particle(VEHICLE_ID, parent_pv + vehicle.1.pv(start).ok()?);
+
+    let moons: Vec<_> = planets
+        .planet_ids()
+        .into_iter()
+        .filter(|id| *id != planets.id)
+        .collect();
+
+    let mut ret = vec![];
+    let mut stamp = start;
+    while stamp <= start + duration {
+        nbody.propagate_to(stamp);
+
+        let planets_div = moons
+            .iter()
+            .filter_map(|id| {
+                let (_, kepler, _, _) = planets.lookup(*id, stamp)?;
+                Some((*id, kepler.pos.distance(nbody.pv(*id)?.pos)))
+            })
+            .collect();
+
+        let vehicle_div = (|| {
+            let kepler = vehicle.1.pv(stamp).ok()?.pos;
+            let integrated = nbody.pv(VEHICLE_ID)?.pos - nbody.pv(vehicle.0)?.pos;
+            Some(kepler.distance(integrated))
+        })();
+
+        ret.push(DivergenceSample {
+            stamp,
+            planets: planets_div,
+            vehicle: vehicle_div,
+        });
+
+        stamp += interval;
+    }
+
+    Some(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn earth_moon() -> (PlanetarySystem, EntityId) {
+        let earth = Body::with_mu(EARTH_RADIUS, EARTH_MU, EARTH_SOI);
+        let luna = Body::with_mu(LUNA_RADIUS, LUNA_MU, LUNA_SOI);
+        let mut system = PlanetarySystem::new(EntityId(0), "Earth", earth);
+        let orbit = SparseOrbit::circular(LUNA_ORBITAL_RADIUS, earth, Nanotime::zero(), false);
+        system.orbit(orbit, PlanetarySystem::new(EntityId(1), "Luna", luna));
+        (system, EntityId(1))
+    }
+
+    #[test]
+    fn massless_moon_matches_kepler() {
+        let (mut system, luna) = earth_moon();
+        system.subsystems[0].1.body.mu = 0.0;
+        let mut nbody = NBodyPlanets::new(&system, Nanotime::zero());
+        let t = Nanotime::days(14);
+        nbody.propagate_to(t);
+        let (_, kepler, _, _) = system.lookup(luna, t).unwrap();
+        let d = kepler.pos.distance(nbody.pv(luna).unwrap().pos);
+        assert!(d < 1000.0, "{d}");
+    }
+
+    #[test]
+    fn applied_state_is_looked_up() {
+        let (mut system, luna) = earth_moon();
+        let mut nbody = NBodyPlanets::new(&system, Nanotime::zero());
+        let t = Nanotime::days(3);
+        nbody.propagate_to(t);
+        nbody.apply_to(&mut system);
+        let (_, pv, _, _) = system.lookup(luna, t).unwrap();
+        let expected = nbody.pv(luna).unwrap();
+        assert!(pv.pos.distance(expected.pos) < 1.0, "{pv:?} {expected:?}");
+        assert!(pv.vel.distance(expected.vel) < 1E-3, "{pv:?} {expected:?}");
+    }
+
+    #[test]
+    fn applied_orbits_follow_the_integration() {
+        let (mut system, luna) = earth_moon();
+        let mut nbody = NBodyPlanets::new(&system, Nanotime::zero());
+        nbody.propagate_to(Nanotime::days(3));
+        nbody.apply_to(&mut system);
+        let t = Nanotime::days(4);
+        nbody.propagate_to(t);
+        let (_, pv, _, _) = system.lookup(luna, t).unwrap();
+        let d = pv.pos.distance(nbody.pv(luna).unwrap().pos);
+        assert!(d < 1000.0, "{d}");
+    }
+
+    #[test]
+    fn moons_on_rails_feel_no_other_moons() {
+        let (mut system, luna) = earth_moon();
+        let earth = system.body;
+        let other = Body::with_mu(LUNA_RADIUS, LUNA_MU, LUNA_SOI);
+        let orbit =
+            SparseOrbit::circular(LUNA_ORBITAL_RADIUS * 0.6, earth, Nanotime::zero(), false);
+        system.orbit(orbit, PlanetarySystem::new(EntityId(2), "Other", other));
+
+        let stamp = Nanotime::zero();
+        let (_, moon, _, _) = system.lookup(luna, stamp).unwrap();
+        let (_, pull, _, _) = system.lookup(EntityId(2), stamp).unwrap();
+        let field = GravityField::new(&system, luna, stamp, GravityModel::NBodyVehicles).unwrap();
+
+        // at the center of the moon, all that's left is the other moon,
+        // which doesn't pull on this one while the planets are on rails
+        let expected = other.gravity(moon.pos - pull.pos);
+        let a = field.accel(DVec2::ZERO);
+        assert!(
+            a.distance(expected) < expected.length() * 1E-6,
+            "{a} {expected}"
+        );
+    }
+
+    #[test]
+    fn summed_field_reduces_to_parent_far_from_moons() {
+        let (system, _) = earth_moon();
+        let stamp = Nanotime::zero();
+        let single =
+            GravityField::new(&system, EntityId(0), stamp, GravityModel::PatchedConics).unwrap();
+        let summed = GravityField::new(&system, EntityId(0), stamp, GravityModel::NBody).unwrap();
+        let p = DVec2::new(-(EARTH_RADIUS + 400_000.0), 0.0);
+        let (a, b) = (single.accel(p), summed.accel(p));
+        // tidal acceleration from the moon is tiny next to earth's gravity
+        assert!(a.distance(b) < a.length() * 1E-6, "{a} {b}");
+        assert!(a.distance(b) > 0.0);
+    }
+
+    #[test]
+    fn divergence_grows_from_zero() {
+        let (system, _) = earth_moon();
+        let orbit = SparseOrbit::circular(
+            EARTH_RADIUS + 20_000_000.0,
+            system.body,
+            Nanotime::zero(),
+            false,
+        );
+        let samples = patched_conics_divergence(
+            &system,
+            GlobalOrbit(EntityId(0), orbit),
+            Nanotime::zero(),
+            Nanotime::days(2),
+            Nanotime::hours(12),
+        )
+        .unwrap();
+        assert_eq!(samples.len(), 5);
+        assert!(samples[0].vehicle.unwrap() < 1E-6);
+        assert!(samples[0].planets[0].1 < 1E-6);
+        let last = samples.last().unwrap();
+        assert!(last.vehicle.unwrap() > 1.0);
+        assert!(last.planets[0].1 > 1.0);
+    }
+}
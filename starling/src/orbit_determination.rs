@@ -0,0 +1,353 @@
+use crate::orbits::universal_lagrange;
+use crate::prelude::*;
+use glam::{DMat3, DMat4, DVec3, DVec4};
+
+/// Minimum number of observations required before attempting a fit.
+pub const MIN_OBSERVATIONS_FOR_FIT: usize = 6;
+
+const MAX_ITERATIONS: usize = 20;
+
+/// Finite difference step sizes for position (m) and velocity (m/s).
+const JACOBIAN_STEPS: DVec4 = DVec4::new(1.0, 1.0, 1E-3, 1E-3);
+
+/// Matrices with a smaller determinant than this are treated as singular.
+const SINGULAR_DETERMINANT: f64 = 1E-12;
+
+/// A single range/bearing measurement of a target, expressed relative to
+/// the body the target is assumed to be orbiting.
+#[derive(Debug, Clone, Copy)]
+pub struct Observation {
+    pub stamp: Nanotime,
+    /// Position of the observer in the reference body's frame
+    pub observer_pos: DVec2,
+    pub range: f64,
+    pub bearing: f64,
+    pub range_sigma: f64,
+    pub bearing_sigma: f64,
+}
+
+impl Observation {
+    /// Converts a world-frame detection into the frame of a body whose
+    /// center was at `origin` at the time of the detection.
+    pub fn from_detection(det: &Detection, origin: DVec2) -> Self {
+        Self {
+            stamp: det.stamp,
+            observer_pos: det.observer_pos - origin,
+            range: det.range,
+            bearing: det.bearing,
+            range_sigma: det.range_sigma,
+            bearing_sigma: det.bearing_sigma,
+        }
+    }
+
+    pub fn position(&self) -> DVec2 {
+        self.observer_pos + rotate_f64(DVec2::X * self.range, self.bearing)
+    }
+
+    fn residuals(&self, predicted: DVec2) -> DVec2 {
+        let delta = predicted - self.observer_pos;
+        let dr = (self.range - delta.length()) / self.range_sigma.max(1E-3);
+        let db = wrap_pi_npi_f64(self.bearing - delta.to_angle()) / self.bearing_sigma.max(1E-9);
+        DVec2::new(dr, db)
+    }
+}
+
+/// The result of fitting an orbit to a set of observations.
+#[derive(Debug, Clone, Copy)]
+pub struct OrbitEstimate {
+    pub parent: EntityId,
+    pub body: Body,
+    pub epoch: Nanotime,
+    /// Estimated state at the epoch, relative to the parent body
+    pub pv: PV,
+    /// Covariance of [x, y, vx, vy] at the epoch
+    pub covariance: DMat4,
+    /// Root-mean-square of the normalized residuals
+    pub residual_rms: f64,
+    pub num_observations: usize,
+}
+
+impl OrbitEstimate {
+    pub fn orbit(&self) -> Option<SparseOrbit> {
+        SparseOrbit::from_pv(self.pv, self.body, self.epoch)
+    }
+
+    pub fn global_orbit(&self) -> Option<GlobalOrbit> {
+        Some(GlobalOrbit(self.parent, self.orbit()?))
+    }
+
+    /// One-sigma position uncertainty at the epoch, in meters.
+    pub fn position_sigma(&self) -> f64 {
+        (self.covariance.x_axis.x + self.covariance.y_axis.y)
+            .max(0.0)
+            .sqrt()
+    }
+
+    /// One-sigma velocity uncertainty at the epoch, in meters per second.
+    pub fn velocity_sigma(&self) -> f64 {
+        (self.covariance.z_axis.z + self.covariance.w_axis.w)
+            .max(0.0)
+            .sqrt()
+    }
+}
+
+fn to_state(pv: PV) -> DVec4 {
+    DVec4::new(pv.pos.x, pv.pos.y, pv.vel.x, pv.vel.y)
+}
+
+fn from_state(x: DVec4) -> PV {
+    PV::from_f64(DVec2::new(x.x, x.y), DVec2::new(x.z, x.w))
+}
+
+fn predict(x: DVec4, epoch: Nanotime, stamp: Nanotime, mu: f64) -> Option<DVec2> {
+    let (_, res) = universal_lagrange(from_state(x), stamp - epoch, mu);
+    Some(res?.pv.pos)
+}
+
+fn outer(a: DVec4, b: DVec4) -> DMat4 {
+    DMat4::from_cols(a * b.x, a * b.y, a * b.z, a * b.w)
+}
+
+fn cost(obs: &[Observation], x: DVec4, epoch: Nanotime, mu: f64) -> Option<f64> {
+    let mut sum = 0.0;
+    for o in obs {
+        sum += o
+            .residuals(predict(x, epoch, o.stamp, mu)?)
+            .length_squared();
+    }
+    Some(sum)
+}
+
+/// Fits a quadratic in time to the measured positions, yielding a rough
+/// position and velocity at the epoch suitable for seeding the solver.
+pub fn initial_state_guess(obs: &[Observation], epoch: Nanotime) -> Option<PV> {
+    if obs.len() < 3 {
+        return None;
+    }
+
+    let mut ata = DMat3::ZERO;
+    let mut atx = DVec3::ZERO;
+    let mut aty = DVec3::ZERO;
+    for o in obs {
+        let t = (o.stamp - epoch).to_secs_f64();
+        let row = DVec3::new(1.0, t, 0.5 * t * t);
+        ata += DMat3::from_cols(row * row.x, row * row.y, row * row.z);
+        let p = o.position();
+        atx += row * p.x;
+        aty += row * p.y;
+    }
+
+    if ata.determinant().abs() < SINGULAR_DETERMINANT {
+        return None;
+    }
+
+    let inv = ata.inverse();
+    let cx = inv * atx;
+    let cy = inv * aty;
+    Some(PV::from_f64(DVec2::new(cx.x, cy.x), DVec2::new(cx.y, cy.y)))
+}
+
+/// Normal matrix and right hand side of the Gauss-Newton step for the
+/// state `x`, built from finite difference jacobians of the residuals.
+fn normal_equations(
+    obs: &[Observation],
+    x: DVec4,
+    epoch: Nanotime,
+    mu: f64,
+) -> Result<(DMat4, DVec4), &'static str> {
+    let mut normal = DMat4::ZERO;
+    let mut rhs = DVec4::ZERO;
+
+    for o in obs {
+        let nominal = predict(x, epoch, o.stamp, mu).ok_or("Propagation failed")?;
+        let r0 = o.residuals(nominal);
+
+        // jacobian of the (range, bearing) residuals with respect to the state
+        let mut jr = DVec4::ZERO;
+        let mut jb = DVec4::ZERO;
+        for i in 0..4 {
+            let mut dx = DVec4::ZERO;
+            dx[i] = JACOBIAN_STEPS[i];
+            let p = predict(x + dx, epoch, o.stamp, mu).ok_or("Propagation failed")?;
+            let r = o.residuals(p);
+            // residual is measured minus predicted, so the sign flips
+            jr[i] = -(r.x - r0.x) / JACOBIAN_STEPS[i];
+            jb[i] = -wrap_pi_npi_f64(r.y - r0.y) / JACOBIAN_STEPS[i];
+        }
+
+        normal += outer(jr, jr) + outer(jb, jb);
+        rhs += jr * r0.x + jb * r0.y;
+    }
+
+    if normal.determinant().abs() < SINGULAR_DETERMINANT {
+        return Err("Observations do not constrain the orbit");
+    }
+
+    Ok((normal, rhs))
+}
+
+/// Batch weighted least-squares orbit determination. Starting from an
+/// initial guess of the state at `epoch`, iteratively refines the state
+/// by Gauss-Newton using universal Lagrange coefficients to predict the
+/// target position at each observation time.
+pub fn fit_orbit(
+    obs: &[Observation],
+    parent: EntityId,
+    body: Body,
+    epoch: Nanotime,
+    guess: PV,
+) -> Result<OrbitEstimate, &'static str> {
+    if obs.len() < MIN_OBSERVATIONS_FOR_FIT {
+        return Err("Not enough observations");
+    }
+
+    let mu = body.mu;
+    let mut x = to_state(guess);
+    let mut current_cost = cost(obs, x, epoch, mu).ok_or("Failed to propagate guess")?;
+
+    for _ in 0..MAX_ITERATIONS {
+        let (normal, rhs) = normal_equations(obs, x, epoch, mu)?;
+        let mut delta = normal.inverse() * rhs;
+
+        // halve the step until the cost stops increasing
+        let mut accepted = false;
+        for _ in 0..8 {
+            if let Some(c) = cost(obs, x + delta, epoch, mu) {
+                if c <= current_cost {
+                    x += delta;
+                    current_cost = c;
+                    accepted = true;
+                    break;
+                }
+            }
+            delta *= 0.5;
+        }
+
+        if !accepted || DVec2::new(delta.x, delta.y).length() < 1E-3 {
+            break;
+        }
+    }
+
+    if !x.is_finite() {
+        return Err("Solution diverged");
+    }
+
+    // covariance of the final state, not the one before the last step
+    let (normal, _) = normal_equations(obs, x, epoch, mu)?;
+
+    Ok(OrbitEstimate {
+        parent,
+        body,
+        epoch,
+        pv: from_state(x),
+        covariance: normal.inverse(),
+        residual_rms: (current_cost / (2 * obs.len()) as f64).sqrt(),
+        num_observations: obs.len(),
+    })
+}
+
+/// Fits an orbit to the history of a sensor track, relative to the body
+/// which dominates gravitationally at the target's most recent position.
+/// If the track already has an estimate about the same body, it is used
+/// to seed the solver.
+pub fn estimate_track_orbit(
+    track: &SensorTrack,
+    planets: &PlanetarySystem,
+) -> Result<OrbitEstimate, &'static str> {
+    let latest = track.latest();
+    let parent = nearest_relevant_body(planets, latest.position(), latest.stamp)
+        .ok_or("No relevant body")?;
+    let (body, _, _, _) = planets.lookup(parent, latest.stamp).ok_or("No body")?;
+
+    let obs: Vec<Observation> = track
+        .history()
+        .iter()
+        .filter_map(|det| {
+            let (_, origin, _, _) = planets.lookup(parent, det.stamp)?;
+            Some(Observation::from_detection(det, origin.pos))
+        })
+        .collect();
+
+    let epoch = obs.last().ok_or("No observations")?.stamp;
+
+    let previous = track.estimate().filter(|e| e.parent == parent);
+    let guess = match previous.and_then(|e| e.orbit()).map(|o| o.pv(epoch)) {
+        Some(Ok(pv)) => pv,
+        _ => initial_state_guess(&obs, epoch).ok_or("Failed to seed estimate")?,
+    };
+
+    fit_orbit(&obs, parent, body, epoch, guess)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn observe(
+        orbit: &SparseOrbit,
+        observer: DVec2,
+        stamp: Nanotime,
+        noise: Option<&mut StdRng>,
+    ) -> Observation {
+        let truth = orbit.pv(stamp).unwrap().pos;
+        let delta = truth - observer;
+        let (rs, bs) = (50.0, 1E-4);
+        let (dr, db) = match noise {
+            Some(rng) => (randn_from(rng, 0.0, rs), randn_from(rng, 0.0, bs)),
+            None => (0.0, 0.0),
+        };
+        Observation {
+            stamp,
+            observer_pos: observer,
+            range: delta.length() + dr,
+            bearing: delta.to_angle() + db,
+            range_sigma: rs,
+            bearing_sigma: bs,
+        }
+    }
+
+    fn test_orbit() -> SparseOrbit {
+        SparseOrbit::new(
+            4_000_000.0,
+            2_500_000.0,
+            0.4,
+            Body::LUNA,
+            Nanotime::zero(),
+            false,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn fit_recovers_true_orbit() {
+        let orbit = test_orbit();
+        let observer = DVec2::new(-3_000_000.0, 1_000_000.0);
+        let mut rng = StdRng::seed_from_u64(27);
+
+        let obs: Vec<_> = (0..60)
+            .map(|i| observe(&orbit, observer, Nanotime::secs(i * 20), Some(&mut rng)))
+            .collect();
+
+        let epoch = obs.last().unwrap().stamp;
+        let guess = initial_state_guess(&obs, epoch).unwrap();
+        let est = fit_orbit(&obs, EntityId(0), Body::LUNA, epoch, guess).unwrap();
+
+        let truth = orbit.pv(epoch).unwrap();
+        assert!(est.pv.pos.distance(truth.pos) < 500.0);
+        assert!(est.pv.vel.distance(truth.vel) < 1.0);
+        assert!(est.position_sigma() < 500.0);
+        assert!(est.residual_rms < 3.0);
+    }
+
+    #[test]
+    fn fit_requires_observations() {
+        let orbit = test_orbit();
+        let obs: Vec<_> = (0..3)
+            .map(|i| observe(&orbit, DVec2::ZERO, Nanotime::secs(i), None))
+            .collect();
+        let guess = orbit.pv(Nanotime::secs(2)).unwrap();
+        assert!(fit_orbit(&obs, EntityId(0), Body::LUNA, Nanotime::secs(2), guess).is_err());
+    }
+}
@@ -1,139 +1,190 @@
-use crate::math::{apply, lerp_f64, linspace_f64, PI_64};
-use crate::nanotime::Nanotime;
-use crate::orbits::{Body, OrbitClass, SparseOrbit};
+use crate::math::{lerp_f64, linspace_f64, wrap_0_2pi_f64, PI_64};
 use lazy_static::lazy_static;
-use splines::{Interpolation, Key, Spline};
-use std::collections::HashMap;
-
-const ECCENTRICITY_STEP: u8 = 1;
-const N_SAMPLES: usize = 500;
-
-#[derive(Debug, Clone)]
-struct DenseOrbit(Spline<f64, f64>);
-
-impl DenseOrbit {
-    pub fn new(orbit: &SparseOrbit) -> Result<Self, &'static str> {
-        let n_samples = match orbit.class() {
-            OrbitClass::NearCircular => 1000,
-            OrbitClass::Circular => 1000,
-            OrbitClass::Elliptical => 1000,
-            OrbitClass::HighlyElliptical => 1000,
-            OrbitClass::Parabolic => return Err("Parabolic"),
-            OrbitClass::Hyperbolic => return Err("Hyperbolic"),
-            OrbitClass::VeryThin => return Err("Too thin"),
-        };
-
-        let sample_space = linspace_f64(-0.25, 1.25, n_samples);
-
-        let period = orbit.period().ok_or("No period")?;
-        let ta = orbit.t_next_p(orbit.epoch).ok_or("No next periapsis")?;
-        let start = ta;
-        let end = ta + period;
-        let mut samples = vec![];
-        let mut prev = None;
-
-        let mut wrap_monotonic = |ta: f64| {
-            let mut ta = ta;
-            if let Some(prev) = prev {
-                while prev > ta {
-                    ta += PI_64 * 2.0;
-                }
-            }
-            prev = Some(ta);
-            ta
-        };
-
-        for s in sample_space {
-            let t = start.lerp(end, s as f32);
-            let ta = orbit.ta_at_time(t).ok_or("Bad true anomaly")?;
-            let ta = wrap_monotonic(ta);
-            samples.push(((t - start).to_secs_f64() / period.to_secs_f64(), ta));
-        }
 
-        let mut keys = vec![];
-        for (i, (dt, ta)) in samples.iter().enumerate() {
-            let interp = if i == 0 {
-                Interpolation::Linear
-            } else if i + 2 < samples.len() {
-                Interpolation::CatmullRom
-            } else {
-                Interpolation::Linear
-            };
-            let key = Key::new(*dt, *ta, interp);
-            keys.push(key);
-        }
+// Tables map eccentric (or hyperbolic) anomaly to mean anomaly, sampled
+// uniformly in the eccentric anomaly. Since the mean anomaly changes
+// slowly near periapsis, this samples most densely exactly where the
+// inverse function is steepest. The table lookup only provides an
+// initial guess; a few Newton iterations on Kepler's equation then
+// bring the result down to `KEPLER_TOLERANCE`.
 
-        let spline = Spline::<f64, f64>::from_vec(keys);
+const N_SAMPLES: usize = 256;
 
-        Ok(Self(spline))
-    }
+/// Largest eccentricity handled by the elliptical tables.
+pub const MAX_ELLIPTICAL_ECCENTRICITY: f64 = 0.99;
 
-    pub fn sample_normalized(&self, s: f64) -> f64 {
-        self.0.sample(s).unwrap()
-    }
-}
+/// Smallest eccentricity handled by the hyperbolic tables. Orbits between
+/// this and `MAX_ELLIPTICAL_ECCENTRICITY` are close enough to parabolic
+/// that they should be propagated with universal variables instead.
+pub const MIN_HYPERBOLIC_ECCENTRICITY: f64 = 1.01;
 
-fn get_orbit_with_ecc(ecc: f64) -> Vec<f64> {
-    let a = 1000.0;
-    let ra = a * (1.0 + ecc);
-    let rp = a * (1.0 - ecc);
-    let argp = 0.0;
-    let body = Body {
-        radius: 1.0,
-        mu: 1000.0 * 12000.0,
-        soi: 100000.0,
-    };
-    let epoch = Nanotime::zero();
-    let retrograde = false;
-    let orbit = SparseOrbit::new(ra, rp, argp, body, epoch, retrograde).unwrap();
-    let dense = DenseOrbit::new(&orbit).unwrap();
+const ELLIPTICAL_ECC_STEP: f64 = 0.01;
+const HYPERBOLIC_ECC_STEP: f64 = 0.05;
+const MAX_TABULATED_HYPERBOLIC_ECCENTRICITY: f64 = 5.0;
 
-    let s = linspace_f64(0.0, 1.0, N_SAMPLES);
-    apply(&s, |s: f64| dense.sample_normalized(s) - s * 2.0 * PI_64)
+/// Range of hyperbolic anomaly covered by the hyperbolic tables.
+const MAX_HYPERBOLIC_ANOMALY: f64 = 6.0;
+
+/// Residual of Kepler's equation, in radians, below which a lookup is
+/// considered converged.
+pub const KEPLER_TOLERANCE: f64 = 1E-12;
+
+const MAX_NEWTON_ITERATIONS: usize = 8;
+
+fn elliptical_mean_anomaly(e_anom: f64, ecc: f64) -> f64 {
+    e_anom - ecc * e_anom.sin()
 }
 
-lazy_static! {
-    pub static ref BIG_ORBITS: HashMap<u8, Vec<f64>> = HashMap::from_iter(
-        (0..=93)
-            .step_by(ECCENTRICITY_STEP as usize)
-            .map(|e| (e, get_orbit_with_ecc(e as f64 / 100.0)))
-    );
+fn hyperbolic_mean_anomaly(h_anom: f64, ecc: f64) -> f64 {
+    ecc * h_anom.sinh() - h_anom
 }
 
-fn fmod(a: f64, n: f64) -> f64 {
-    a - n * (a / n).floor()
+struct KeplerTable {
+    ecc: f64,
+    anomalies: Vec<f64>,
+    mean_anomalies: Vec<f64>,
 }
 
-pub fn lookup_ta_from_ma(ma: f64, ecc: f64) -> Option<f64> {
-    let ma = fmod(ma, 2.0 * PI_64);
+impl KeplerTable {
+    fn elliptical(ecc: f64) -> Self {
+        let anomalies = linspace_f64(0.0, 2.0 * PI_64, N_SAMPLES);
+        let mean_anomalies = anomalies
+            .iter()
+            .map(|e| elliptical_mean_anomaly(*e, ecc))
+            .collect();
+        Self {
+            ecc,
+            anomalies,
+            mean_anomalies,
+        }
+    }
 
-    let ei = (ecc * 100.0) as u8;
+    fn hyperbolic(ecc: f64) -> Self {
+        let anomalies = linspace_f64(-MAX_HYPERBOLIC_ANOMALY, MAX_HYPERBOLIC_ANOMALY, N_SAMPLES);
+        let mean_anomalies = anomalies
+            .iter()
+            .map(|f| hyperbolic_mean_anomaly(*f, ecc))
+            .collect();
+        Self {
+            ecc,
+            anomalies,
+            mean_anomalies,
+        }
+    }
 
-    let el = ei - (ei % ECCENTRICITY_STEP);
-    let eu = el + ECCENTRICITY_STEP;
-    let sy = ((ecc * 100.0) - (el as f64)) / ECCENTRICITY_STEP as f64;
+    /// Linearly interpolated anomaly for the given mean anomaly, or None
+    /// if it lies outside the table.
+    fn lookup(&self, ma: f64) -> Option<f64> {
+        let m = &self.mean_anomalies;
+        if ma < m[0] || ma > m[m.len() - 1] {
+            return None;
+        }
+        let i = m.partition_point(|x| *x < ma).clamp(1, m.len() - 1);
+        let s = (ma - m[i - 1]) / (m[i] - m[i - 1]);
+        Some(lerp_f64(self.anomalies[i - 1], self.anomalies[i], s))
+    }
+}
 
-    let lower = BIG_ORBITS.get(&el)?;
-    let upper = BIG_ORBITS.get(&eu)?;
+fn make_tables(start: f64, end: f64, step: f64, f: fn(f64) -> KeplerTable) -> Vec<KeplerTable> {
+    let n = ((end - start) / step).round() as usize + 1;
+    linspace_f64(start, end, n).into_iter().map(f).collect()
+}
 
-    let x1 = ((ma / (2.0 * PI_64)) * (N_SAMPLES - 1) as f64) as usize;
-    let x2 = x1 + 1;
+lazy_static! {
+    static ref ELLIPTICAL_TABLES: Vec<KeplerTable> = make_tables(
+        0.0,
+        MAX_ELLIPTICAL_ECCENTRICITY,
+        ELLIPTICAL_ECC_STEP,
+        KeplerTable::elliptical
+    );
+    static ref HYPERBOLIC_TABLES: Vec<KeplerTable> = make_tables(
+        MIN_HYPERBOLIC_ECCENTRICITY,
+        MAX_TABULATED_HYPERBOLIC_ECCENTRICITY,
+        HYPERBOLIC_ECC_STEP,
+        KeplerTable::hyperbolic
+    );
+}
 
-    let ma_x1 = (x1 as f64 / (N_SAMPLES - 1) as f64) * 2.0 * PI_64;
-    let ma_x2 = (x2 as f64 / (N_SAMPLES - 1) as f64) * 2.0 * PI_64;
+/// Interpolates between the two tables bracketing the given eccentricity.
+fn table_guess(tables: &[KeplerTable], ma: f64, ecc: f64) -> Option<f64> {
+    let i = tables
+        .partition_point(|t| t.ecc < ecc)
+        .min(tables.len() - 1);
+    if i == 0 || tables[i].ecc == ecc {
+        return tables[i].lookup(ma);
+    }
+    let (lower, upper) = (&tables[i - 1], &tables[i]);
+    let s = (ecc - lower.ecc) / (upper.ecc - lower.ecc);
+    Some(lerp_f64(lower.lookup(ma)?, upper.lookup(ma)?, s))
+}
+
+/// Solves Kepler's equation for the eccentric anomaly, given the mean
+/// anomaly in [0, 2pi).
+fn solve_elliptical(ma: f64, ecc: f64) -> Option<f64> {
+    let mut e_anom = table_guess(&ELLIPTICAL_TABLES, ma, ecc)?;
+    for _ in 0..MAX_NEWTON_ITERATIONS {
+        let f = elliptical_mean_anomaly(e_anom, ecc) - ma;
+        if f.abs() < KEPLER_TOLERANCE {
+            return Some(e_anom);
+        }
+        e_anom -= f / (1.0 - ecc * e_anom.cos());
+    }
+    let f = elliptical_mean_anomaly(e_anom, ecc) - ma;
+    (f.abs() < KEPLER_TOLERANCE).then_some(e_anom)
+}
 
-    let sx = (ma - ma_x1) / (ma_x2 - ma_x1);
+/// Solves the hyperbolic Kepler equation for the hyperbolic anomaly.
+fn solve_hyperbolic(ma: f64, ecc: f64) -> Option<f64> {
+    let guess = if ecc <= MAX_TABULATED_HYPERBOLIC_ECCENTRICITY {
+        table_guess(&HYPERBOLIC_TABLES, ma, ecc)
+    } else {
+        None
+    };
 
-    let x1y1 = lower[x1 % N_SAMPLES];
-    let x1y2 = upper[x1 % N_SAMPLES];
+    // far from periapsis, e * sinh(F) dominates the equation
+    let mut h_anom = guess.unwrap_or_else(|| (ma / ecc).asinh());
 
-    let x2y1 = lower[x2 % N_SAMPLES];
-    let x2y2 = upper[x2 % N_SAMPLES];
+    for _ in 0..MAX_NEWTON_ITERATIONS {
+        let f = hyperbolic_mean_anomaly(h_anom, ecc) - ma;
+        if f.abs() < KEPLER_TOLERANCE * ma.abs().max(1.0) {
+            return Some(h_anom);
+        }
+        h_anom -= f / (ecc * h_anom.cosh() - 1.0);
+    }
+    let f = hyperbolic_mean_anomaly(h_anom, ecc) - ma;
+    (f.abs() < KEPLER_TOLERANCE * ma.abs().max(1.0)).then_some(h_anom)
+}
 
-    let p1 = lerp_f64(x1y1, x1y2, sy);
-    let p2 = lerp_f64(x2y1, x2y2, sy);
+/// Looks up the true anomaly for the given mean anomaly and eccentricity.
+///
+/// For elliptical orbits, the mean anomaly is wrapped into [0, 2pi) and
+/// the true anomaly is returned in the same range. For hyperbolic orbits,
+/// the mean anomaly is negative before periapsis and positive after, and
+/// the true anomaly is returned in (-pi, pi).
+///
+/// Returns None for near-parabolic orbits, where eccentricity lies between
+/// `MAX_ELLIPTICAL_ECCENTRICITY` and `MIN_HYPERBOLIC_ECCENTRICITY`.
+pub fn lookup_ta_from_ma(ma: f64, ecc: f64) -> Option<f64> {
+    if !ma.is_finite() || !ecc.is_finite() || ecc < 0.0 {
+        return None;
+    }
 
-    Some(lerp_f64(p1, p2, sx) + ma)
+    if ecc <= MAX_ELLIPTICAL_ECCENTRICITY {
+        let ma = wrap_0_2pi_f64(ma);
+        let e_anom = solve_elliptical(ma, ecc)?;
+        let ta = 2.0
+            * f64::atan2(
+                (1.0 + ecc).sqrt() * (e_anom / 2.0).sin(),
+                (1.0 - ecc).sqrt() * (e_anom / 2.0).cos(),
+            );
+        Some(wrap_0_2pi_f64(ta))
+    } else if ecc >= MIN_HYPERBOLIC_ECCENTRICITY {
+        let h_anom = solve_hyperbolic(ma, ecc)?;
+        let ta = 2.0 * (((ecc + 1.0) / (ecc - 1.0)).sqrt() * (h_anom / 2.0).tanh()).atan();
+        Some(ta)
+    } else {
+        None
+    }
 }
 
 #[cfg(test)]
@@ -153,4 +204,33 @@ mod tests {
             assert_float_absolute_eq!(lookup_ta_from_ma(ma, 0.0).unwrap(), ma, 1E-2);
         }
     }
+
+    #[test]
+    fn lut_solves_kepler_equation() {
+        for ecc in linspace_f64(0.0, MAX_ELLIPTICAL_ECCENTRICITY, 57) {
+            for ma in linspace_f64(0.0, 2.0 * PI_64, 301) {
+                let e_anom = solve_elliptical(wrap_0_2pi_f64(ma), ecc).unwrap();
+                let residual = elliptical_mean_anomaly(e_anom, ecc) - wrap_0_2pi_f64(ma);
+                assert!(residual.abs() < KEPLER_TOLERANCE, "e={ecc} M={ma}");
+            }
+        }
+
+        for ecc in linspace_f64(MIN_HYPERBOLIC_ECCENTRICITY, 12.0, 57) {
+            for ma in linspace_f64(-500.0, 500.0, 301) {
+                let h_anom = solve_hyperbolic(ma, ecc).unwrap();
+                let residual = hyperbolic_mean_anomaly(h_anom, ecc) - ma;
+                assert!(
+                    residual.abs() < KEPLER_TOLERANCE * ma.abs().max(1.0),
+                    "e={ecc} M={ma}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn lut_rejects_near_parabolic() {
+        assert!(lookup_ta_from_ma(0.3, 1.0).is_none());
+        assert!(lookup_ta_from_ma(0.3, 0.995).is_none());
+        assert!(lookup_ta_from_ma(0.3, 1.005).is_none());
+    }
 }
@@ -8,6 +8,20 @@ use glam::f32::Vec2;
 use glam::f64::DVec2;
 use serde::{Deserialize, Serialize};
 
+/// Distance from a body, as a multiple of its sphere of influence, past
+/// which an orbit about it has no position. Vehicles are handed over to the
+/// parent body as they leave the SOI, so this is only ever reached by
+/// predictions running far ahead on an escape trajectory, where the
+/// two-body solution no longer means anything and the far end of a
+/// hyperbola would otherwise overflow.
+const ESCAPE_RADIUS_IN_SOI: f64 = 3.0;
+
+/// Samples of the separation taken per orbital period of the faster of two
+/// orbits when looking for approaches between them.
+const APPROACH_SAMPLES_PER_PERIOD: i64 = 48;
+const MIN_APPROACH_SAMPLES: usize = 16;
+const MAX_APPROACH_SAMPLES: usize = 4096;
+
 pub fn hyperbolic_range_ta(ecc: f32) -> f32 {
     (-1.0 / ecc).acos()
 }
@@ -116,6 +130,9 @@ pub struct Body {
     pub radius: f64,
     pub mu: f64,
     pub soi: f64,
+    /// Sidereal rotation rate in radians per second, counterclockwise
+    #[serde(default)]
+    pub rotation_rate: f64,
 }
 
 impl Body {
@@ -123,6 +140,7 @@ impl Body {
         radius: 1_737_400.0,
         mu: 4.902800118E12,
         soi: 12_000_000.0,
+        rotation_rate: crate::quantities::LUNA_ROTATION_RATE,
     };
 
     pub const fn with_mass(radius: f64, mass: f64, soi: f64) -> Self {
@@ -130,11 +148,63 @@ impl Body {
             radius,
             mu: mass * 12000.0,
             soi,
+            rotation_rate: 0.0,
         }
     }
 
     pub const fn with_mu(radius: f64, mu: f64, soi: f64) -> Self {
-        Body { radius, mu, soi }
+        Body {
+            radius,
+            mu,
+            soi,
+            rotation_rate: 0.0,
+        }
+    }
+
+    pub const fn with_rotation_rate(mut self, rotation_rate: f64) -> Self {
+        self.rotation_rate = rotation_rate;
+        self
+    }
+
+    /// Acceleration due to gravity at the surface, in m/s^2.
+    pub fn surface_gravity(&self) -> f64 {
+        self.mu / (self.radius * self.radius)
+    }
+
+    /// Time taken to complete one full rotation, if the body rotates at all.
+    pub fn sidereal_day(&self) -> Option<Nanotime> {
+        (self.rotation_rate != 0.0)
+            .then(|| Nanotime::secs_f64(2.0 * PI_64 / self.rotation_rate.abs()))
+    }
+
+    /// Angle the body has rotated through since time zero, in [0, 2pi).
+    pub fn rotation_angle(&self, stamp: Nanotime) -> f64 {
+        let day = match self.sidereal_day() {
+            Some(d) => d,
+            None => return 0.0,
+        };
+        // reduce the stamp first, so the angle doesn't lose precision
+        // after many rotations
+        let t = stamp % day;
+        wrap_0_2pi_f64(self.rotation_rate * t.to_secs_f64())
+    }
+
+    /// Converts a position relative to the body's center from the
+    /// body-fixed frame to the inertial frame.
+    pub fn fixed_to_inertial(&self, pos: DVec2, stamp: Nanotime) -> DVec2 {
+        rotate_f64(pos, self.rotation_angle(stamp))
+    }
+
+    /// Converts a position relative to the body's center from the
+    /// inertial frame to the body-fixed frame.
+    pub fn inertial_to_fixed(&self, pos: DVec2, stamp: Nanotime) -> DVec2 {
+        rotate_f64(pos, -self.rotation_angle(stamp))
+    }
+
+    /// Velocity of a point at the given position relative to the body's
+    /// center, which is carried along by the body's rotation.
+    pub fn surface_velocity(&self, pos: DVec2) -> DVec2 {
+        pos.perp() * self.rotation_rate
     }
 
     pub fn mu(&self) -> f64 {
@@ -400,10 +470,13 @@ impl SparseOrbit {
         self.period().unwrap_or(fallback)
     }
 
+    /// Position and velocity at the given time. Orbits the lookup tables
+    /// cover, which is all but the nearly parabolic ones, are solved with
+    /// the tables; the rest fall back to universal variables.
     pub fn pv(&self, stamp: Nanotime) -> Result<PV, ULData> {
-        // if let Some(pv) = self.pv_lut(stamp) {
-        //     return Ok(pv);
-        // }
+        if let Some(pv) = self.pv_lut(stamp) {
+            return Ok(pv);
+        }
         self.pv_universal(stamp)
     }
 
@@ -416,18 +489,21 @@ impl SparseOrbit {
 
         let ul = universal_lagrange(self.initial, tof, self.body.mu());
         let sol = ul.1.ok_or(ul.0)?;
-        if sol.pv.pos.length() > 3.0 * self.body.soi {
+        if sol.pv.pos.length() > ESCAPE_RADIUS_IN_SOI * self.body.soi {
             return Err(ul.0);
         }
         Ok(sol.pv.filter_numerr().ok_or(ul.0)?)
     }
 
     pub fn pv_lut(&self, stamp: Nanotime) -> Option<PV> {
-        let ma = self.mean_anomaly(stamp)?;
+        let ma = self.kepler_mean_anomaly(stamp)?;
         let ta = crate::orbital_luts::lookup_ta_from_ma(ma, self.ecc())?;
         let pos = self.position_at(ta);
+        if pos.length() > ESCAPE_RADIUS_IN_SOI * self.body.soi {
+            return None;
+        }
         let vel = self.velocity_at(ta);
-        Some(PV::from_f64(pos, vel))
+        PV::from_f64(pos, vel).filter_numerr()
     }
 
     pub fn position_at(&self, true_anomaly: f64) -> DVec2 {
@@ -478,6 +554,31 @@ impl SparseOrbit {
         Some(2.0 * PI_64 * dt.to_secs_f64() / period.to_secs_f64())
     }
 
+    fn true_anomaly_at_epoch(&self) -> f64 {
+        let angle = self.initial.pos.to_angle();
+        let ta = if self.is_retrograde() {
+            -angle + self.arg_periapsis
+        } else {
+            angle - self.arg_periapsis
+        };
+        wrap_pi_npi_f64(ta)
+    }
+
+    /// Mean anomaly propagated from the state at epoch. Unlike `mean_anomaly`,
+    /// this doesn't depend on a known time of periapsis, so it also works for
+    /// highly eccentric and hyperbolic orbits. Hyperbolic mean anomaly is
+    /// negative before periapsis and positive after.
+    pub fn kepler_mean_anomaly(&self, stamp: Nanotime) -> Option<f64> {
+        let ecc = self.eccentricity;
+        if ecc == 1.0 {
+            return None;
+        }
+        let ta = Anomaly::with_ecc(ecc, self.true_anomaly_at_epoch());
+        let m0 = eccentric_to_mean(true_to_eccentric(ta, ecc), ecc).as_f64();
+        let dt = (stamp - self.epoch).to_secs_f64();
+        Some(m0 + self.mean_motion() * dt)
+    }
+
     pub fn orbit_number(&self, stamp: Nanotime) -> Option<i64> {
         let p = self.period()?;
         let dt = stamp - self.time_at_periapsis?;
@@ -503,6 +604,35 @@ impl SparseOrbit {
         Some(p * (n + 1) + tp)
     }
 
+    /// Next time at or after `current` at which the orbit passes through the
+    /// given radius, if it ever does.
+    pub fn t_next_radius(&self, radius: f64, current: Nanotime) -> Option<Nanotime> {
+        let ecc = self.eccentricity;
+        if ecc == 1.0 || radius <= 0.0 {
+            return None;
+        }
+        let cos_ta = (self.semi_latus_rectum() / radius - 1.0) / ecc;
+        if !(-1.0..=1.0).contains(&cos_ta) {
+            return None;
+        }
+        let m_now = self.kepler_mean_anomaly(current)?;
+        let n = self.mean_motion();
+        let ta = cos_ta.acos();
+        [ta, -ta]
+            .into_iter()
+            .filter_map(|ta| {
+                let ea = true_to_eccentric(Anomaly::with_ecc(ecc, ta), ecc);
+                let dm = eccentric_to_mean(ea, ecc).as_f64() - m_now;
+                let dm = if ecc < 1.0 {
+                    dm.rem_euclid(2.0 * PI_64)
+                } else {
+                    dm
+                };
+                (dm >= 0.0).then(|| current + Nanotime::secs_f64(dm / n))
+            })
+            .min()
+    }
+
     pub fn asymptotes(&self) -> Option<(DVec2, DVec2)> {
         if self.eccentricity < 1.0 {
             return None;
@@ -572,48 +702,86 @@ impl SparseOrbit {
         (ret, dist.abs() * sign)
     }
 
+    /// Closest and farthest approach between this orbit and another about
+    /// the same body, over the window starting at `now`. If the two come
+    /// within `distance` of each other around their closest approach, the
+    /// separation is falling through `distance` at `falling` and rising
+    /// back through it at `rising`; these are clipped to the window.
     pub fn timed_approach_info(
         &self,
         other: SparseOrbit,
         now: Nanotime,
         dur: Nanotime,
-    ) -> (Option<Nanotime>, Option<Nanotime>) {
-        // distance between orbits along a ray cast from planet object
-        let separation = |t: Nanotime| {
-            let d = self.pv(t).unwrap_or(PV::NAN) - other.pv(t).unwrap_or(PV::NAN);
-            d.pos.length_squared()
-        };
-
-        let teval = tspace(now, now + dur, 100);
-
-        let c3 = |t: Nanotime| separation(t) > 1000.0;
-        let c4 = |t: Nanotime| separation(t) < 1000.0;
-
-        let mut rising = None;
-        let mut falling = None;
+        distance: f64,
+    ) -> Option<ApproachInfo<Nanotime>> {
+        let relative = |t: Nanotime| Some(self.pv(t).ok()? - other.pv(t).ok()?);
+        let separation = |t: Nanotime| relative(t).map(|r| r.pos.length()).unwrap_or(f64::NAN);
+        let closing = |t: Nanotime| relative(t).is_some_and(|r| r.pos.dot(r.vel) < 0.0);
+        let opening = |t: Nanotime| relative(t).is_some_and(|r| r.pos.dot(r.vel) > 0.0);
+        let inside = |t: Nanotime| separation(t) < distance;
+        let outside = |t: Nanotime| separation(t) > distance;
+
+        // fine enough to resolve every approach on the faster orbit
+        let nsamples = [self.period(), other.period()]
+            .into_iter()
+            .flatten()
+            .min()
+            .map(|p| (dur.inner() / (p.inner() / APPROACH_SAMPLES_PER_PERIOD).max(1)) as usize + 1)
+            .unwrap_or(0)
+            .clamp(MIN_APPROACH_SAMPLES, MAX_APPROACH_SAMPLES);
+        let teval = tspace(now, now + dur, nsamples);
+        let rates: Vec<f64> = teval
+            .iter()
+            .map(|t| relative(*t).map(|r| r.pos.dot(r.vel)))
+            .collect::<Option<_>>()?;
+
+        // the separation only turns around where its rate changes sign
+        let tol = Nanotime::millis(1);
+        let mut extrema = vec![now, now + dur];
+        for (t, r) in teval.windows(2).zip(rates.windows(2)) {
+            let found = if r[0] < 0.0 && r[1] >= 0.0 {
+                search_condition(t[0], t[1], tol, &closing)
+            } else if r[0] > 0.0 && r[1] <= 0.0 {
+                search_condition(t[0], t[1], tol, &opening)
+            } else {
+                continue;
+            };
+            extrema.extend(found.ok().flatten());
+        }
+        extrema.sort();
 
-        let tol = Nanotime::secs(30);
+        let nearest = *extrema
+            .iter()
+            .min_by(|a, b| separation(**a).total_cmp(&separation(**b)))?;
+        let farthest = *extrema
+            .iter()
+            .max_by(|a, b| separation(**a).total_cmp(&separation(**b)))?;
 
-        for t in teval.windows(2) {
-            match search_condition::<Nanotime>(t[0], t[1], tol, &c3) {
-                Ok(Some(found)) => rising = Some(found),
-                Ok(None) => (),
-                Err(e) => {
-                    dbg!(e);
-                    return (None, None);
-                }
-            }
-            match search_condition::<Nanotime>(t[0], t[1], tol, &c4) {
-                Ok(Some(found)) => falling = Some(found),
-                Ok(None) => (),
-                Err(e) => {
-                    dbg!(e);
-                    return (None, None);
-                }
-            }
+        if separation(nearest) >= distance {
+            return Some(ApproachInfo::NonIntersecting { nearest, farthest });
         }
 
-        (rising, falling)
+        // the separation is monotonic between extrema, so it passes through
+        // the distance at most once in each span
+        let falling = extrema
+            .windows(2)
+            .filter(|w| w[1] <= nearest)
+            .filter_map(|w| search_condition(w[0], w[1], tol, &outside).ok()?)
+            .next_back()
+            .unwrap_or(now);
+        let rising = extrema
+            .windows(2)
+            .filter(|w| w[0] >= nearest)
+            .filter_map(|w| search_condition(w[0], w[1], tol, &inside).ok()?)
+            .next()
+            .unwrap_or(now + dur);
+
+        Some(ApproachInfo::Intersecting {
+            nearest,
+            farthest,
+            rising,
+            falling,
+        })
     }
 
     pub fn is_similar(&self, other: &Self) -> bool {
@@ -777,10 +945,43 @@ impl ULData {
         ULResults::new(self.chi_0, &self)
     }
 
-    fn solve(&self) -> Option<ULResults> {
+    fn is_bracket(&self, chi_min: f64, chi_max: f64) -> bool {
+        let lower = self.universal_kepler(chi_min);
+        let upper = self.universal_kepler(chi_max);
+        lower.is_finite() && upper.is_finite() && lower.signum() != upper.signum()
+    }
+
+    // universal kepler is monotonic in chi and negative at chi = 0 for
+    // positive time of flight, so any interval with a sign change contains
+    // the one root. the fixed default radius overflows the stumpff functions
+    // for hyperbolic orbits, in which case the interval is instead grown
+    // outwards from zero until it brackets the root.
+    fn bracket(&self) -> (f64, f64) {
         let radius = 800000.0;
-        let chi_min = self.chi_0 - radius;
-        let chi_max = self.chi_0 + radius;
+        let default = (self.chi_0 - radius, self.chi_0 + radius);
+        if self.is_bracket(default.0, default.1) {
+            return default;
+        }
+
+        let sign = self.tof.inner().signum() as f64;
+        let mut extent = 1.0;
+        while extent < radius {
+            let (chi_min, chi_max) = if sign < 0.0 {
+                (-extent, 0.0)
+            } else {
+                (0.0, extent)
+            };
+            if self.is_bracket(chi_min, chi_max) {
+                return (chi_min, chi_max);
+            }
+            extent *= 2.0;
+        }
+
+        default
+    }
+
+    fn solve(&self) -> Option<ULResults> {
+        let (chi_min, chi_max) = self.bracket();
         let chi = if self.tof == Nanotime::zero() {
             0.0
         } else {
@@ -1456,6 +1657,29 @@ mod tests {
         assert_relative_eq!(stumpff_3(20.0), 0.060859215);
     }
 
+    #[test]
+    fn body_rotation() {
+        let body = Body::with_mass(100.0, 1000.0, 10000.0).with_rotation_rate(0.5);
+        let day = body.sidereal_day().unwrap();
+        assert!((day.to_secs_f64() - 4.0 * PI_64).abs() < 1E-6);
+        assert!(
+            body.rotation_angle(day * 7i64)
+                .min(2.0 * PI_64 - body.rotation_angle(day * 7i64))
+                < 1E-6
+        );
+        assert!((body.rotation_angle(Nanotime::secs(1)) - 0.5).abs() < 1E-9);
+
+        let p = DVec2::new(100.0, 20.0);
+        let t = Nanotime::secs(3);
+        let q = body.fixed_to_inertial(p, t);
+        assert!((q.to_angle() - p.to_angle() - 1.5).abs() < 1E-9);
+        assert!(body.inertial_to_fixed(q, t).distance(p) < 1E-9);
+        assert!((body.surface_velocity(p).length() - p.length() * 0.5).abs() < 1E-9);
+        assert!(body.surface_velocity(p).dot(p).abs() < 1E-9);
+
+        assert_eq!(Body::with_mass(100.0, 1000.0, 10000.0).sidereal_day(), None);
+    }
+
     #[test]
     fn inverse_orbit() {
         const TEST_POSITION: DVec2 = DVec2::new(500.0, 300.0);
@@ -1465,6 +1689,7 @@ mod tests {
             radius: 100.0,
             mu: 1000.0 * 12000.0,
             soi: 10000.0,
+            rotation_rate: 0.0,
         };
 
         let o1 =
@@ -1529,4 +1754,70 @@ mod tests {
             );
         }
     }
+
+    fn assert_lut_agrees_with_universal(orbit: &SparseOrbit, t: Nanotime) {
+        let (lut, universal) = match (orbit.pv_lut(t), orbit.pv_universal(t)) {
+            (Some(lut), Ok(universal)) => (lut, universal),
+            (lut, universal) => panic!("Failed at {t}: {lut:?}, {universal:?}\n  {orbit:?}"),
+        };
+        // pv_universal is itself only good to a few parts per million
+        let scale = orbit.periapsis_r();
+        assert_le!(
+            lut.pos.distance(universal.pos) / scale,
+            1E-5,
+            "Position disagreement at {t}: lut={lut:?}, universal={universal:?}\n  {orbit:?}"
+        );
+        assert_le!(
+            lut.vel.distance(universal.vel) / universal.vel.length(),
+            1E-5,
+            "Velocity disagreement at {t}: lut={lut:?}, universal={universal:?}\n  {orbit:?}"
+        );
+
+        // the LUT should conserve energy and angular momentum almost exactly
+        let energy = |pv: PV| pv.vel.length_squared() / 2.0 - orbit.body.mu / pv.pos.length();
+        let e0 = energy(orbit.initial);
+        assert_le!(((energy(lut) - e0) / e0).abs(), 1E-10);
+        let h0 = orbit.h();
+        assert_le!(((cross2d(lut.pos, lut.vel) - h0) / h0).abs(), 1E-10);
+    }
+
+    #[test]
+    fn pv_lut_elliptical_accuracy() {
+        let body = Body::with_mass(63.0, 1000.0, 1E9);
+        let rp = 100.0;
+        for ecc in linspace_f64(0.0, 0.985, 34) {
+            for retrograde in [false, true] {
+                let ra = rp * (1.0 + ecc) / (1.0 - ecc);
+                let orbit =
+                    SparseOrbit::new(ra, rp, 0.7, body, Nanotime::secs(3), retrograde).unwrap();
+                let period = orbit.period().unwrap();
+                for s in linspace_f64(-1.3, 2.1, 97) {
+                    let t = orbit.epoch + Nanotime::secs_f64(period.to_secs_f64() * s);
+                    assert_lut_agrees_with_universal(&orbit, t);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pv_lut_hyperbolic_accuracy() {
+        let body = Body::with_mass(63.0, 1000.0, 1E7);
+        let rp = 100.0;
+        for ecc in [1.02, 1.1, 1.5, 2.0, 3.7, 6.0, 15.0] {
+            for retrograde in [false, true] {
+                let sign = if retrograde { -1.0 } else { 1.0 };
+                let v = (body.mu * (1.0 + ecc) / rp).sqrt();
+                let pv = PV::from_f64(
+                    rotate_f64(DVec2::X * rp, -1.1),
+                    rotate_f64(DVec2::Y * v * sign, -1.1),
+                );
+                let orbit = SparseOrbit::from_pv(pv, body, Nanotime::secs(5)).unwrap();
+                assert_eq!(orbit.class(), OrbitClass::Hyperbolic);
+                for secs in linspace_f64(-300.0, 300.0, 121) {
+                    let t = orbit.epoch + Nanotime::secs_f64(secs);
+                    assert_lut_agrees_with_universal(&orbit, t);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,39 @@
+use crate::factory::Mass;
+use crate::math::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Antenna {
+    name: String,
+    dims: UVec2,
+    mass: Mass,
+    /// Maximum distance at which this antenna can close a link, in meters
+    range: f64,
+}
+
+impl Antenna {
+    pub fn new(name: String, dims: UVec2, mass: Mass, range: f64) -> Self {
+        Self {
+            name,
+            dims,
+            mass,
+            range,
+        }
+    }
+
+    pub fn part_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn dims(&self) -> UVec2 {
+        self.dims
+    }
+
+    pub fn mass(&self) -> Mass {
+        self.mass
+    }
+
+    pub fn range(&self) -> f64 {
+        self.range
+    }
+}
@@ -1,3 +1,4 @@
+pub mod antenna;
 pub mod cargo;
 pub mod generic;
 pub mod gyro;
@@ -9,6 +10,7 @@ pub mod rotation;
 pub mod tank;
 pub mod thruster;
 
+pub use antenna::*;
 pub use cargo::*;
 pub use generic::*;
 pub use gyro::*;
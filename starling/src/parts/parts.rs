@@ -13,6 +13,7 @@ pub enum PartPrototype {
     Thruster(ThrusterModel),
     Tank(TankModel),
     Radar(Radar),
+    Antenna(Antenna),
     Cargo(Cargo),
     Magnetorquer(Magnetorquer),
     Machine(Machine),
@@ -33,6 +34,7 @@ impl PartPrototype {
             Self::Thruster(p) => p.dims(),
             Self::Tank(p) => p.dims(),
             Self::Radar(p) => p.dims(),
+            Self::Antenna(p) => p.dims(),
             Self::Cargo(p) => p.dims(),
             Self::Magnetorquer(p) => p.dims(),
             Self::Generic(p) => p.dims(),
@@ -49,6 +51,7 @@ impl PartPrototype {
             Self::Thruster(p) => p.part_name(),
             Self::Tank(p) => p.part_name(),
             Self::Radar(p) => p.part_name(),
+            Self::Antenna(p) => p.part_name(),
             Self::Cargo(p) => p.part_name(),
             Self::Magnetorquer(p) => p.part_name(),
             Self::Generic(p) => p.part_name(),
@@ -61,6 +64,7 @@ impl PartPrototype {
             Self::Thruster(p) => p.mass(),
             Self::Tank(p) => p.dry_mass(),
             Self::Radar(p) => p.mass(),
+            Self::Antenna(p) => p.mass(),
             Self::Cargo(p) => p.empty_mass(),
             Self::Magnetorquer(p) => p.mass(),
             Self::Generic(p) => p.mass(),
@@ -73,6 +77,7 @@ impl PartPrototype {
             Self::Thruster(..) => PartLayer::Internal,
             Self::Tank(..) => PartLayer::Internal,
             Self::Radar(..) => PartLayer::Internal,
+            Self::Antenna(..) => PartLayer::Internal,
             Self::Cargo(..) => PartLayer::Internal,
             Self::Magnetorquer(..) => PartLayer::Internal,
             Self::Generic(p) => p.layer(),
@@ -123,6 +128,7 @@ pub enum InstantiatedPartVariant {
     Thruster(ThrusterModel, ThrusterInstanceData),
     Tank(TankModel, TankInstanceData),
     Radar(Radar),
+    Antenna(Antenna),
     Cargo(Cargo, CargoInstanceData),
     Magnetorquer(Magnetorquer, MagnetorquerInstanceData),
     Machine(Machine, MachineInstanceData),
@@ -169,6 +175,7 @@ impl InstantiatedPart {
                 InstantiatedPartVariant::Magnetorquer(m, MagnetorquerInstanceData::new())
             }
             PartPrototype::Radar(r) => InstantiatedPartVariant::Radar(r),
+            PartPrototype::Antenna(a) => InstantiatedPartVariant::Antenna(a),
             PartPrototype::Tank(t) => InstantiatedPartVariant::Tank(t, TankInstanceData::default()),
             PartPrototype::Thruster(t) => {
                 InstantiatedPartVariant::Thruster(t, ThrusterInstanceData::new())
@@ -190,6 +197,7 @@ impl InstantiatedPart {
             InstantiatedPartVariant::Thruster(t, _) => PartPrototype::Thruster(t),
             InstantiatedPartVariant::Tank(t, _) => PartPrototype::Tank(t),
             InstantiatedPartVariant::Radar(r) => PartPrototype::Radar(r),
+            InstantiatedPartVariant::Antenna(a) => PartPrototype::Antenna(a),
             InstantiatedPartVariant::Cargo(c, _) => PartPrototype::Cargo(c),
             InstantiatedPartVariant::Magnetorquer(m, _) => PartPrototype::Magnetorquer(m),
             InstantiatedPartVariant::Machine(m, _) => PartPrototype::Machine(m),
@@ -206,6 +214,7 @@ impl InstantiatedPart {
             InstantiatedPartVariant::Thruster(t, _) => t.mass(),
             InstantiatedPartVariant::Tank(t, d) => t.dry_mass() + d.contents_mass(),
             InstantiatedPartVariant::Radar(r) => r.mass(),
+            InstantiatedPartVariant::Antenna(a) => a.mass(),
             InstantiatedPartVariant::Cargo(c, d) => c.empty_mass() + d.contents_mass(),
             InstantiatedPartVariant::Magnetorquer(m, _) => m.mass(),
             InstantiatedPartVariant::Machine(m, _) => m.mass(),
@@ -223,6 +232,14 @@ impl InstantiatedPart {
         self.builds_performed = self.builds_required;
     }
 
+    pub fn builds_performed(&self) -> u32 {
+        self.builds_performed
+    }
+
+    pub fn set_builds_performed(&mut self, n: u32) {
+        self.builds_performed = n.min(self.builds_required);
+    }
+
     pub fn percent_built(&self) -> f32 {
         (self.builds_performed as f32 / self.builds_required as f32).clamp(0.0, 1.0)
     }
@@ -385,4 +402,12 @@ impl InstantiatedPart {
             None
         }
     }
+
+    pub fn as_antenna(&self) -> Option<&Antenna> {
+        if let InstantiatedPartVariant::Antenna(a) = &self.variant {
+            Some(a)
+        } else {
+            None
+        }
+    }
 }
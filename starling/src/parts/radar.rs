@@ -6,9 +6,36 @@ use serde::{Deserialize, Serialize};
 pub struct Radar {
     dims: UVec2,
     mass: Mass,
+    /// Maximum detection range, in meters
+    range: f64,
+    /// Full angular width of the detection cone, in radians,
+    /// centered on the part's facing direction
+    fov: f64,
+    /// Standard deviation of range measurements, in meters
+    range_noise: f64,
+    /// Standard deviation of bearing measurements, in radians
+    bearing_noise: f64,
 }
 
 impl Radar {
+    pub fn new(
+        dims: UVec2,
+        mass: Mass,
+        range: f64,
+        fov: f64,
+        range_noise: f64,
+        bearing_noise: f64,
+    ) -> Self {
+        Self {
+            dims,
+            mass,
+            range,
+            fov,
+            range_noise,
+            bearing_noise,
+        }
+    }
+
     pub fn part_name(&self) -> &str {
         "radar"
     }
@@ -20,4 +47,29 @@ impl Radar {
     pub fn mass(&self) -> Mass {
         self.mass
     }
+
+    pub fn range(&self) -> f64 {
+        self.range
+    }
+
+    pub fn fov(&self) -> f64 {
+        self.fov
+    }
+
+    pub fn range_noise(&self) -> f64 {
+        self.range_noise
+    }
+
+    pub fn bearing_noise(&self) -> f64 {
+        self.bearing_noise
+    }
+
+    /// Whether a target at the given inertial bearing falls within
+    /// the detection cone of a radar pointed along the given boresight.
+    pub fn in_fov(&self, boresight: f64, bearing: f64) -> bool {
+        if self.fov >= 2.0 * PI_64 {
+            return true;
+        }
+        wrap_pi_npi_f64(bearing - boresight).abs() <= self.fov / 2.0
+    }
 }
@@ -11,6 +11,15 @@ pub struct TankModel {
 }
 
 impl TankModel {
+    pub fn new(name: String, dims: UVec2, dry_mass: Mass, max_fluid_mass: Mass) -> Self {
+        Self {
+            name,
+            dims,
+            dry_mass,
+            max_fluid_mass,
+        }
+    }
+
     pub fn part_name(&self) -> &str {
         &self.name
     }
@@ -81,6 +90,10 @@ impl TankInstanceData {
         self.stored.map(|(item, _)| item)
     }
 
+    pub fn contents(&self) -> Option<(Item, Mass)> {
+        self.stored
+    }
+
     pub fn clear_contents(&mut self) {
         self.stored = None;
     }
@@ -62,6 +62,8 @@ pub struct ThrusterInstanceData {
     throttle: f32,
     target_throttle: f32,
     seconds_remaining: f32,
+    /// Disabled thrusters ignore throttle commands and stay shut down
+    enabled: bool,
 }
 
 impl ThrusterInstanceData {
@@ -70,6 +72,18 @@ impl ThrusterInstanceData {
             throttle: 0.0,
             target_throttle: 0.0,
             seconds_remaining: 20.0,
+            enabled: true,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.set_throttle(0.0);
         }
     }
 
@@ -82,7 +96,11 @@ impl ThrusterInstanceData {
     }
 
     pub fn set_throttle(&mut self, throttle: f32) {
-        self.target_throttle = throttle.clamp(0.0, 1.0);
+        self.target_throttle = if self.enabled {
+            throttle.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
         // TODO!
         self.throttle = self.target_throttle;
     }
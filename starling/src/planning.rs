@@ -305,6 +305,64 @@ fn generate_maneuver_plans(
     [direct, hohmann].into_iter().flatten().collect()
 }
 
+/// Longest a phasing orbit may take to bring the target round, in
+/// revolutions.
+const MAX_PHASING_REVOLUTIONS: u32 = 8;
+
+/// Plan to catch up with a target on nearly the same orbit. A burn at the
+/// current position puts the vehicle on a phasing orbit whose period lets
+/// the target come round to meet it after a whole number of revolutions,
+/// and a second burn there puts it back on the target's orbit.
+pub fn phasing_plan(
+    current: &SparseOrbit,
+    target: &SparseOrbit,
+    now: Nanotime,
+) -> Option<ManeuverPlan> {
+    let period = target.period()?.to_secs_f64();
+    let mu = current.body.mu();
+    let before = current.pv(now).ok()?;
+    let r = before.pos.length();
+
+    // fraction of a revolution the target is ahead of the vehicle
+    let ahead = target.pv(now).ok()?.pos.to_angle() - before.pos.to_angle();
+    let ahead = if target.is_retrograde() {
+        -ahead
+    } else {
+        ahead
+    };
+    let f = wrap_0_2pi_f64(ahead) / (2.0 * PI_64);
+
+    // either wait above for the target to lap around, or cut below to
+    // catch it up, whichever is quicker and stays clear of the surface
+    // and the edge of the sphere of influence
+    let semi_major_axis = |p: f64| (mu * (p / (2.0 * PI_64)).powi(2)).cbrt();
+    let phasing_period = (1..=MAX_PHASING_REVOLUTIONS)
+        .flat_map(|k| {
+            let k = k as f64;
+            [
+                (k, period * (1.0 + (1.0 - f) / k)),
+                (k, period * (1.0 - f / k)),
+            ]
+        })
+        .filter(|(_, p)| {
+            let other_apsis = 2.0 * semi_major_axis(*p) - r;
+            other_apsis > current.body.radius * 1.02 && other_apsis < current.body.soi * 0.9
+        })
+        .min_by(|(k1, p1), (k2, p2)| (k1 * p1).total_cmp(&(k2 * p2)))?;
+
+    let (revolutions, p) = phasing_period;
+    let v = vis_viva_equation(mu, r, semi_major_axis(p));
+    let dv1 = before.vel.normalize_or_zero() * v - before.vel;
+
+    let phasing = SparseOrbit::from_pv(before + PV::vel(dv1), current.body, now)?;
+    let t2 = now + Nanotime::secs_f64(revolutions * p);
+    let before = phasing.pv_universal(t2).ok()?;
+    let (after, _) = target.nearest(before.pos);
+    let dv2 = after.vel - before.vel;
+
+    ManeuverPlan::new(now, *current, &[(now, dv1), (t2, dv2)])
+}
+
 pub fn rendezvous_plan(
     src: &SparseOrbit,
     dst: &SparseOrbit,
@@ -409,4 +467,31 @@ mod tests {
             maneuver_plan_is_continuous(&plan);
         }
     }
+
+    #[test]
+    fn phasing_plan_catches_up_with_target() {
+        let body = Body::LUNA;
+        let r = body.radius * 2.5;
+        for angle in [0.3, 1.1, 2.5, 4.0, 5.9] {
+            let chaser = SparseOrbit::circular(r, body, Nanotime::zero(), false);
+            let pv = chaser.pv(Nanotime::zero()).unwrap();
+            let rot = DVec2::from_angle(angle);
+            let pv = PV::from_f64(rot.rotate(pv.pos), rot.rotate(pv.vel));
+            let target = SparseOrbit::from_pv(pv, body, Nanotime::zero()).unwrap();
+            let now = Nanotime::secs(100);
+
+            let plan = phasing_plan(&chaser, &target, now).unwrap();
+            maneuver_plan_segments_join(&plan);
+
+            let end = plan.end();
+            let pv = plan.terminal.pv(end).unwrap();
+            let goal = target.pv(end).unwrap();
+            assert!(
+                pv.pos.distance(goal.pos) < 1000.0 && pv.vel.distance(goal.vel) < 1.0,
+                "{angle}: missed by {} and {}",
+                pv.pos.distance(goal.pos),
+                pv.vel.distance(goal.vel),
+            );
+        }
+    }
 }
@@ -2,6 +2,8 @@ pub use crate::aabb::{Polygon, AABB, OBB};
 pub use crate::belts::AsteroidBelt;
 pub use crate::bezier::*;
 pub use crate::casts::*;
+pub use crate::comms::*;
+pub use crate::conjunctions::*;
 pub use crate::construction_bot::*;
 pub use crate::control::OrbitalController;
 pub use crate::control_signals::*;
@@ -10,23 +12,30 @@ pub use crate::examples::{default_example, make_earth, make_luna};
 pub use crate::factory::*;
 pub use crate::file_export::export_orbit_data;
 pub use crate::id::{EntityId, ObjectId};
+pub use crate::impact::*;
 pub use crate::lpf::*;
 pub use crate::math::*;
+pub use crate::mission::*;
 pub use crate::nanotime::Nanotime;
+pub use crate::nbody::*;
+pub use crate::orbit_determination::*;
 pub use crate::orbital_luts::lookup_ta_from_ma;
 pub use crate::orbiter::Orbiter;
 pub use crate::orbits::{hyperbolic_range_ta, Body, GlobalOrbit, SparseOrbit};
 pub use crate::parts::*;
 pub use crate::pid::*;
-pub use crate::planning::{best_maneuver_plan, get_next_intersection, ManeuverPlan};
+pub use crate::planning::{best_maneuver_plan, get_next_intersection, phasing_plan, ManeuverPlan};
 pub use crate::plants::Plant;
 pub use crate::propagator::{EventType, HorizonState, Propagator};
 pub use crate::pv::*;
 pub use crate::quantities::*;
 pub use crate::region::Region;
 pub use crate::scenario::{ObjectLookup, PlanetarySystem, ScenarioObject};
+pub use crate::sensors::*;
+pub use crate::spatial_index::*;
 pub use crate::surface::*;
 pub use crate::take::*;
+pub use crate::three_body::*;
 pub use crate::thrust_particles::*;
 pub use crate::universe::*;
 pub use crate::vehicle::*;
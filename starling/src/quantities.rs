@@ -10,3 +10,7 @@ pub const LUNA_RADIUS: f64 = 1_737_400.0;
 pub const LUNA_SOI: f64 = 35_000_000.0;
 
 pub const LUNA_ORBITAL_RADIUS: f64 = 384_399_000.0;
+
+// sidereal rotation rates listed in rad/s
+pub const EARTH_ROTATION_RATE: f64 = 7.292_115E-5;
+pub const LUNA_ROTATION_RATE: f64 = 2.661_699E-6;
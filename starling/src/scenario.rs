@@ -92,6 +92,15 @@ impl PlanetarySystem {
         ret
     }
 
+    /// Every body in the system along with its name, starting with this one.
+    pub fn named_bodies(&self) -> Vec<(&str, Body)> {
+        let mut ret = vec![(self.name.as_str(), self.body)];
+        for (_, sub) in &self.subsystems {
+            ret.extend(sub.named_bodies());
+        }
+        ret
+    }
+
     pub fn bodies<T: Into<Option<PV>>>(
         &self,
         stamp: Nanotime,
@@ -0,0 +1,368 @@
+use crate::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+
+/// Tracks which haven't been refreshed by a detection within this
+/// duration are dropped.
+pub const SENSOR_TRACK_TIMEOUT: Nanotime = Nanotime::secs(30);
+
+/// Minimum spacing between detections retained in a track's history.
+pub const SENSOR_TRACK_SAMPLE_PERIOD: Nanotime = Nanotime::secs(5);
+
+/// Maximum number of detections retained per track.
+pub const SENSOR_TRACK_HISTORY: usize = 100;
+
+/// A single noisy radar return, measured in the inertial frame.
+#[derive(Debug, Clone, Copy)]
+pub struct Detection {
+    pub target: ObjectId,
+    pub stamp: Nanotime,
+    /// World-frame position of the observer at the time of detection
+    pub observer_pos: DVec2,
+    /// Measured distance to the target, in meters
+    pub range: f64,
+    /// Measured inertial angle from observer to target, in radians
+    pub bearing: f64,
+    /// Standard deviation of the range measurement
+    pub range_sigma: f64,
+    /// Standard deviation of the bearing measurement
+    pub bearing_sigma: f64,
+}
+
+impl Detection {
+    pub fn relative_position(&self) -> DVec2 {
+        rotate_f64(DVec2::X * self.range, self.bearing)
+    }
+
+    /// World-frame position of the target implied by this measurement.
+    pub fn position(&self) -> DVec2 {
+        self.observer_pos + self.relative_position()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SensorTrack {
+    pub first_seen: Nanotime,
+    latest: Detection,
+    history: VecDeque<Detection>,
+    estimate: Option<OrbitEstimate>,
+    last_fit_attempt: Option<Nanotime>,
+}
+
+impl SensorTrack {
+    fn new(det: Detection) -> Self {
+        Self {
+            first_seen: det.stamp,
+            latest: det,
+            history: VecDeque::from([det]),
+            estimate: None,
+            last_fit_attempt: None,
+        }
+    }
+
+    fn insert(&mut self, det: Detection) {
+        self.latest = det;
+        let last = self.history.back().map(|d| d.stamp).unwrap_or(det.stamp);
+        if det.stamp - last >= SENSOR_TRACK_SAMPLE_PERIOD {
+            self.history.push_back(det);
+            if self.history.len() > SENSOR_TRACK_HISTORY {
+                self.history.pop_front();
+            }
+        }
+    }
+
+    pub fn latest(&self) -> &Detection {
+        &self.latest
+    }
+
+    /// Detections retained for orbit determination, oldest first.
+    pub fn history(&self) -> &VecDeque<Detection> {
+        &self.history
+    }
+
+    pub fn estimate(&self) -> Option<&OrbitEstimate> {
+        self.estimate.as_ref()
+    }
+
+    pub fn estimated_position(&self) -> DVec2 {
+        self.latest.position()
+    }
+
+    pub fn age(&self, stamp: Nanotime) -> Nanotime {
+        stamp - self.latest.stamp
+    }
+
+    fn needs_estimate(&self) -> bool {
+        let newest = self.history.back().map(|d| d.stamp);
+        self.target_is_orbiter()
+            && self.history.len() >= MIN_OBSERVATIONS_FOR_FIT
+            && self.last_fit_attempt != newest
+    }
+
+    fn target_is_orbiter(&self) -> bool {
+        self.latest.target.as_orbiter().is_some()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SensorTracks {
+    tracks: HashMap<ObjectId, SensorTrack>,
+}
+
+impl SensorTracks {
+    pub fn insert(&mut self, det: Detection) {
+        if let Some(track) = self.tracks.get_mut(&det.target) {
+            track.insert(det);
+        } else {
+            self.tracks.insert(det.target, SensorTrack::new(det));
+        }
+    }
+
+    pub fn prune(&mut self, stamp: Nanotime) {
+        self.tracks
+            .retain(|_, t| t.age(stamp) <= SENSOR_TRACK_TIMEOUT);
+    }
+
+    /// Refits the orbit of any orbiter track which has gained
+    /// observations since its last estimate.
+    pub fn update_estimates(&mut self, planets: &PlanetarySystem) {
+        for track in self.tracks.values_mut() {
+            if track.needs_estimate() {
+                track.estimate = estimate_track_orbit(track, planets).ok();
+                track.last_fit_attempt = track.history.back().map(|d| d.stamp);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.tracks.clear();
+    }
+
+    pub fn get(&self, id: &ObjectId) -> Option<&SensorTrack> {
+        self.tracks.get(id)
+    }
+
+    pub fn contains(&self, id: &ObjectId) -> bool {
+        self.tracks.contains_key(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&ObjectId, &SensorTrack)> + use<'_> {
+        self.tracks.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+}
+
+/// Generator for one vehicle's sensor noise on one tick. Seeded from
+/// nothing but the universe's seed, the vehicle and the time, so results
+/// don't depend on the order vehicles are scanned in.
+fn sensor_rng(seed: u64, id: EntityId, stamp: Nanotime) -> StdRng {
+    let mut hasher = DefaultHasher::new();
+    (seed, id, stamp.inner()).hash(&mut hasher);
+    StdRng::seed_from_u64(hasher.finish())
+}
+
+fn is_occluded_by_any(observer: DVec2, target: DVec2, bodies: &[(DVec2, f64)]) -> bool {
+    bodies.iter().any(|(center, radius)| {
+        is_occluded(
+            aabb_stopgap_cast(observer),
+            aabb_stopgap_cast(target),
+            aabb_stopgap_cast(*center),
+            *radius as f32,
+        )
+    })
+}
+
+/// Computes this tick's radar returns for every vehicle carrying a radar.
+/// Targets must be within range and within the field of view of at least
+/// one radar, and must not be hidden behind a planet.
+pub fn radar_detections(universe: &Universe) -> HashMap<EntityId, Vec<Detection>> {
+    let stamp = universe.stamp();
+
+    let planets: Vec<(EntityId, DVec2, f64)> = universe
+        .planets
+        .planet_ids()
+        .into_iter()
+        .filter_map(|id| {
+            let (body, pv, _, _) = universe.planets.lookup(id, stamp)?;
+            Some((id, pv.pos, body.radius))
+        })
+        .collect();
+
+    let mut ret = HashMap::new();
+
+    for (id, sv) in &universe.surface_vehicles {
+        if !sv.vehicle.has_radar() {
+            continue;
+        }
+
+        let observer = match universe.pv(*id) {
+            Some(pv) => pv.pos,
+            None => continue,
+        };
+
+        let radars: Vec<_> = sv.vehicle.radars_with_rotation().collect();
+
+        let nearby = universe
+            .spatial_index()
+            .within_radius(observer, sv.vehicle.radar_range().unwrap_or(0.0));
+
+        let targets = nearby
+            .iter()
+            .filter(|e| e.id != *id)
+            .map(|e| (ObjectId::Orbiter(e.id), e.pos, 0.0))
+            .chain(
+                planets
+                    .iter()
+                    .map(|(pid, pos, radius)| (ObjectId::Planet(*pid), *pos, *radius)),
+            );
+
+        let mut detections = vec![];
+        let mut rng = sensor_rng(universe.sensor_seed, *id, stamp);
+
+        for (target, pos, radius) in targets {
+            let delta = pos - observer;
+            let range = (delta.length() - radius).max(0.0);
+            let bearing = delta.to_angle();
+
+            let radar = radars.iter().find(|(r, rot)| {
+                range <= r.range() && r.in_fov(sv.body.angle + rot.to_angle(), bearing)
+            });
+
+            let radar = match radar {
+                Some((r, _)) => r,
+                None => continue,
+            };
+
+            let occluders: Vec<_> = planets
+                .iter()
+                .filter(|(pid, _, _)| ObjectId::Planet(*pid) != target)
+                .map(|(_, c, r)| (*c, *r))
+                .collect();
+
+            if is_occluded_by_any(observer, pos, &occluders) {
+                continue;
+            }
+
+            detections.push(Detection {
+                target,
+                stamp,
+                observer_pos: observer,
+                range: randn_from(&mut rng, range, radar.range_noise()).max(0.0),
+                bearing: randn_from(&mut rng, bearing, radar.bearing_noise()),
+                range_sigma: radar.range_noise(),
+                bearing_sigma: radar.bearing_noise(),
+            });
+        }
+
+        ret.insert(*id, detections);
+    }
+
+    ret
+}
+
+/// Orbiters which are visible to the player's sensor network; that is,
+/// vehicles which carry a radar, and anything those radars are tracking.
+pub fn radar_visible_ids(universe: &Universe) -> HashSet<EntityId> {
+    let mut ret = HashSet::new();
+    for (id, sv) in &universe.surface_vehicles {
+        if sv.vehicle.has_radar() {
+            ret.insert(*id);
+        }
+        for (target, _) in sv.tracks.iter() {
+            if let Some(eid) = target.as_orbiter() {
+                ret.insert(eid);
+            }
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn radar_vehicle(range: f64) -> Vehicle {
+        let radar = Radar::new(
+            UVec2::new(4, 4),
+            Mass::kilograms(100),
+            range,
+            2.0 * PI_64,
+            0.0,
+            0.0,
+        );
+        Vehicle::from_parts(
+            "radar".into(),
+            "radar".into(),
+            vec![(IVec2::ZERO, Rotation::East, PartPrototype::Radar(radar))],
+            Default::default(),
+        )
+    }
+
+    fn dummy_vehicle() -> Vehicle {
+        Vehicle::from_parts("dummy".into(), "dummy".into(), vec![], Default::default())
+    }
+
+    #[test]
+    fn radar_range_gating() {
+        let mut universe = Universe::new(PlanetarySystem::new(EntityId(0), "test", Body::LUNA));
+        let r = Body::LUNA.radius;
+
+        let observer = place_vehicle(
+            &mut universe,
+            radar_vehicle(10_000.0),
+            DVec2::new(r * 2.0, 0.0),
+        );
+        let near = place_vehicle(&mut universe, dummy_vehicle(), DVec2::new(r * 2.0, 5_000.0));
+        let far = place_vehicle(
+            &mut universe,
+            dummy_vehicle(),
+            DVec2::new(r * 2.0, 50_000.0),
+        );
+
+        universe.update_spatial_index();
+        let dets = radar_detections(&universe);
+        let dets = dets.get(&observer).unwrap();
+
+        assert!(dets.iter().any(|d| d.target == ObjectId::Orbiter(near)));
+        assert!(!dets.iter().any(|d| d.target == ObjectId::Orbiter(far)));
+
+        let det = dets
+            .iter()
+            .find(|d| d.target == ObjectId::Orbiter(near))
+            .unwrap();
+        assert!((det.range - 5_000.0).abs() < 1.0);
+        assert!((det.position() - DVec2::new(r * 2.0, 5_000.0)).length() < 1.0);
+    }
+
+    #[test]
+    fn radar_occlusion() {
+        let mut universe = Universe::new(PlanetarySystem::new(EntityId(0), "test", Body::LUNA));
+        let r = Body::LUNA.radius;
+
+        let observer = place_vehicle(
+            &mut universe,
+            radar_vehicle(r * 10.0),
+            DVec2::new(r * 1.5, 0.0),
+        );
+        let hidden = place_vehicle(&mut universe, dummy_vehicle(), DVec2::new(-r * 1.5, 0.0));
+
+        universe.update_spatial_index();
+        let dets = radar_detections(&universe);
+        let dets = dets.get(&observer).unwrap();
+
+        assert!(!dets.iter().any(|d| d.target == ObjectId::Orbiter(hidden)));
+        assert!(dets
+            .iter()
+            .any(|d| d.target == ObjectId::Planet(EntityId(0))));
+    }
+}
@@ -0,0 +1,386 @@
+use crate::prelude::*;
+use std::collections::HashMap;
+
+/// Number of entries per grid cell the index aims for.
+const TARGET_CELL_OCCUPANCY: f64 = 4.0;
+
+/// Smallest allowed grid cell, in meters.
+const MIN_CELL_SIZE: f64 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IndexEntry {
+    pub id: EntityId,
+    /// Position in the world frame
+    pub pos: DVec2,
+}
+
+/// Uniform grid over the vehicles orbiting a single parent body. The grid
+/// just covers the bounding box of its entries, with a cell size chosen
+/// from the number of entries, so that clusters of vehicles around
+/// different bodies don't force a common resolution on each other.
+#[derive(Debug, Clone)]
+struct FrameGrid {
+    lower: DVec2,
+    upper: DVec2,
+    cell_size: f64,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+    entries: Vec<IndexEntry>,
+    /// Position of each entry in `entries`
+    slots: HashMap<EntityId, usize>,
+}
+
+impl FrameGrid {
+    fn new(entries: Vec<IndexEntry>) -> Self {
+        let lower = entries
+            .iter()
+            .fold(DVec2::INFINITY, |acc, e| acc.min(e.pos));
+        let upper = entries
+            .iter()
+            .fold(DVec2::NEG_INFINITY, |acc, e| acc.max(e.pos));
+        let extent = (upper - lower).max_element();
+        let cells_per_side = (entries.len() as f64 / TARGET_CELL_OCCUPANCY)
+            .sqrt()
+            .ceil()
+            .max(1.0);
+        let cell_size = (extent / cells_per_side).max(MIN_CELL_SIZE);
+
+        let mut grid = Self {
+            lower,
+            upper,
+            cell_size,
+            cells: HashMap::new(),
+            slots: entries.iter().enumerate().map(|(i, e)| (e.id, i)).collect(),
+            entries,
+        };
+
+        for (i, e) in grid.entries.iter().enumerate() {
+            grid.cells.entry(grid.cell(e.pos)).or_default().push(i);
+        }
+
+        grid
+    }
+
+    /// Takes an entry out of its cell. The last entry is moved into the
+    /// freed slot, so only two cells are touched. The bounds are left as
+    /// they were, which is still correct, just a little loose.
+    fn remove(&mut self, id: EntityId) -> bool {
+        let Some(i) = self.slots.remove(&id) else {
+            return false;
+        };
+        let cell = self.cell(self.entries[i].pos);
+        if let Some(slots) = self.cells.get_mut(&cell) {
+            slots.retain(|j| *j != i);
+        }
+
+        let last = self.entries.len() - 1;
+        self.entries.swap_remove(i);
+        if i != last {
+            let moved = self.entries[i];
+            let cell = self.cell(moved.pos);
+            for j in self.cells.get_mut(&cell).into_iter().flatten() {
+                if *j == last {
+                    *j = i;
+                }
+            }
+            self.slots.insert(moved.id, i);
+        }
+        true
+    }
+
+    fn cell(&self, p: DVec2) -> (i64, i64) {
+        let c = ((p - self.lower) / self.cell_size).floor();
+        (c.x as i64, c.y as i64)
+    }
+
+    fn cell_entries(&self, cell: (i64, i64)) -> impl Iterator<Item = &IndexEntry> + use<'_> {
+        self.cells
+            .get(&cell)
+            .into_iter()
+            .flatten()
+            .map(|i| &self.entries[*i])
+    }
+
+    fn for_each_in_box(&self, lo: DVec2, hi: DVec2, mut f: impl FnMut(&IndexEntry)) {
+        let lo = lo.max(self.lower);
+        let hi = hi.min(self.upper);
+        if lo.x > hi.x || lo.y > hi.y {
+            return;
+        }
+        let (a, b) = (self.cell(lo), self.cell(hi));
+        for x in a.0..=b.0 {
+            for y in a.1..=b.1 {
+                self.cell_entries((x, y)).for_each(&mut f);
+            }
+        }
+    }
+
+    /// Searches rings of cells outward from the query point until no
+    /// unvisited cell could hold anything closer than the best so far.
+    fn nearest(&self, pos: DVec2, max_dist: f64) -> Option<(IndexEntry, f64)> {
+        // every point in the grid is at least as far from the query point
+        // as it is from the query point's projection onto the grid
+        let clamped = pos.clamp(self.lower, self.upper);
+        if clamped.distance(pos) > max_dist {
+            return None;
+        }
+
+        let center = self.cell(clamped);
+        let (lo, hi) = (self.cell(self.lower), self.cell(self.upper));
+        let max_ring = (center.0 - lo.0)
+            .max(hi.0 - center.0)
+            .max(center.1 - lo.1)
+            .max(hi.1 - center.1);
+
+        let mut best: Option<(IndexEntry, f64)> = None;
+        for k in 0..=max_ring {
+            if let Some((_, d)) = best {
+                if (k - 1) as f64 * self.cell_size > d {
+                    break;
+                }
+            }
+            for cell in ring(center, k) {
+                for e in self.cell_entries(cell) {
+                    let d = e.pos.distance(pos);
+                    if d <= max_dist && best.map(|(_, b)| d < b).unwrap_or(true) {
+                        best = Some((*e, d));
+                    }
+                }
+            }
+        }
+
+        best
+    }
+}
+
+/// Cells at a Chebyshev distance of exactly `k` from the center cell.
+fn ring(center: (i64, i64), k: i64) -> Vec<(i64, i64)> {
+    if k == 0 {
+        return vec![center];
+    }
+    let (cx, cy) = center;
+    let mut ret = vec![];
+    for x in cx - k..=cx + k {
+        ret.push((x, cy - k));
+        ret.push((x, cy + k));
+    }
+    for y in cy - k + 1..cy + k {
+        ret.push((cx - k, y));
+        ret.push((cx + k, y));
+    }
+    ret
+}
+
+/// Index of vehicle positions, rebuilt by the universe every tick, for
+/// answering nearest-neighbor, radius and bounding box queries without
+/// scanning every vehicle.
+#[derive(Debug, Clone, Default)]
+pub struct SpatialIndex {
+    frames: HashMap<EntityId, FrameGrid>,
+}
+
+impl SpatialIndex {
+    pub fn new(universe: &Universe) -> Self {
+        Self::from_entries(universe.surface_vehicles.iter().filter_map(|(id, sv)| {
+            let pos = universe.pv(*id)?.pos;
+            Some((sv.parent(), IndexEntry { id: *id, pos }))
+        }))
+    }
+
+    /// Builds an index from entries grouped by the id of their parent body.
+    pub fn from_entries(entries: impl IntoIterator<Item = (EntityId, IndexEntry)>) -> Self {
+        let mut grouped: HashMap<EntityId, Vec<IndexEntry>> = HashMap::new();
+        for (parent, e) in entries {
+            grouped.entry(parent).or_default().push(e);
+        }
+        let frames = grouped
+            .into_iter()
+            .map(|(parent, entries)| (parent, FrameGrid::new(entries)))
+            .collect();
+        Self { frames }
+    }
+
+    /// Removes a single entry without rebuilding the index.
+    pub fn remove(&mut self, id: EntityId) -> bool {
+        let Some(parent) = self
+            .frames
+            .iter_mut()
+            .find_map(|(parent, f)| f.remove(id).then_some(*parent))
+        else {
+            return false;
+        };
+        if self.frames[&parent].entries.is_empty() {
+            self.frames.remove(&parent);
+        }
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.values().map(|f| f.entries.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// All entries within the given distance of a point, sorted by id.
+    pub fn within_radius(&self, pos: DVec2, radius: f64) -> Vec<IndexEntry> {
+        let mut ret = vec![];
+        for frame in self.frames.values() {
+            frame.for_each_in_box(pos - radius, pos + radius, |e| {
+                if e.pos.distance(pos) <= radius {
+                    ret.push(*e);
+                }
+            });
+        }
+        ret.sort_by_key(|e| e.id);
+        ret
+    }
+
+    /// All entries inside the given bounding box, sorted by id.
+    pub fn within_bounds(&self, bounds: AABB) -> Vec<IndexEntry> {
+        let lo = bounds.lower().as_dvec2();
+        let hi = bounds.upper().as_dvec2();
+        let mut ret = vec![];
+        for frame in self.frames.values() {
+            frame.for_each_in_box(lo, hi, |e| {
+                if bounds.contains(aabb_stopgap_cast(e.pos)) {
+                    ret.push(*e);
+                }
+            });
+        }
+        ret.sort_by_key(|e| e.id);
+        ret
+    }
+
+    /// The closest entry to a point, and its distance, optionally limited
+    /// to some maximum distance.
+    pub fn nearest(&self, pos: DVec2, max_dist: impl Into<Option<f64>>) -> Option<(EntityId, f64)> {
+        let max_dist = max_dist.into().unwrap_or(f64::INFINITY);
+        self.frames
+            .values()
+            .filter_map(|f| f.nearest(pos, max_dist))
+            .min_by(|(a, d1), (b, d2)| d1.total_cmp(d2).then(a.id.cmp(&b.id)))
+            .map(|(e, d)| (e.id, d))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_entries(rng: &mut StdRng, n: usize) -> Vec<(EntityId, IndexEntry)> {
+        (0..n)
+            .map(|i| {
+                // two tight clusters far apart, plus a sparse background
+                let (parent, center, spread) = match i % 3 {
+                    0 => (EntityId(0), DVec2::ZERO, 1E4),
+                    1 => (EntityId(1), DVec2::new(1E9, -3E8), 50.0),
+                    _ => (EntityId(0), DVec2::ZERO, 1E7),
+                };
+                let pos = center + randvec_from(rng, 0.0, 1.0).as_dvec2() * spread;
+                (
+                    parent,
+                    IndexEntry {
+                        id: EntityId(i as i64),
+                        pos,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn queries_match_brute_force() {
+        let mut rng = StdRng::seed_from_u64(33);
+        let entries = random_entries(&mut rng, 2000);
+        let index = SpatialIndex::from_entries(entries.clone());
+        assert_eq!(index.len(), 2000);
+
+        let all: Vec<IndexEntry> = entries.iter().map(|(_, e)| *e).collect();
+
+        for i in 0..200 {
+            let pos = all[i * 7].pos + randvec_from(&mut rng, 0.0, 2E4).as_dvec2();
+            let radius = rng.gen_range(1.0..1E6);
+
+            let mut expected: Vec<_> = all
+                .iter()
+                .filter(|e| e.pos.distance(pos) <= radius)
+                .cloned()
+                .collect();
+            expected.sort_by_key(|e| e.id);
+            assert_eq!(index.within_radius(pos, radius), expected);
+
+            let nearest = all
+                .iter()
+                .map(|e| (e.id, e.pos.distance(pos)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap();
+            assert_eq!(index.nearest(pos, None), Some(nearest));
+
+            let limited = index.nearest(pos, nearest.1 * 0.5);
+            assert!(limited.is_none() || nearest.1 == 0.0);
+        }
+    }
+
+    #[test]
+    fn bounds_query_matches_brute_force() {
+        let entries = random_entries(&mut StdRng::seed_from_u64(33), 900);
+        let index = SpatialIndex::from_entries(entries.clone());
+        let bounds = AABB::new(Vec2::new(1000.0, -2000.0), Vec2::new(3E4, 2E4));
+        let mut expected: Vec<_> = entries
+            .iter()
+            .map(|(_, e)| *e)
+            .filter(|e| bounds.contains(aabb_stopgap_cast(e.pos)))
+            .collect();
+        expected.sort_by_key(|e| e.id);
+        assert!(!expected.is_empty());
+        assert_eq!(index.within_bounds(bounds), expected);
+    }
+
+    #[test]
+    fn removed_entries_leave_the_index() {
+        let entries = random_entries(&mut StdRng::seed_from_u64(33), 600);
+        let mut index = SpatialIndex::from_entries(entries.clone());
+
+        // everything in the tight cluster, and some of the rest
+        let (gone, kept): (Vec<_>, Vec<_>) = entries
+            .iter()
+            .map(|(parent, e)| (*parent, *e))
+            .partition(|(parent, e)| *parent == EntityId(1) || e.id.0 % 4 == 0);
+        for (_, e) in &gone {
+            assert!(index.remove(e.id));
+        }
+        assert!(!index.remove(gone[0].1.id));
+        assert_eq!(index.len(), kept.len());
+
+        let rebuilt = SpatialIndex::from_entries(kept);
+        for (_, e) in entries.iter().step_by(5) {
+            assert_eq!(
+                index.within_radius(e.pos, 1E6),
+                rebuilt.within_radius(e.pos, 1E6)
+            );
+            assert_eq!(index.nearest(e.pos, None), rebuilt.nearest(e.pos, None));
+        }
+    }
+
+    #[test]
+    fn vehicles_are_indexed_when_added() {
+        let mut universe = Universe::new(PlanetarySystem::new(EntityId(0), "test", Body::LUNA));
+        let vehicle = Vehicle::from_parts("v".into(), "v".into(), vec![], Default::default());
+        let id = universe
+            .add_surface_vehicle(EntityId(0), vehicle, 0.0, 1000.0)
+            .unwrap();
+        let pos = universe.pv(id).unwrap().pos;
+        assert_eq!(universe.spatial_index().nearest(pos, None), Some((id, 0.0)));
+    }
+
+    #[test]
+    fn empty_index() {
+        let index = SpatialIndex::default();
+        assert!(index.is_empty());
+        assert!(index.nearest(DVec2::ZERO, None).is_none());
+        assert!(index.within_radius(DVec2::ZERO, 1E9).is_empty());
+    }
+}
@@ -0,0 +1,438 @@
+use crate::prelude::*;
+
+/// Iterations used when bisecting for the collinear Lagrange points; each
+/// halves the bracket, so this is well past double precision.
+const BISECTION_ITERATIONS: usize = 200;
+
+/// Half-width of the region covered by zero-velocity curves, as a multiple
+/// of the distance between the two bodies.
+const CONTOUR_EXTENT: f64 = 1.6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LagrangePoint {
+    L1,
+    L2,
+    L3,
+    L4,
+    L5,
+}
+
+impl LagrangePoint {
+    pub const ALL: [LagrangePoint; 5] = [
+        LagrangePoint::L1,
+        LagrangePoint::L2,
+        LagrangePoint::L3,
+        LagrangePoint::L4,
+        LagrangePoint::L5,
+    ];
+}
+
+impl std::fmt::Display for LagrangePoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// A moon and its parent, approximated as the circular restricted
+/// three-body problem: both bodies circle their barycenter at the moon's
+/// current distance, and a massless vehicle moves under their combined
+/// gravity. The rotating frame keeps both bodies fixed, and the Jacobi
+/// constant of a vehicle is conserved in it.
+///
+/// Positions and velocities passed in and out are relative to the primary,
+/// in the inertial frame, at the moment the system was built.
+#[derive(Debug, Clone, Copy)]
+pub struct ThreeBodySystem {
+    pub primary: EntityId,
+    pub secondary: EntityId,
+    pub primary_body: Body,
+    pub secondary_body: Body,
+    /// Position of the secondary relative to the primary
+    pub separation: DVec2,
+    /// Angular rate of the rotating frame, in radians per second; negative
+    /// for a retrograde moon
+    pub omega: f64,
+}
+
+impl ThreeBodySystem {
+    /// The rate of the rotating frame is that of a circular orbit at the
+    /// current separation, in the direction the moon is currently moving.
+    pub fn new(
+        primary: (EntityId, Body),
+        secondary: (EntityId, Body),
+        secondary_pv: PV,
+    ) -> Option<Self> {
+        let a = secondary_pv.pos.length();
+        let mu = primary.1.mu + secondary.1.mu;
+        if a == 0.0 || mu <= 0.0 {
+            return None;
+        }
+        let direction = cross2d(secondary_pv.pos, secondary_pv.vel).signum();
+        Some(ThreeBodySystem {
+            primary: primary.0,
+            secondary: secondary.0,
+            primary_body: primary.1,
+            secondary_body: secondary.1,
+            separation: secondary_pv.pos,
+            omega: direction * (mu / a.powi(3)).sqrt(),
+        })
+    }
+
+    /// The system formed by a moon and its parent.
+    pub fn from_planets(
+        planets: &PlanetarySystem,
+        secondary: EntityId,
+        stamp: Nanotime,
+    ) -> Option<Self> {
+        let (body, pv, parent, _) = planets.lookup(secondary, stamp)?;
+        let (parent_body, parent_pv, _, _) = planets.lookup(parent?, stamp)?;
+        Self::new((parent?, parent_body), (secondary, body), pv - parent_pv)
+    }
+
+    /// The system most relevant to a vehicle orbiting the given body: the
+    /// body and its parent if it's a moon, otherwise the body and its most
+    /// massive moon.
+    pub fn for_body(planets: &PlanetarySystem, id: EntityId, stamp: Nanotime) -> Option<Self> {
+        let (_, _, parent, sys) = planets.lookup(id, stamp)?;
+        if parent.is_some() {
+            return Self::from_planets(planets, id, stamp);
+        }
+        let moon = sys
+            .subsystems
+            .iter()
+            .max_by(|(_, a), (_, b)| a.body.mu.total_cmp(&b.body.mu))?;
+        Self::from_planets(planets, moon.1.id, stamp)
+    }
+
+    /// Pairs each moon in the system with its parent.
+    pub fn all(planets: &PlanetarySystem, stamp: Nanotime) -> Vec<Self> {
+        planets
+            .planet_ids()
+            .into_iter()
+            .filter_map(|id| Self::from_planets(planets, id, stamp))
+            .collect()
+    }
+
+    /// Ratio of the secondary's mass to the total mass.
+    pub fn mass_ratio(&self) -> f64 {
+        self.secondary_body.mu / (self.primary_body.mu + self.secondary_body.mu)
+    }
+
+    pub fn distance(&self) -> f64 {
+        self.separation.length()
+    }
+
+    fn angle(&self) -> f64 {
+        self.separation.to_angle()
+    }
+
+    /// Barycenter of the two bodies, relative to the primary.
+    pub fn barycenter(&self) -> DVec2 {
+        self.separation * self.mass_ratio()
+    }
+
+    fn barycenter_velocity(&self) -> DVec2 {
+        self.barycenter().perp() * self.omega
+    }
+
+    /// Converts a position relative to the primary into the rotating
+    /// frame, centered on the barycenter with the secondary along +X.
+    pub fn to_rotating(&self, pos: DVec2) -> DVec2 {
+        rotate_f64(pos - self.barycenter(), -self.angle())
+    }
+
+    pub fn from_rotating(&self, pos: DVec2) -> DVec2 {
+        self.barycenter() + rotate_f64(pos, self.angle())
+    }
+
+    /// Converts a state relative to the primary into the rotating frame.
+    pub fn to_rotating_pv(&self, pv: PV) -> PV {
+        let r = pv.pos - self.barycenter();
+        let v = pv.vel - self.barycenter_velocity() - r.perp() * self.omega;
+        PV::from_f64(rotate_f64(r, -self.angle()), rotate_f64(v, -self.angle()))
+    }
+
+    /// Twice the effective potential (centrifugal plus gravitational) at a
+    /// point in the rotating frame.
+    fn rotating_potential(&self, p: DVec2) -> f64 {
+        let a = self.distance();
+        let mu = self.mass_ratio();
+        let r1 = p.distance(DVec2::new(-mu * a, 0.0)).max(1.0);
+        let r2 = p.distance(DVec2::new((1.0 - mu) * a, 0.0)).max(1.0);
+        self.omega.powi(2) * p.length_squared()
+            + 2.0 * self.primary_body.mu / r1
+            + 2.0 * self.secondary_body.mu / r2
+    }
+
+    /// Jacobi constant of a state relative to the primary, in m^2/s^2.
+    /// Larger values are more tightly bound to one of the two bodies.
+    pub fn jacobi_constant(&self, pv: PV) -> f64 {
+        let rot = self.to_rotating_pv(pv);
+        self.rotating_potential(rot.pos) - rot.vel.length_squared()
+    }
+
+    /// Jacobi constant of a vehicle at rest in the rotating frame at the
+    /// given position. The zero-velocity curve for a constant C is where
+    /// this equals C.
+    pub fn jacobi_at_rest(&self, pos: DVec2) -> f64 {
+        self.rotating_potential(self.to_rotating(pos))
+    }
+
+    /// Whether a vehicle with the given Jacobi constant could ever reach a
+    /// position; it can't enter regions where it would need negative
+    /// kinetic energy in the rotating frame.
+    pub fn is_reachable(&self, pos: DVec2, jacobi: f64) -> bool {
+        self.jacobi_at_rest(pos) >= jacobi
+    }
+
+    /// Position of a Lagrange point in the rotating frame.
+    fn rotating_lagrange_point(&self, point: LagrangePoint) -> DVec2 {
+        let mu = self.mass_ratio();
+        let eps = 1E-9;
+        let h = 3.0f64.sqrt() / 2.0;
+        let p = match point {
+            LagrangePoint::L1 => DVec2::new(collinear_point(mu, -mu + eps, 1.0 - mu - eps), 0.0),
+            LagrangePoint::L2 => DVec2::new(collinear_point(mu, 1.0 - mu + eps, 2.0), 0.0),
+            LagrangePoint::L3 => DVec2::new(collinear_point(mu, -2.0, -mu - eps), 0.0),
+            LagrangePoint::L4 => DVec2::new(0.5 - mu, h),
+            LagrangePoint::L5 => DVec2::new(0.5 - mu, -h),
+        };
+        p * self.distance()
+    }
+
+    /// Position of a Lagrange point relative to the primary. L4 leads the
+    /// secondary along its orbit and L5 trails it.
+    pub fn lagrange_point(&self, point: LagrangePoint) -> DVec2 {
+        let mut p = self.rotating_lagrange_point(point);
+        if self.omega < 0.0 {
+            p.y = -p.y;
+        }
+        self.from_rotating(p)
+    }
+
+    pub fn lagrange_points(&self) -> [(LagrangePoint, DVec2); 5] {
+        LagrangePoint::ALL.map(|p| (p, self.lagrange_point(p)))
+    }
+
+    /// Jacobi constant of a vehicle at rest at a Lagrange point. A vehicle
+    /// whose constant is below that of L1 can pass between the two bodies;
+    /// below L2 or L3 it can escape the system entirely.
+    pub fn critical_jacobi(&self, point: LagrangePoint) -> f64 {
+        self.jacobi_at_rest(self.lagrange_point(point))
+    }
+
+    /// Zero-velocity curves for the given Jacobi constant, as line segments
+    /// relative to the primary, traced with marching squares over a square
+    /// grid of the given resolution around the barycenter.
+    pub fn zero_velocity_curves(&self, jacobi: f64, resolution: usize) -> Vec<(DVec2, DVec2)> {
+        let n = resolution.max(2);
+        let half = self.distance() * CONTOUR_EXTENT;
+        let step = 2.0 * half / n as f64;
+        let corner = |i: usize, j: usize| DVec2::new(i as f64, j as f64) * step - half;
+
+        let values: Vec<Vec<f64>> = (0..=n)
+            .map(|i| {
+                (0..=n)
+                    .map(|j| self.rotating_potential(corner(i, j)) - jacobi)
+                    .collect()
+            })
+            .collect();
+
+        let mut segments = vec![];
+        for i in 0..n {
+            for j in 0..n {
+                let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+                let v = corners.map(|(x, y)| values[x][y]);
+                let p = corners.map(|(x, y)| corner(x, y));
+
+                let crossings: Vec<DVec2> = (0..4)
+                    .filter_map(|k| {
+                        let (a, b) = (v[k], v[(k + 1) % 4]);
+                        if (a < 0.0) == (b < 0.0) {
+                            return None;
+                        }
+                        let t = a / (a - b);
+                        Some(p[k].lerp(p[(k + 1) % 4], t))
+                    })
+                    .collect();
+
+                match crossings.as_slice() {
+                    [a, b] => segments.push((*a, *b)),
+                    [a, b, c, d] => {
+                        // saddle; pick the pairing that keeps the center's
+                        // side connected
+                        let center = v.iter().sum::<f64>() / 4.0;
+                        if (center < 0.0) == (v[0] < 0.0) {
+                            segments.push((*a, *d));
+                            segments.push((*b, *c));
+                        } else {
+                            segments.push((*a, *b));
+                            segments.push((*c, *d));
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        segments
+            .into_iter()
+            .map(|(a, b)| (self.from_rotating(a), self.from_rotating(b)))
+            .collect()
+    }
+}
+
+/// Finds the root of the dimensionless collinear equilibrium condition
+/// within the given bracket, where the primary sits at -mu and the
+/// secondary at 1 - mu.
+fn collinear_point(mu: f64, mut lo: f64, mut hi: f64) -> f64 {
+    let f = |x: f64| {
+        let d1 = x + mu;
+        let d2 = x - 1.0 + mu;
+        x - (1.0 - mu) * d1 / d1.abs().powi(3) - mu * d2 / d2.abs().powi(3)
+    };
+    let lo_sign = f(lo) < 0.0;
+    for _ in 0..BISECTION_ITERATIONS {
+        let mid = (lo + hi) / 2.0;
+        if (f(mid) < 0.0) == lo_sign {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    (lo + hi) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn earth_luna() -> ThreeBodySystem {
+        let earth = Body::with_mu(EARTH_RADIUS, EARTH_MU, EARTH_SOI);
+        let luna = Body::with_mu(LUNA_RADIUS, LUNA_MU, LUNA_SOI);
+        let a = LUNA_ORBITAL_RADIUS;
+        let v = (EARTH_MU / a).sqrt();
+        let pv = PV::from_f64(
+            rotate_f64(DVec2::new(a, 0.0), 0.7),
+            rotate_f64(DVec2::new(0.0, v), 0.7),
+        );
+        ThreeBodySystem::new((EntityId(0), earth), (EntityId(1), luna), pv).unwrap()
+    }
+
+    #[test]
+    fn lagrange_points_are_equilibria() {
+        let sys = earth_luna();
+        let a = sys.distance();
+
+        // well known distances of the Earth-Moon points from Earth
+        let l1 = sys.lagrange_point(LagrangePoint::L1).length() / a;
+        let l2 = sys.lagrange_point(LagrangePoint::L2).length() / a;
+        let l3 = sys.lagrange_point(LagrangePoint::L3).length() / a;
+        assert!((l1 - 0.8491).abs() < 1E-3, "{l1}");
+        assert!((l2 - 1.1678).abs() < 1E-3, "{l2}");
+        assert!((l3 - 0.9929).abs() < 1E-3, "{l3}");
+
+        for p in [LagrangePoint::L4, LagrangePoint::L5] {
+            let pos = sys.lagrange_point(p);
+            assert!((pos.distance(sys.barycenter()) / a - 1.0).abs() < 0.02);
+            assert!((pos.distance(sys.separation) / a - 1.0).abs() < 1E-9);
+        }
+
+        // the effective potential is stationary at every point
+        let h = a * 1E-6;
+        let scale = sys.omega.powi(2) * a;
+        for (p, pos) in sys.lagrange_points() {
+            let dx =
+                sys.jacobi_at_rest(pos + DVec2::X * h) - sys.jacobi_at_rest(pos - DVec2::X * h);
+            let dy =
+                sys.jacobi_at_rest(pos + DVec2::Y * h) - sys.jacobi_at_rest(pos - DVec2::Y * h);
+            let grad = DVec2::new(dx, dy) / (2.0 * h);
+            assert!(grad.length() < scale * 1E-5, "{p}: {grad}");
+        }
+
+        // L4 leads the moon
+        let l4 = sys.lagrange_point(LagrangePoint::L4);
+        assert!(cross2d(sys.separation, l4) > 0.0);
+    }
+
+    #[test]
+    fn jacobi_constant_is_conserved() {
+        let sys = earth_luna();
+        let mu = sys.mass_ratio();
+        let (m1, m2) = (sys.primary_body.mu, sys.secondary_body.mu);
+
+        // propagate a vehicle in the inertial frame with both bodies on
+        // circular orbits about the barycenter
+        let bodies = |t: f64| {
+            let bary = sys.barycenter();
+            let p1 = bary + rotate_f64(-bary, sys.omega * t);
+            let p2 = bary + rotate_f64(sys.separation - bary, sys.omega * t);
+            (p1, p2)
+        };
+        let accel = |p: DVec2, t: f64| {
+            let (p1, p2) = bodies(t);
+            let d1 = p1 - p;
+            let d2 = p2 - p;
+            m1 * d1 / d1.length().powi(3) + m2 * d2 / d2.length().powi(3)
+        };
+
+        let mut pos = sys.lagrange_point(LagrangePoint::L1) * 0.9;
+        let mut vel = pos.perp().normalize() * 900.0;
+        let dt = 10.0;
+
+        // the primary moves, so measure against its position at time t
+        let jacobi = |pos: DVec2, vel: DVec2, t: f64| {
+            let (p1, _) = bodies(t);
+            let v1 = (p1 - sys.barycenter()).perp() * sys.omega;
+            let rotated = ThreeBodySystem {
+                separation: rotate_f64(sys.separation, sys.omega * t),
+                ..sys
+            };
+            rotated.jacobi_constant(PV::from_f64(pos - p1, vel - v1))
+        };
+
+        let c0 = jacobi(pos, vel, 0.0);
+        let mut t = 0.0;
+        for _ in 0..20_000 {
+            let k1v = accel(pos, t);
+            let k1p = vel;
+            let k2v = accel(pos + k1p * dt / 2.0, t + dt / 2.0);
+            let k2p = vel + k1v * dt / 2.0;
+            let k3v = accel(pos + k2p * dt / 2.0, t + dt / 2.0);
+            let k3p = vel + k2v * dt / 2.0;
+            let k4v = accel(pos + k3p * dt, t + dt);
+            let k4p = vel + k3v * dt;
+            pos += (k1p + 2.0 * k2p + 2.0 * k3p + k4p) * dt / 6.0;
+            vel += (k1v + 2.0 * k2v + 2.0 * k3v + k4v) * dt / 6.0;
+            t += dt;
+        }
+
+        let c1 = jacobi(pos, vel, t);
+        assert!(mu > 0.0);
+        assert!(((c1 - c0) / c0).abs() < 1E-8, "{c0} {c1}");
+    }
+
+    #[test]
+    fn zero_velocity_curves_bound_forbidden_regions() {
+        let sys = earth_luna();
+        let c1 = sys.critical_jacobi(LagrangePoint::L1);
+        let c2 = sys.critical_jacobi(LagrangePoint::L2);
+        let c4 = sys.critical_jacobi(LagrangePoint::L4);
+        assert!(c1 > c2 && c2 > c4);
+
+        // between L1 and L2 energies the neck at L1 is open but L2 is not
+        let c = (c1 + c2) / 2.0;
+        assert!(sys.is_reachable(sys.lagrange_point(LagrangePoint::L1), c));
+        assert!(!sys.is_reachable(sys.lagrange_point(LagrangePoint::L2), c));
+        assert!(!sys.is_reachable(sys.lagrange_point(LagrangePoint::L4), c));
+
+        let curves = sys.zero_velocity_curves(c, 200);
+        assert!(!curves.is_empty());
+        for (a, b) in curves {
+            for p in [a, b] {
+                let err = (sys.jacobi_at_rest(p) - c) / c;
+                assert!(err.abs() < 0.05, "{err}");
+            }
+        }
+    }
+}
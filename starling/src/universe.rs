@@ -1,8 +1,30 @@
 use crate::control_signals::ControlSignals;
 use crate::prelude::*;
+use rayon::prelude::*;
 use std::collections::{HashMap, HashSet};
 use std::time::{Duration, Instant};
 
+/// Mutable references to every vehicle, sorted by id.
+fn vehicles_by_id(
+    vehicles: &mut HashMap<EntityId, SurfaceSpacecraftEntity>,
+) -> Vec<(&EntityId, &mut SurfaceSpacecraftEntity)> {
+    let mut ret: Vec<_> = vehicles.iter_mut().collect();
+    ret.sort_by_key(|(id, _)| **id);
+    ret
+}
+
+/// How the universe was advanced during a call to `Universe::on_sim_ticks`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SimMode {
+    /// Every tick simulated individually
+    #[default]
+    Stepped,
+    /// All vehicles propagated analytically on rails
+    Batch,
+    /// Off-rails vehicles integrated several ticks at a time
+    PhysicsWarp,
+}
+
 pub struct Universe {
     stamp: Nanotime,
     ticks: u128,
@@ -11,6 +33,16 @@ pub struct Universe {
     pub planets: PlanetarySystem,
     pub constellations: HashMap<EntityId, EntityId>,
     pub thrust_particles: ThrustParticleEffects,
+    pub ground_stations: Vec<GroundStation>,
+    comms: CommsNetwork,
+    spatial_index: SpatialIndex,
+    pub physics: PhysicsSettings,
+    /// Seeds sensor noise, so the same universe always sees the same
+    /// radar returns
+    pub sensor_seed: u64,
+    pub conjunctions: ConjunctionScreener,
+    nbody: Option<NBodyPlanets>,
+    crashes: Vec<(EntityId, Impact)>,
 }
 
 impl Universe {
@@ -20,6 +52,7 @@ impl Universe {
     }
 
     pub fn new(planets: PlanetarySystem) -> Self {
+        let ground_stations = default_ground_stations(&planets);
         Self {
             stamp: Nanotime::zero(),
             ticks: 0,
@@ -28,6 +61,14 @@ impl Universe {
             planets,
             constellations: HashMap::new(),
             thrust_particles: ThrustParticleEffects::new(),
+            ground_stations,
+            comms: CommsNetwork::default(),
+            spatial_index: SpatialIndex::default(),
+            physics: PhysicsSettings::default(),
+            sensor_seed: 0,
+            conjunctions: ConjunctionScreener::default(),
+            nbody: None,
+            crashes: vec![],
         }
     }
 
@@ -45,8 +86,10 @@ impl Universe {
         ret
     }
 
-    pub fn remove(&mut self, id: EntityId) {
-        self.surface_vehicles.remove(&id);
+    pub fn remove(&mut self, id: EntityId) -> Option<SurfaceSpacecraftEntity> {
+        let ret = self.surface_vehicles.remove(&id);
+        self.spatial_index.remove(id);
+        ret
     }
 
     pub fn on_sim_ticks(
@@ -54,16 +97,39 @@ impl Universe {
         ticks: u32,
         signals: &ControlSignals,
         max_dur: Duration,
-    ) -> (u32, Duration, bool) {
+    ) -> (u32, Duration, SimMode) {
         let start = Instant::now();
         let mut actual_ticks = 0;
         let mut exec_time = Duration::ZERO;
 
-        let batch_mode = if self.can_run_batch_mode() && signals.is_empty() {
-            self.run_batch_ticks(ticks);
-            exec_time = std::time::Instant::now() - start;
-            actual_ticks = ticks;
-            true
+        let mode = if self.can_run_batch_mode() && signals.is_empty() {
+            // missions get a look in at each of their events, and may take
+            // vehicles off rails when they do
+            while actual_ticks < ticks && self.can_run_batch_mode() {
+                let n = self.ticks_until_mission_event(ticks - actual_ticks);
+                self.run_batch_ticks(n);
+                actual_ticks += n;
+                exec_time = std::time::Instant::now() - start;
+                if exec_time > max_dur {
+                    break;
+                }
+            }
+            SimMode::Batch
+        } else if self.physics.warp && signals.is_empty() && ticks > 1 {
+            // controls are held over each warp step, so the first tick is
+            // always taken on its own to pick up any change in policy
+            let mut limit = 1;
+            while actual_ticks < ticks {
+                let n = self.next_warp_step(limit.min(ticks - actual_ticks));
+                self.run_warp_step(n);
+                actual_ticks += n;
+                limit = self.physics.max_warp_ticks;
+                exec_time = std::time::Instant::now() - start;
+                if exec_time > max_dur {
+                    break;
+                }
+            }
+            SimMode::PhysicsWarp
         } else {
             for _ in 0..ticks {
                 actual_ticks += 1;
@@ -73,10 +139,62 @@ impl Universe {
                     break;
                 }
             }
-            false
+            SimMode::Stepped
         };
 
-        (actual_ticks, exec_time, batch_mode)
+        self.update_crashes();
+        self.update_conjunctions();
+
+        (actual_ticks, exec_time, mode)
+    }
+
+    /// Number of ticks the next physics warp step may cover, limited by
+    /// the vehicle which can least afford to be warped.
+    fn next_warp_step(&self, limit: u32) -> u32 {
+        self.surface_vehicles
+            .values()
+            .map(|sv| sv.max_warp_ticks(&self.planets, self.stamp, limit))
+            .min()
+            .unwrap_or(limit)
+            .max(1)
+    }
+
+    /// Advances the universe by several ticks at once. Vehicles which can
+    /// be put on rails are propagated analytically, and the rest are
+    /// integrated with adaptive steps.
+    fn run_warp_step(&mut self, ticks: u32) {
+        if ticks == 1 {
+            self.on_sim_tick(&ControlSignals::new());
+            return;
+        }
+
+        self.ticks += ticks as u128;
+        self.stamp += PHYSICS_CONSTANT_DELTA_TIME * ticks;
+        let stamp = self.stamp;
+
+        self.step_planets();
+        self.thrust_particles.step();
+
+        let planets = &self.planets;
+        let physics = self.physics;
+        vehicles_by_id(&mut self.surface_vehicles)
+            .par_iter_mut()
+            .for_each(|(_, sv)| {
+                if sv.can_be_on_rails() {
+                    sv.step_on_rails(PHYSICS_CONSTANT_DELTA_TIME * ticks, stamp, planets);
+                } else {
+                    sv.step_ticks(planets, stamp, VehicleControl::NULLOPT, physics, ticks);
+                }
+            });
+
+        self.constellations
+            .retain(|id, _| self.surface_vehicles.contains_key(id));
+
+        self.update_spatial_index();
+        self.update_vehicle_relative_info();
+        self.update_sensor_tracks();
+        self.update_comms();
+        self.update_missions();
     }
 
     fn can_run_batch_mode(&self) -> bool {
@@ -87,16 +205,27 @@ impl Universe {
 
     fn step_surface_vehicles(&mut self, signals: &ControlSignals) {
         let stamp = self.stamp();
+        let planets = &self.planets;
+        let comms = &self.comms;
+        let physics = self.physics;
 
-        for (id, sv) in &mut self.surface_vehicles {
-            let ext = *signals
-                .piloting_commands
-                .get(&id)
-                .unwrap_or(&VehicleControl::NULLOPT);
+        // each vehicle only reads the planetary system and writes its own
+        // state, so they're stepped in parallel. side effects are merged
+        // afterwards in order of id, independent of thread scheduling.
+        let mut vehicles = vehicles_by_id(&mut self.surface_vehicles);
+
+        vehicles.par_iter_mut().for_each(|(id, sv)| {
+            // vehicles out of contact with the ground can't be piloted
+            let ext = match signals.piloting_commands.get(*id) {
+                Some(ctrl) if comms.is_connected(**id) => *ctrl,
+                _ => VehicleControl::NULLOPT,
+            };
 
-            sv.step(&self.planets, stamp, ext);
+            sv.step(planets, stamp, ext, physics);
+        });
 
-            let atmo = match self.planets.lookup(sv.parent(), stamp) {
+        for (_, sv) in vehicles {
+            let atmo = match planets.lookup(sv.parent(), stamp) {
                 Some((body, _, _, _)) => {
                     let altitude = sv.body.pv.pos.length() - body.radius;
                     (1.0 - altitude / 200_000.0).clamp(0.0, 1.0)
@@ -114,6 +243,73 @@ impl Universe {
         }
     }
 
+    /// Brings the planets up to the current time under N-body gravity, if
+    /// enabled. Integration starts from wherever the planets are when the
+    /// mode is switched on, and they're left on their osculating orbits
+    /// when it's switched off.
+    fn step_planets(&mut self) {
+        if self.physics.gravity != GravityModel::NBody {
+            self.nbody = None;
+            return;
+        }
+
+        let nbody = self
+            .nbody
+            .get_or_insert_with(|| NBodyPlanets::new(&self.planets, self.stamp));
+        nbody.propagate_to(self.stamp);
+        nbody.apply_to(&mut self.planets);
+    }
+
+    /// Rebuilds the spatial index. Done every tick, but should also be
+    /// called after moving vehicles around by hand.
+    pub fn update_spatial_index(&mut self) {
+        self.spatial_index = SpatialIndex::new(self);
+    }
+
+    /// Index of vehicle positions as of the most recent tick, or the last
+    /// time a vehicle was added or removed.
+    pub fn spatial_index(&self) -> &SpatialIndex {
+        &self.spatial_index
+    }
+
+    /// Collects touchdowns since the last update, keeping those made at
+    /// unsafe speeds.
+    fn update_crashes(&mut self) {
+        for (id, sv) in vehicles_by_id(&mut self.surface_vehicles) {
+            if let Some(touchdown) = sv.take_touchdown() {
+                if !touchdown.is_safe() {
+                    self.crashes.push((*id, touchdown));
+                }
+            }
+        }
+    }
+
+    /// Vehicles which have hit the ground too fast since this was last
+    /// called.
+    pub fn take_crashes(&mut self) -> Vec<(EntityId, Impact)> {
+        std::mem::take(&mut self.crashes)
+    }
+
+    /// Where and when a vehicle would hit the ground if it coasted from
+    /// now on.
+    pub fn predict_impact(&self, id: EntityId) -> Option<Impact> {
+        let sv = self.surface_vehicles.get(&id)?;
+        if sv.is_landed() {
+            return None;
+        }
+        let (body, _, _, _) = self.planets.lookup(sv.parent(), self.stamp)?;
+        let orbit = SparseOrbit::from_pv(sv.pv(), body, self.stamp)?;
+        predict_impact(&GlobalOrbit(sv.parent(), orbit), self.stamp)
+    }
+
+    /// Reruns conjunction screening if it's been long enough since the
+    /// last time.
+    pub fn update_conjunctions(&mut self) {
+        let mut screener = std::mem::take(&mut self.conjunctions);
+        screener.screen_if_due(self);
+        self.conjunctions = screener;
+    }
+
     fn update_vehicle_relative_info(&mut self) {
         let mut rel = HashMap::new();
         for (id, sv) in &self.surface_vehicles {
@@ -132,15 +328,127 @@ impl Universe {
         }
     }
 
+    fn update_sensor_tracks(&mut self) {
+        let stamp = self.stamp;
+        let mut detections = radar_detections(self);
+        for (id, sv) in &mut self.surface_vehicles {
+            if let Some(dets) = detections.remove(id) {
+                for det in dets {
+                    sv.tracks.insert(det);
+                }
+            }
+            sv.tracks.prune(stamp);
+            sv.tracks.update_estimates(&self.planets);
+        }
+    }
+
+    fn update_comms(&mut self) {
+        self.comms = CommsNetwork::new(self);
+    }
+
+    pub fn comms(&self) -> &CommsNetwork {
+        &self.comms
+    }
+
+    /// Whether a vehicle can be commanded from the ground. Vehicles with no
+    /// antenna have no path to a ground station, so they never can.
+    pub fn has_comms(&self, id: EntityId) -> bool {
+        self.comms.is_connected(id)
+    }
+
+    pub fn set_vehicle_policy(
+        &mut self,
+        id: EntityId,
+        policy: VehicleControlPolicy,
+    ) -> Result<(), &'static str> {
+        if !self.has_comms(id) {
+            return Err("No connection to ground station");
+        }
+        let sv = self
+            .surface_vehicles
+            .get_mut(&id)
+            .ok_or("No such vehicle")?;
+        sv.controller.set_policy(policy);
+        Ok(())
+    }
+
+    pub fn set_mission(&mut self, id: EntityId, script: MissionScript) -> Result<(), &'static str> {
+        if !self.has_comms(id) {
+            return Err("No connection to ground station");
+        }
+        let sv = self
+            .surface_vehicles
+            .get_mut(&id)
+            .ok_or("No such vehicle")?;
+        sv.set_mission(script);
+        Ok(())
+    }
+
+    fn mission_context(&self, sv: &SurfaceSpacecraftEntity) -> Option<MissionContext> {
+        let (body, _, _, _) = self.planets.lookup(sv.parent(), self.stamp)?;
+        let target = match sv.estimated_target_orbit() {
+            Some(GlobalOrbit(parent, orbit)) => (parent == sv.parent())
+                .then(|| orbit.pv(self.stamp).ok())
+                .flatten(),
+            None => sv
+                .target()
+                .and_then(|id| self.surface_vehicles.get(&id))
+                .filter(|t| t.parent() == sv.parent())
+                .map(|t| t.pv()),
+        };
+        Some(MissionContext {
+            stamp: self.stamp,
+            body,
+            target,
+        })
+    }
+
+    /// Lets every running mission act on the current state of its vehicle.
+    /// Missions run one after another in order of id, after all vehicles
+    /// have been stepped.
+    fn update_missions(&mut self) {
+        let contexts: HashMap<EntityId, MissionContext> = self
+            .surface_vehicles
+            .iter()
+            .filter(|(_, sv)| sv.mission().is_some_and(|m| m.is_running()))
+            .filter_map(|(id, sv)| Some((*id, self.mission_context(sv)?)))
+            .collect();
+
+        for (id, sv) in vehicles_by_id(&mut self.surface_vehicles) {
+            if let Some(ctx) = contexts.get(id) {
+                sv.update_mission(ctx);
+            }
+        }
+    }
+
+    /// Number of ticks, up to `limit`, which vehicles can be batched on
+    /// rails for before some mission needs to act.
+    fn ticks_until_mission_event(&self, limit: u32) -> u32 {
+        let dt = PHYSICS_CONSTANT_DELTA_TIME.inner();
+        self.surface_vehicles
+            .values()
+            .filter_map(|sv| {
+                let t = sv.mission()?.next_event(sv, &self.mission_context(sv)?)?;
+                let ticks = ((t - self.stamp).inner().max(0) + dt - 1) / dt;
+                Some(ticks.min(limit as i64) as u32)
+            })
+            .min()
+            .unwrap_or(limit)
+            .max(1)
+    }
+
     pub fn run_batch_ticks(&mut self, ticks: u32) {
         self.ticks += ticks as u128;
         let old_stamp = self.stamp;
         let delta_time = PHYSICS_CONSTANT_DELTA_TIME * ticks;
         self.stamp = old_stamp + delta_time;
 
-        for (_, sv) in &mut self.surface_vehicles {
-            sv.step_on_rails(delta_time, self.stamp, &self.planets);
-        }
+        self.step_planets();
+
+        let (stamp, planets) = (self.stamp, &self.planets);
+        self.surface_vehicles
+            .par_iter_mut()
+            .for_each(|(_, sv)| sv.step_on_rails(delta_time, stamp, planets));
 
         if ticks == 1 {
             self.thrust_particles.step();
@@ -148,13 +456,18 @@ impl Universe {
             self.thrust_particles.particles.clear();
         }
 
+        self.update_spatial_index();
         self.update_vehicle_relative_info();
+        self.update_sensor_tracks();
+        self.update_comms();
+        self.update_missions();
     }
 
     pub fn on_sim_tick(&mut self, signals: &ControlSignals) {
         self.ticks += 1;
         self.stamp += PHYSICS_CONSTANT_DELTA_TIME;
 
+        self.step_planets();
         self.thrust_particles.step();
 
         self.step_surface_vehicles(signals);
@@ -162,7 +475,11 @@ impl Universe {
         self.constellations
             .retain(|id, _| self.surface_vehicles.contains_key(id));
 
+        self.update_spatial_index();
         self.update_vehicle_relative_info();
+        self.update_sensor_tracks();
+        self.update_comms();
+        self.update_missions();
     }
 
     pub fn get_group_members(&mut self, gid: EntityId) -> Vec<EntityId> {
@@ -192,14 +509,36 @@ impl Universe {
         self.surface_vehicles.keys().into_iter().map(|id| *id)
     }
 
-    pub fn add_orbital_vehicle(&mut self, vehicle: Vehicle, orbit: GlobalOrbit) -> Option<()> {
+    pub fn add_orbital_vehicle(
+        &mut self,
+        vehicle: Vehicle,
+        orbit: GlobalOrbit,
+    ) -> Option<EntityId> {
         let id = self.next_entity_id();
         let mut body = RigidBody::random_spin();
         body.pv = orbit.1.pv(self.stamp).ok()?; // orbiter.pv(self.stamp, &self.planets)?;
         let controller = VehicleController::idle();
         let os = SurfaceSpacecraftEntity::new(orbit.0, vehicle, body, controller);
         self.surface_vehicles.insert(id, os);
-        Some(())
+        self.update_spatial_index();
+        self.update_comms();
+        Some(id)
+    }
+
+    /// Moves a vehicle to the given state relative to a planet, wherever
+    /// it was before.
+    pub fn teleport(&mut self, id: EntityId, parent: EntityId, pv: PV) -> Result<(), &'static str> {
+        let (body, _, _, _) = self
+            .planets
+            .lookup(parent, self.stamp)
+            .ok_or("No such planet")?;
+        let sv = self
+            .surface_vehicles
+            .get_mut(&id)
+            .ok_or("No such vehicle")?;
+        sv.teleport(parent, body, pv, self.stamp);
+        self.update_spatial_index();
+        Ok(())
     }
 
     pub fn add_surface_vehicle(
@@ -212,20 +551,24 @@ impl Universe {
         let lup = self.lup_planet(planet_id)?;
         let body = lup.body()?;
 
+        // the angle is a longitude in the body-fixed frame
         let pos = rotate_f64(DVec2::X * (body.radius + altitude), angle);
+        let pos = body.fixed_to_inertial(pos, self.stamp);
 
-        let vel = randvec(2.0, 7.0);
+        let vel = randvec(2.0, 7.0).as_dvec2() + body.surface_velocity(pos);
 
         let body = RigidBody {
             pv: PV::from_f64(pos, vel),
-            angle: PI_64 / 2.0,
-            angular_velocity: 0.0,
+            angle: pos.to_angle(),
+            angular_velocity: body.rotation_rate,
         };
 
         let controller = VehicleController::launch();
         let id = self.next_entity_id();
         let sv = SurfaceSpacecraftEntity::new(planet_id, vehicle, body, controller);
         self.surface_vehicles.insert(id, sv);
+        self.update_spatial_index();
+        self.update_comms();
 
         Some(id)
     }
@@ -239,6 +582,46 @@ impl Universe {
         Some(ObjectLookup(id, ScenarioObject::Orbiter(os), pv))
     }
 
+    /// Predicted position in the world frame of a coasting vehicle at
+    /// some time, assuming it stays on its current orbit.
+    pub fn predicted_pos(&self, id: EntityId, stamp: Nanotime) -> Option<DVec2> {
+        let sv = self.surface_vehicles.get(&id)?;
+        let (_, parent, _, _) = self.planets.lookup(sv.parent(), stamp)?;
+        Some(sv.orbit?.pv(stamp).ok()?.pos + parent.pos)
+    }
+
+    /// The three-body system most relevant to a vehicle, the position of
+    /// its primary in the world frame, and the vehicle's Jacobi constant.
+    pub fn three_body(&self, id: EntityId) -> Option<(ThreeBodySystem, DVec2, f64)> {
+        let sv = self.surface_vehicles.get(&id)?;
+        let sys = ThreeBodySystem::for_body(&self.planets, sv.parent(), self.stamp)?;
+        let (_, primary, _, _) = self.planets.lookup(sys.primary, self.stamp)?;
+        let jacobi = sys.jacobi_constant(self.pv(id)? - primary);
+        Some((sys, primary.pos, jacobi))
+    }
+
+    /// The orbit of a vehicle's target as far as that vehicle knows it:
+    /// estimated from its own sensor track, or the true orbit if it hasn't
+    /// got one.
+    pub fn target_orbit(&self, id: EntityId) -> Option<GlobalOrbit> {
+        let sv = self.surface_vehicles.get(&id)?;
+        match sv.estimated_target_orbit() {
+            Some(orbit) => Some(orbit),
+            None => self.surface_vehicles.get(&sv.target()?)?.current_orbit(),
+        }
+    }
+
+    /// Transfer from a vehicle's orbit onto its target's, as far as the
+    /// vehicle knows where its target is.
+    pub fn rendezvous_plan(&self, id: EntityId) -> Option<ManeuverPlan> {
+        let current = self.surface_vehicles.get(&id)?.current_orbit()?;
+        let target = self.target_orbit(id)?;
+        if current.0 != target.0 {
+            return None;
+        }
+        crate::planning::rendezvous_plan(&current.1, &target.1, self.stamp)
+    }
+
     pub fn pv(&self, id: EntityId) -> Option<PV> {
         if let Some((_, pv, _, _)) = self.planets.lookup(id, self.stamp) {
             return Some(pv);
@@ -301,10 +684,11 @@ pub fn orbiters_within_bounds(
     universe: &Universe,
     bounds: AABB,
 ) -> impl Iterator<Item = EntityId> + use<'_> {
-    universe.surface_vehicles.iter().filter_map(move |(id, _)| {
-        let pv = universe.pv(*id)?;
-        bounds.contains(aabb_stopgap_cast(pv.pos)).then(|| *id)
-    })
+    universe
+        .spatial_index()
+        .within_bounds(bounds)
+        .into_iter()
+        .map(|e| e.id)
 }
 
 pub fn nearest_orbiter_or_planet(
@@ -313,31 +697,27 @@ pub fn nearest_orbiter_or_planet(
     max_dist: impl Into<Option<f64>>,
 ) -> Option<EntityId> {
     let max_dist = max_dist.into();
-    let results = all_orbital_ids(universe)
+
+    let orbiter = universe.spatial_index().nearest(pos, max_dist);
+
+    let planet = universe
+        .planets
+        .planet_ids()
+        .into_iter()
         .filter_map(|id| {
-            let lup = match id {
-                ObjectId::Orbiter(id) => universe.lup_orbiter(id),
-                ObjectId::Planet(id) => universe.lup_planet(id),
-            }?;
-            let size = if let Some(body) = lup.body() {
-                body.radius
-            } else {
-                0.0
-            };
-            let p = lup.pv().pos;
-            let d = pos.distance(p);
-            let passes = if let Some(m) = max_dist {
-                d <= size + m
-            } else {
-                true
-            };
-            passes.then(|| (d, id.as_eid()))
+            let lup = universe.lup_planet(id)?;
+            let size = lup.body()?.radius;
+            let d = pos.distance(lup.pv().pos);
+            let passes = max_dist.map(|m| d <= size + m).unwrap_or(true);
+            passes.then_some((id, d))
         })
-        .collect::<Vec<_>>();
-    results
+        .min_by(|(_, d1), (_, d2)| d1.total_cmp(d2));
+
+    orbiter
         .into_iter()
-        .min_by(|(d1, _), (d2, _)| d1.total_cmp(d2))
-        .map(|(_, id)| id)
+        .chain(planet)
+        .min_by(|(_, d1), (_, d2)| d1.total_cmp(d2))
+        .map(|(id, _)| id)
 }
 
 pub fn landing_site_position(
@@ -348,7 +728,8 @@ pub fn landing_site_position(
     let lup = universe.lup_planet(planet_id)?;
     let body = lup.body()?;
     let center = lup.pv().pos;
-    Some(center + rotate_f64(DVec2::X * body.radius, angle))
+    let fixed = rotate_f64(DVec2::X * body.radius, angle);
+    Some(center + body.fixed_to_inertial(fixed, universe.stamp()))
 }
 
 pub fn nearest_relevant_body(
@@ -371,3 +752,453 @@ pub fn nearest_relevant_body(
         .min_by(|(d1, _), (d2, _)| d1.total_cmp(d2))
         .map(|(_, id)| *id)
 }
+
+/// Adds a vehicle to the primary body and pins it at rest at `pos`,
+/// for tests that only care about where vehicles are.
+#[cfg(test)]
+pub(crate) fn place_vehicle(universe: &mut Universe, vehicle: Vehicle, pos: DVec2) -> EntityId {
+    let planet = universe.planets.id;
+    let id = universe
+        .add_surface_vehicle(planet, vehicle, 0.0, 1000.0)
+        .unwrap();
+    universe.surface_vehicles.get_mut(&id).unwrap().body.pv = PV::from_f64(pos, DVec2::ZERO);
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_burner(universe: &mut Universe, angle: f64) -> EntityId {
+        // gentle enough that physics warp takes more than a tick at a time
+        let thruster = ThrusterModel::main_thruster(4_000.0, 3000.0);
+        let vehicle = Vehicle::from_parts(
+            "burner".into(),
+            "burner".into(),
+            vec![(
+                IVec2::ZERO,
+                Rotation::East,
+                PartPrototype::Thruster(thruster),
+            )],
+            Default::default(),
+        );
+
+        let id = universe
+            .add_surface_vehicle(EntityId(0), vehicle, 0.0, 1000.0)
+            .unwrap();
+
+        let r = Body::LUNA.radius * 2.0;
+        let v = (Body::LUNA.mu / r).sqrt();
+        let sv = universe.surface_vehicles.get_mut(&id).unwrap();
+        sv.body = RigidBody {
+            pv: PV::from_f64(
+                rotate_f64(DVec2::X * r, angle),
+                rotate_f64(DVec2::Y * v, angle),
+            ),
+            angle: angle + PI_64 / 2.0,
+            angular_velocity: 0.0,
+        };
+        sv.controller.set_policy(VehicleControlPolicy::BurnPrograde);
+        id
+    }
+
+    fn burning_universe(warp: bool) -> (Universe, EntityId) {
+        let mut universe = Universe::new(PlanetarySystem::new(EntityId(0), "test", Body::LUNA));
+        universe.physics.warp = warp;
+        let id = add_burner(&mut universe, 0.0);
+        (universe, id)
+    }
+
+    #[test]
+    fn parallel_stepping_matches_serial() {
+        let new = || {
+            let mut universe = Universe::new(PlanetarySystem::new(EntityId(0), "test", Body::LUNA));
+            let ids: Vec<_> = (0..40)
+                .map(|i| add_burner(&mut universe, i as f64 * 0.1))
+                .collect();
+            (universe, ids)
+        };
+
+        let (mut parallel, ids) = new();
+        for _ in 0..200 {
+            parallel.on_sim_tick(&ControlSignals::new());
+        }
+
+        let (mut serial, _) = new();
+        for _ in 0..200 {
+            serial.stamp += PHYSICS_CONSTANT_DELTA_TIME;
+            for id in &ids {
+                let sv = serial.surface_vehicles.get_mut(id).unwrap();
+                sv.step(
+                    &serial.planets,
+                    serial.stamp,
+                    VehicleControl::NULLOPT,
+                    serial.physics,
+                );
+            }
+        }
+
+        for id in &ids {
+            let a = parallel.surface_vehicles[id].body;
+            let b = serial.surface_vehicles[id].body;
+            assert_eq!(a.pv, b.pv);
+            assert_eq!(a.angle, b.angle);
+        }
+    }
+
+    #[test]
+    fn physics_warp_matches_stepping() {
+        let ticks = 60 * PHYSICS_CONSTANT_UPDATE_RATE;
+        let max_dur = Duration::from_secs(60);
+
+        let (mut stepped, id) = burning_universe(false);
+        let (n, _, mode) = stepped.on_sim_ticks(ticks, &ControlSignals::new(), max_dur);
+        assert_eq!(n, ticks);
+        assert_eq!(mode, SimMode::Stepped);
+
+        let (mut warped, _) = burning_universe(true);
+        let (n, _, mode) = warped.on_sim_ticks(ticks, &ControlSignals::new(), max_dur);
+        assert_eq!(n, ticks);
+        assert_eq!(mode, SimMode::PhysicsWarp);
+        assert_eq!(warped.stamp(), stepped.stamp());
+
+        let initial = burning_universe(false).0.surface_vehicles[&id].pv();
+        let a = stepped.surface_vehicles[&id].pv();
+        let b = warped.surface_vehicles[&id].pv();
+
+        // the vehicle actually burned, and both ended up in the same place
+        assert!(a.vel.length() > initial.vel.length() + 10.0, "{a:?}");
+        assert!(a.pos.distance(b.pos) < 1.0, "{a:?} {b:?}");
+        assert!(a.vel.distance(b.vel) < 0.01, "{a:?} {b:?}");
+    }
+
+    #[test]
+    fn warp_steps_hold_burns_short() {
+        let (universe, id) = burning_universe(true);
+        assert!(!PhysicsSettings::default().warp);
+
+        let sv = &universe.surface_vehicles[&id];
+        let ticks = sv.max_warp_ticks(&universe.planets, universe.stamp(), 10_000);
+        let accel = sv.vehicle.max_forward_thrust() / sv.vehicle.total_mass().to_kg_f64();
+        let dv = accel * (PHYSICS_CONSTANT_DELTA_TIME * ticks).to_secs_f64();
+        assert!(ticks > 1 && dv <= 1.0, "{ticks} {dv}");
+    }
+
+    #[test]
+    fn vehicles_without_a_parent_stay_put() {
+        let (mut universe, id) = burning_universe(false);
+        let sv = universe.surface_vehicles.get_mut(&id).unwrap();
+        sv.planet_id = EntityId(99);
+        let pv = sv.pv();
+        let stamp = Nanotime::secs(10);
+        sv.step_on_rails(stamp, stamp, &universe.planets);
+        sv.step(
+            &universe.planets,
+            stamp,
+            VehicleControl::NULLOPT,
+            universe.physics,
+        );
+        assert_eq!(sv.pv(), pv);
+    }
+
+    #[test]
+    fn landed_vehicles_turn_with_the_surface() {
+        let body = Body::LUNA.with_rotation_rate(1E-4);
+        let mut universe = Universe::new(PlanetarySystem::new(EntityId(0), "test", body));
+        let thruster = ThrusterModel::main_thruster(40_000.0, 3000.0);
+        let vehicle = Vehicle::from_parts(
+            "lander".into(),
+            "lander".into(),
+            vec![(
+                IVec2::ZERO,
+                Rotation::East,
+                PartPrototype::Thruster(thruster),
+            )],
+            Default::default(),
+        );
+        let id = universe
+            .add_surface_vehicle(EntityId(0), vehicle, 1.0, 0.0)
+            .unwrap();
+        universe
+            .surface_vehicles
+            .get_mut(&id)
+            .unwrap()
+            .controller
+            .set_policy(VehicleControlPolicy::Idle);
+
+        // vehicles spawn with a small random velocity, so let it settle
+        for _ in 0..20 * PHYSICS_CONSTANT_UPDATE_RATE {
+            universe.on_sim_tick(&ControlSignals::new());
+        }
+
+        let fixed_pos = |universe: &Universe| {
+            let pv = universe.surface_vehicles[&id].pv();
+            body.inertial_to_fixed(pv.pos, universe.stamp())
+        };
+        let landed = fixed_pos(&universe);
+        let drift = (landed.to_angle() - 1.0).abs();
+        assert!(drift < 1E-4, "{landed:?}");
+        // the site stays at exactly 1 radian, so it's off from the vehicle
+        // by however far the vehicle drifted while settling
+        let site_offset = drift * body.radius + 1.0;
+
+        let check = |universe: &Universe| {
+            let pv = universe.surface_vehicles[&id].pv();
+            let fixed = fixed_pos(universe);
+            assert!(fixed.distance(landed) < 1E-3, "{fixed:?} {landed:?}");
+            assert!((pv.pos.length() - body.radius).abs() < 1E-3, "{pv:?}");
+            // the ground's velocity is what a launch would start out with
+            let ground = body.surface_velocity(pv.pos);
+            assert!(pv.vel.distance(ground) < 1E-6, "{pv:?} {ground:?}");
+            let site = landing_site_position(universe, EntityId(0), 1.0).unwrap();
+            assert!(site.distance(pv.pos) < site_offset, "{site:?} {pv:?}");
+        };
+
+        for _ in 0..200 {
+            universe.on_sim_tick(&ControlSignals::new());
+        }
+        check(&universe);
+
+        // and the same on rails
+        universe.run_batch_ticks(60 * PHYSICS_CONSTANT_UPDATE_RATE);
+        check(&universe);
+        assert!(body.rotation_angle(universe.stamp()) > 0.006);
+    }
+
+    #[test]
+    fn fast_touchdowns_are_crashes() {
+        let falling = || {
+            let mut universe = Universe::new(PlanetarySystem::new(EntityId(0), "test", Body::LUNA));
+            let thruster = ThrusterModel::main_thruster(40_000.0, 3000.0);
+            let vehicle = Vehicle::from_parts(
+                "lander".into(),
+                "lander".into(),
+                vec![(
+                    IVec2::ZERO,
+                    Rotation::East,
+                    PartPrototype::Thruster(thruster),
+                )],
+                Default::default(),
+            );
+            let id = universe
+                .add_surface_vehicle(EntityId(0), vehicle, 0.0, 20_000.0)
+                .unwrap();
+            let sv = universe.surface_vehicles.get_mut(&id).unwrap();
+            sv.controller.set_policy(VehicleControlPolicy::Idle);
+            sv.body.pv.vel = DVec2::new(-300.0, 200.0);
+            universe.on_sim_tick(&ControlSignals::new());
+            (universe, id)
+        };
+
+        let (stepped, _) = falling();
+        let (batched, id) = falling();
+        let predicted = stepped.predict_impact(id).unwrap();
+        assert!(!predicted.is_safe());
+
+        for (mut universe, batch) in [(stepped, false), (batched, true)] {
+            if batch {
+                universe.run_batch_ticks(120 * PHYSICS_CONSTANT_UPDATE_RATE);
+            } else {
+                for _ in 0..120 * PHYSICS_CONSTANT_UPDATE_RATE {
+                    universe.on_sim_tick(&ControlSignals::new());
+                }
+            }
+            universe.on_sim_ticks(1, &ControlSignals::new(), Duration::from_secs(1));
+
+            let sv = &universe.surface_vehicles[&id];
+            assert!(sv.is_landed());
+            assert!((sv.pv().pos.length() - Body::LUNA.radius).abs() < 1E-3);
+            assert!(universe.predict_impact(id).is_none());
+
+            let crashes = universe.take_crashes();
+            assert_eq!(crashes.len(), 1, "{batch} {crashes:?}");
+            let (crashed, impact) = crashes[0];
+            assert_eq!(crashed, id);
+            assert!(
+                (impact.speed() - predicted.speed()).abs() < 1.0,
+                "{impact:?}"
+            );
+            assert!((impact.angle - predicted.angle).abs() < 1E-4, "{impact:?}");
+            assert!(universe.take_crashes().is_empty());
+        }
+    }
+
+    /// Runs the universe at high warp until the vehicle's mission finishes,
+    /// or gives up after the given time.
+    fn run_mission(universe: &mut Universe, id: EntityId, limit: Nanotime) {
+        universe.physics.warp = true;
+        let end = universe.stamp() + limit;
+        while universe.stamp() < end {
+            universe.on_sim_ticks(10_000, &ControlSignals::new(), Duration::from_secs(10));
+            if !universe.surface_vehicles[&id]
+                .mission()
+                .unwrap()
+                .is_running()
+            {
+                return;
+            }
+        }
+    }
+
+    /// A vehicle with a main engine and RCS thrusters to turn it, in a
+    /// circular orbit.
+    fn add_mission_vehicle(universe: &mut Universe, r: f64, angle: f64) -> EntityId {
+        let main = ThrusterModel::main_thruster(40_000.0, 3000.0);
+        let mut rcs = ThrusterModel::main_thruster(2_000.0, 700.0);
+        rcs.is_rcs = true;
+        rcs.minimum_throttle = 0.0;
+        let mut parts = vec![(IVec2::ZERO, Rotation::East, PartPrototype::Thruster(main))];
+        for (x, y, rot) in [
+            (40, 15, Rotation::North),
+            (40, -15, Rotation::South),
+            (-40, 15, Rotation::North),
+            (-40, -15, Rotation::South),
+        ] {
+            parts.push((IVec2::new(x, y), rot, PartPrototype::Thruster(rcs.clone())));
+        }
+        let vehicle = Vehicle::from_parts("auto".into(), "auto".into(), parts, Default::default());
+        let orbit = SparseOrbit::circular(r, Body::LUNA, universe.stamp(), false);
+        let id = universe
+            .add_surface_vehicle(EntityId(0), vehicle, 0.0, 1000.0)
+            .unwrap();
+        let pv = orbit.pv(universe.stamp()).unwrap();
+        let sv = universe.surface_vehicles.get_mut(&id).unwrap();
+        sv.body = RigidBody {
+            pv: PV::from_f64(rotate_f64(pv.pos, angle), rotate_f64(pv.vel, angle)),
+            angle: angle + PI_64 / 2.0,
+            angular_velocity: 0.0,
+        };
+        sv.controller.set_idle();
+        id
+    }
+
+    fn orbiting_universe(script: &str) -> (Universe, EntityId) {
+        let mut universe = Universe::new(PlanetarySystem::new(EntityId(0), "test", Body::LUNA));
+        let id = add_mission_vehicle(&mut universe, Body::LUNA.radius * 2.0, 0.0);
+        let sv = universe.surface_vehicles.get_mut(&id).unwrap();
+        sv.set_mission(script.parse::<MissionScript>().unwrap());
+        (universe, id)
+    }
+
+    #[test]
+    fn missions_burn_and_wait_for_apsides() {
+        let (mut universe, id) = orbiting_universe(
+            "burn prograde until apoapsis > 5000km
+             wait until apoapsis
+             burn prograde until periapsis > 4900km",
+        );
+        run_mission(&mut universe, id, Nanotime::hours(24));
+
+        let sv = &universe.surface_vehicles[&id];
+        let mission = sv.mission().unwrap();
+        assert_eq!(*mission.status(), MissionStatus::Complete, "{mission}");
+
+        // raising the periapsis from the apoapsis leaves a near circular orbit
+        let orbit = sv.current_orbit().unwrap().1;
+        let radius = Body::LUNA.radius;
+        assert!(orbit.periapsis_r() - radius > 4_900_000.0, "{orbit}");
+        assert!(orbit.apoapsis_r() - radius < 5_150_000.0, "{orbit}");
+    }
+
+    #[test]
+    fn missions_land_near_the_site() {
+        let site = 2.0;
+        let (mut universe, id) = orbiting_universe("land at 2rad");
+        run_mission(&mut universe, id, Nanotime::hours(12));
+
+        let sv = &universe.surface_vehicles[&id];
+        let mission = sv.mission().unwrap();
+        assert_eq!(*mission.status(), MissionStatus::Complete, "{mission}");
+        assert!(sv.is_landed());
+
+        let landed = Body::LUNA.inertial_to_fixed(sv.pv().pos, universe.stamp());
+        let miss = wrap_pi_npi_f64(landed.to_angle() - site).abs() * Body::LUNA.radius;
+        assert!(miss < 50_000.0, "{miss}");
+        assert!(universe.take_crashes().is_empty());
+    }
+
+    #[test]
+    fn missions_rendezvous_with_their_target() {
+        let (mut universe, id) = orbiting_universe("");
+        let target = add_mission_vehicle(&mut universe, Body::LUNA.radius * 2.5, 2.5);
+
+        let script = format!("target {target}\nrendezvous");
+        let sv = universe.surface_vehicles.get_mut(&id).unwrap();
+        sv.set_mission(script.parse::<MissionScript>().unwrap());
+        run_mission(&mut universe, id, Nanotime::days(2));
+
+        let mission = universe.surface_vehicles[&id].mission().unwrap();
+        assert_eq!(*mission.status(), MissionStatus::Complete, "{mission}");
+        let rel = universe.pv(id).unwrap() - universe.pv(target).unwrap();
+        assert!(rel.pos.length() < 100.0, "{rel:?}");
+        assert!(rel.vel.length() < 1.0, "{rel:?}");
+    }
+
+    #[test]
+    fn missions_catch_thresholds_at_high_warp() {
+        let threshold = Body::LUNA.radius / 2.0;
+        let script = format!("wait until altitude < {threshold}m");
+        let (mut universe, id) = orbiting_universe(&script);
+
+        // from apoapsis, the orbit only dips below the threshold briefly
+        // around periapsis
+        let mut pv = universe.pv(id).unwrap();
+        pv.vel *= 0.9;
+        universe.teleport(id, EntityId(0), pv).unwrap();
+        let orbit = universe.surface_vehicles[&id].current_orbit().unwrap().1;
+        assert!(
+            orbit.periapsis_r() - Body::LUNA.radius < threshold,
+            "{orbit}"
+        );
+
+        // four whole orbits in a single call, ending back up at apoapsis
+        let period = orbit.period().unwrap().inner();
+        let ticks = (4 * period / PHYSICS_CONSTANT_DELTA_TIME.inner()) as u32;
+        let (actual, _, mode) =
+            universe.on_sim_ticks(ticks, &ControlSignals::new(), Duration::from_secs(60));
+        assert_eq!(actual, ticks);
+        assert_eq!(mode, SimMode::Batch);
+
+        let sv = &universe.surface_vehicles[&id];
+        assert!(sv.pv().pos.length() - Body::LUNA.radius > threshold);
+        let mission = sv.mission().unwrap();
+        assert_eq!(*mission.status(), MissionStatus::Complete, "{mission}");
+    }
+
+    #[test]
+    fn missions_fail_without_a_target() {
+        let (mut universe, id) = orbiting_universe("wait 10s\nrendezvous\nwait 10s");
+        run_mission(&mut universe, id, Nanotime::mins(1));
+        let mission = universe.surface_vehicles[&id].mission().unwrap();
+        assert_eq!(*mission.status(), MissionStatus::Failed("No target"));
+        assert_eq!(mission.step_index(), 1);
+        assert!(universe.surface_vehicles[&id].controller.is_idle());
+    }
+
+    #[test]
+    fn teleported_vehicles_leave_the_ground() {
+        let mut universe = Universe::new(PlanetarySystem::new(EntityId(0), "test", Body::LUNA));
+        let id = add_burner(&mut universe, 0.0);
+        let sv = universe.surface_vehicles.get_mut(&id).unwrap();
+        sv.body.pv = PV::pos(DVec2::X * Body::LUNA.radius);
+        sv.controller.set_idle();
+        for _ in 0..40 {
+            universe.on_sim_tick(&ControlSignals::new());
+        }
+        assert!(universe.surface_vehicles[&id].is_landed());
+
+        let r = Body::LUNA.radius * 3.0;
+        let orbit = SparseOrbit::circular(r, Body::LUNA, universe.stamp(), false);
+        let pv = orbit.pv(universe.stamp()).unwrap();
+        universe.teleport(id, EntityId(0), pv).unwrap();
+        for _ in 0..40 {
+            universe.on_sim_tick(&ControlSignals::new());
+        }
+
+        let sv = &universe.surface_vehicles[&id];
+        assert!(!sv.is_landed());
+        let orbit = sv.current_orbit().unwrap().1;
+        assert!((orbit.periapsis_r() - r).abs() < 100.0, "{orbit}");
+        assert!(universe.teleport(id, EntityId(7), pv).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use crate::factory::Mass;
+use crate::parts::*;
+use crate::scenario::PlanetarySystem;
+use crate::vehicle::*;
+use std::collections::HashSet;
+
+/// Performance of one stage of a vehicle, found by [`Vehicle::analyze`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageAnalysis {
+    /// Mass of the stage with all of its propellant
+    pub wet_mass: Mass,
+    /// Mass left once the main engines have burned all they can reach
+    pub dry_mass: Mass,
+    /// Propellant in tanks plumbed to the main engines
+    pub propellant_mass: Mass,
+    /// Combined thrust of the main engines which have propellant, in N
+    pub thrust: f64,
+    /// Effective exhaust velocity of those engines together, in m/s
+    pub exhaust_velocity: f64,
+    /// In m/s
+    pub delta_v: f64,
+    /// Seconds taken to burn all of the propellant at full throttle
+    pub burn_time: f64,
+    /// Thrust to weight ratio at the surface of each body, by name
+    pub twr: Vec<(String, f64)>,
+}
+
+impl std::fmt::Display for StageAnalysis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Wet mass: {}", self.wet_mass)?;
+        writeln!(f, "Dry mass: {}", self.dry_mass)?;
+        writeln!(f, "Propellant: {}", self.propellant_mass)?;
+        writeln!(f, "Thrust: {:0.2} kN", self.thrust / 1000.0)?;
+        writeln!(f, "DV: {:0.1} m/s", self.delta_v)?;
+        writeln!(f, "Burn time: {:0.1} s", self.burn_time)?;
+        for (name, twr) in &self.twr {
+            writeln!(f, "TWR ({}): {:0.2}", name, twr)?;
+        }
+        Ok(())
+    }
+}
+
+impl Vehicle {
+    /// Delta-v, thrust to weight and so on for each stage of the vehicle,
+    /// using whatever is in its tanks right now. Vehicles can't be staged
+    /// yet, so there's always exactly one stage covering the whole craft.
+    pub fn analyze(&self, system: &PlanetarySystem) -> Vec<StageAnalysis> {
+        vec![self.analyze_stage(system)]
+    }
+
+    fn analyze_stage(&self, system: &PlanetarySystem) -> StageAnalysis {
+        let is_propellant_tank = |p: &InstantiatedPart| {
+            p.as_tank()
+                .and_then(|(_, d)| d.item())
+                .is_some_and(|item| item.is_propellant())
+        };
+
+        let mut tanks = HashSet::new();
+        let mut thrust = 0.0;
+        let mut mass_flow = 0.0;
+
+        // only main engines pushing the craft forwards count
+        for (id, part) in self.parts() {
+            let Some((t, d)) = part.as_thruster() else {
+                continue;
+            };
+            if t.is_rcs() || !d.is_enabled() || part.rotation() != Rotation::East {
+                continue;
+            }
+            let reachable: Vec<_> = self
+                .plumbed_to(*id)
+                .filter(|(_, p)| is_propellant_tank(p))
+                .map(|(id, _)| id)
+                .collect();
+            if reachable.is_empty() {
+                continue;
+            }
+            tanks.extend(reachable);
+            thrust += t.max_thrust();
+            mass_flow += t.max_thrust() / t.exhaust_velocity as f64;
+        }
+
+        let propellant_mass: Mass = tanks
+            .iter()
+            .filter_map(|id| self.get_part(*id)?.as_tank())
+            .map(|(_, d)| d.contents_mass())
+            .sum();
+
+        let wet_mass = self.total_mass();
+        let dry_mass = wet_mass - propellant_mass;
+
+        let exhaust_velocity = if mass_flow > 0.0 {
+            thrust / mass_flow
+        } else {
+            0.0
+        };
+
+        let delta_v = if dry_mass > Mass::ZERO && exhaust_velocity > 0.0 {
+            rocket_equation(exhaust_velocity, wet_mass, dry_mass)
+        } else {
+            0.0
+        };
+
+        let burn_time = if mass_flow > 0.0 {
+            propellant_mass.to_kg_f64() / mass_flow
+        } else {
+            0.0
+        };
+
+        let twr = system
+            .named_bodies()
+            .into_iter()
+            .map(|(name, body)| {
+                let weight = wet_mass.to_kg_f64() * body.surface_gravity();
+                let twr = if weight > 0.0 { thrust / weight } else { 0.0 };
+                (name.to_string(), twr)
+            })
+            .collect();
+
+        StageAnalysis {
+            wet_mass,
+            dry_mass,
+            propellant_mass,
+            thrust,
+            exhaust_velocity,
+            delta_v,
+            burn_time,
+            twr,
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::math::*;
+use crate::parts::*;
+use crate::vehicle::*;
+use std::collections::HashSet;
+
+/// Group of parts, and the pipes running under them, which can be placed
+/// into a craft all at once. Positions are in pixels relative to the lower
+/// left corner of the group.
+#[derive(Debug, Clone, Default)]
+pub struct Assembly {
+    name: String,
+    parts: Vec<(IVec2, Rotation, PartPrototype)>,
+    pipes: Vec<IVec2>,
+}
+
+/// Lower left corner and size in pixels of the box around a group of parts.
+fn bounds(parts: &[(IVec2, Rotation, PartPrototype)]) -> (IVec2, IVec2) {
+    let lower = parts.iter().map(|(p, _, _)| *p).reduce(IVec2::min);
+    let upper = parts
+        .iter()
+        .map(|(p, rot, proto)| *p + pixel_dims_with_rotation(*rot, proto).as_ivec2())
+        .reduce(IVec2::max);
+    match (lower, upper) {
+        (Some(lower), Some(upper)) => (lower, upper - lower),
+        _ => (IVec2::ZERO, IVec2::ZERO),
+    }
+}
+
+impl Assembly {
+    /// Moves the parts and pipes together so the lower left corner of the
+    /// parts ends up at the origin.
+    pub fn new(
+        name: String,
+        parts: Vec<(IVec2, Rotation, PartPrototype)>,
+        pipes: impl IntoIterator<Item = IVec2>,
+    ) -> Self {
+        let (lower, _) = bounds(&parts);
+        let parts = parts
+            .into_iter()
+            .map(|(p, rot, proto)| (p - lower, rot, proto))
+            .collect();
+        let mut pipes: Vec<_> = pipes.into_iter().map(|p| p - lower).collect();
+        pipes.sort_by_key(|p| (p.x, p.y));
+        Self { name, parts, pipes }
+    }
+
+    /// The given parts of a vehicle, along with any pipes underneath them.
+    pub fn from_vehicle(
+        name: String,
+        vehicle: &Vehicle,
+        ids: impl IntoIterator<Item = PartId>,
+    ) -> Self {
+        let mut ids: Vec<_> = ids.into_iter().collect();
+        ids.sort();
+        let parts: Vec<_> = ids
+            .into_iter()
+            .filter_map(|id| vehicle.get_part(id))
+            .map(|p| (p.origin(), p.rotation(), p.prototype()))
+            .collect();
+        let covered: HashSet<IVec2> = parts
+            .iter()
+            .flat_map(|(p, rot, proto)| occupied_pixels(*p, *rot, proto))
+            .collect();
+        let pipes: Vec<_> = vehicle.pipes().filter(|p| covered.contains(p)).collect();
+        Self::new(name, parts, pipes)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    pub fn parts(&self) -> &[(IVec2, Rotation, PartPrototype)] {
+        &self.parts
+    }
+
+    pub fn pipes(&self) -> &[IVec2] {
+        &self.pipes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    /// Size of the box around the parts, in pixels.
+    pub fn dims(&self) -> IVec2 {
+        bounds(&self.parts).1
+    }
+
+    /// The whole group turned a quarter turn counterclockwise, the same
+    /// way as [`InstantiatedPart::rotated`].
+    pub fn rotated(&self) -> Self {
+        let parts = self
+            .parts
+            .iter()
+            .map(|(p, rot, proto)| {
+                let wh = pixel_dims_with_rotation(*rot, proto).as_ivec2();
+                let p = IVec2::new(-p.y - wh.y, p.x);
+                (p, enum_iterator::next_cycle(rot), proto.clone())
+            })
+            .collect();
+        let pipes = self.pipes.iter().map(|p| IVec2::new(-p.y - 1, p.x));
+        Self::new(self.name.clone(), parts, pipes)
+    }
+
+    /// Parts and pipes with the lower left corner of the group moved to
+    /// the given pixel.
+    pub fn placed_at(&self, offset: IVec2) -> (Vec<(IVec2, Rotation, PartPrototype)>, Vec<IVec2>) {
+        let parts = self
+            .parts
+            .iter()
+            .map(|(p, rot, proto)| (*p + offset, *rot, proto.clone()))
+            .collect();
+        let pipes = self.pipes.iter().map(|p| *p + offset).collect();
+        (parts, pipes)
+    }
+}
@@ -0,0 +1,302 @@
+use crate::factory::Mass;
+use crate::math::*;
+use crate::parts::*;
+use crate::vehicle::*;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Fastest exhaust velocity a thruster may have before it's considered a
+/// typo, in m/s. Well above anything chemical or electric.
+const MAX_EXHAUST_VELOCITY: f32 = 100_000.0;
+
+/// Most a thruster may push compared to its own weight at one gee.
+const MAX_THRUSTER_TWR: f64 = 1000.0;
+
+/// Problem with something in the parts or vehicles directory, found by
+/// [`lint_parts_dir`] or [`lint_vehicles_dir`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssetIssueKind {
+    BadDirectory(String),
+    MissingMetadata,
+    BadMetadata(String),
+    MissingSprite,
+    BadSprite(String),
+    /// Image in a part directory other than the skin
+    ExtraSprite(String),
+    /// Size of the skin in pixels, and the dims in the metadata
+    DimensionMismatch(UVec2, UVec2),
+    /// Part name which doesn't match its directory, so neither vehicles
+    /// nor sprites can find it
+    NameMismatch(String),
+    /// Name of the part, and the directory which had it first
+    DuplicateName(String, PathBuf),
+    /// Stats which can't be right, and what's wrong with them
+    Unphysical(String),
+    BadVehicle(String),
+    /// Part name used by a vehicle, and how many times it's used
+    MissingPart(String, usize),
+    /// Indices into the vehicle's list of parts, and where they first overlap
+    OverlappingParts(usize, usize, IVec2),
+}
+
+impl std::fmt::Display for AssetIssueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadDirectory(e) => write!(f, "failed to read directory: {}", e),
+            Self::MissingMetadata => write!(f, "no metadata.yaml"),
+            Self::BadMetadata(e) => write!(f, "failed to parse metadata: {}", e),
+            Self::MissingSprite => write!(f, "no skin.png"),
+            Self::BadSprite(e) => write!(f, "failed to read skin.png: {}", e),
+            Self::ExtraSprite(name) => write!(f, "unused image {}", name),
+            Self::DimensionMismatch(sprite, dims) => write!(
+                f,
+                "skin.png is {}x{} but dims are {}x{}",
+                sprite.x, sprite.y, dims.x, dims.y
+            ),
+            Self::NameMismatch(name) => {
+                write!(f, "part is named \"{}\", unlike its directory", name)
+            }
+            Self::DuplicateName(name, other) => write!(
+                f,
+                "part name \"{}\" is already used by {}",
+                name,
+                other.display()
+            ),
+            Self::Unphysical(e) => write!(f, "{}", e),
+            Self::BadVehicle(e) => write!(f, "failed to parse vehicle: {}", e),
+            Self::MissingPart(name, count) => {
+                write!(f, "uses missing part \"{}\" ({}x)", name, count)
+            }
+            Self::OverlappingParts(a, b, p) => {
+                write!(f, "parts {} and {} overlap at ({}, {})", a, b, p.x, p.y)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetIssue {
+    pub path: PathBuf,
+    pub kind: AssetIssueKind,
+}
+
+impl std::fmt::Display for AssetIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.kind)
+    }
+}
+
+fn sorted_entries(path: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = std::fs::read_dir(path).map_err(|e| e.to_string())?;
+    let mut ret: Vec<_> = entries.flatten().map(|e| e.path()).collect();
+    ret.sort();
+    Ok(ret)
+}
+
+/// What's wrong with a part's stats, if anything.
+pub fn unphysical_stats(proto: &PartPrototype) -> Option<String> {
+    let dims = proto.dims();
+    if dims.x == 0 || dims.y == 0 {
+        return Some(format!("dims are {}x{}", dims.x, dims.y));
+    }
+
+    match proto {
+        PartPrototype::Thruster(t) => {
+            let mass = t.mass().to_kg_f64();
+            if t.mass() == Mass::ZERO {
+                Some("thruster has no mass".into())
+            } else if t.max_thrust().is_nan() || t.max_thrust() <= 0.0 {
+                Some(format!("thrust of {} N", t.max_thrust()))
+            } else if !(t.exhaust_velocity > 0.0 && t.exhaust_velocity <= MAX_EXHAUST_VELOCITY) {
+                Some(format!("exhaust velocity of {} m/s", t.exhaust_velocity))
+            } else if t.max_thrust() / (mass * 9.81) > MAX_THRUSTER_TWR {
+                Some(format!(
+                    "{} N of thrust from {} kg of thruster",
+                    t.max_thrust(),
+                    mass
+                ))
+            } else if !(0.0..1.0).contains(&t.minimum_throttle) {
+                Some(format!("minimum throttle of {}", t.minimum_throttle))
+            } else if t.throttle_rate.is_nan() || t.throttle_rate <= 0.0 {
+                Some(format!("throttle rate of {}", t.throttle_rate))
+            } else {
+                None
+            }
+        }
+        PartPrototype::Tank(t) if t.capacity() == Mass::ZERO => {
+            Some("tank can't hold anything".into())
+        }
+        _ => None,
+    }
+}
+
+/// Checks every part directory, carrying on past any which are broken.
+/// Returns the parts which loaded, for checking vehicles against.
+pub fn lint_parts_dir(path: &Path) -> (HashMap<String, PartPrototype>, Vec<AssetIssue>) {
+    let mut parts = HashMap::new();
+    let mut first_seen: HashMap<String, PathBuf> = HashMap::new();
+    let mut issues = Vec::new();
+
+    let entries = match sorted_entries(path) {
+        Ok(e) => e,
+        Err(e) => {
+            let kind = AssetIssueKind::BadDirectory(e);
+            issues.push(AssetIssue {
+                path: path.to_path_buf(),
+                kind,
+            });
+            return (parts, issues);
+        }
+    };
+
+    for dir in entries.into_iter().filter(|p| p.is_dir()) {
+        let mut issue = |path: PathBuf, kind| issues.push(AssetIssue { path, kind });
+
+        let metadata = dir.join("metadata.yaml");
+        let skin = dir.join("skin.png");
+
+        for file in sorted_entries(&dir).unwrap_or_default() {
+            if file.extension().is_some_and(|e| e == "png") && file != skin {
+                let name = file.file_name().unwrap_or_default().to_string_lossy();
+                issue(dir.clone(), AssetIssueKind::ExtraSprite(name.to_string()));
+            }
+        }
+
+        let sprite_dims = if skin.exists() {
+            match image::image_dimensions(&skin) {
+                Ok((w, h)) => Some(UVec2::new(w, h)),
+                Err(e) => {
+                    issue(skin.clone(), AssetIssueKind::BadSprite(e.to_string()));
+                    None
+                }
+            }
+        } else {
+            issue(dir.clone(), AssetIssueKind::MissingSprite);
+            None
+        };
+
+        let Ok(s) = std::fs::read_to_string(&metadata) else {
+            issue(dir.clone(), AssetIssueKind::MissingMetadata);
+            continue;
+        };
+
+        let proto: PartPrototype = match serde_yaml::from_str(&s) {
+            Ok(p) => p,
+            Err(e) => {
+                issue(metadata, AssetIssueKind::BadMetadata(e.to_string()));
+                continue;
+            }
+        };
+
+        if let Some(sprite_dims) = sprite_dims {
+            if sprite_dims != proto.dims() {
+                let kind = AssetIssueKind::DimensionMismatch(sprite_dims, proto.dims());
+                issue(skin.clone(), kind);
+            }
+        }
+
+        let name = proto.part_name().to_string();
+        if dir.file_name().is_some_and(|d| d.to_string_lossy() != name) {
+            issue(metadata.clone(), AssetIssueKind::NameMismatch(name.clone()));
+        }
+
+        if let Some(e) = unphysical_stats(&proto) {
+            issue(metadata.clone(), AssetIssueKind::Unphysical(e));
+        }
+
+        if let Some(other) = first_seen.get(&name) {
+            let kind = AssetIssueKind::DuplicateName(name, other.clone());
+            issue(metadata, kind);
+            continue;
+        }
+
+        first_seen.insert(name.clone(), dir.clone());
+        parts.insert(name, proto);
+    }
+
+    (parts, issues)
+}
+
+/// Missing parts and overlapping parts in a vehicle file.
+pub fn lint_vehicle(
+    storage: &VehicleFileStorage,
+    parts: &HashMap<String, PartPrototype>,
+) -> Vec<AssetIssueKind> {
+    let mut issues = Vec::new();
+    let mut missing: HashMap<&str, usize> = HashMap::new();
+    // every part on each pixel, so three parts stacked on one spot
+    // report all three pairs rather than just the last two
+    let mut claimed: HashMap<(PartLayer, IVec2), Vec<usize>> = HashMap::new();
+    let mut overlaps: BTreeMap<(usize, usize), IVec2> = BTreeMap::new();
+
+    for (i, part) in storage.parts.iter().enumerate() {
+        let Some(proto) = parts.get(&part.partname) else {
+            *missing.entry(&part.partname).or_default() += 1;
+            continue;
+        };
+        for p in occupied_pixels(part.pos, part.rot, proto) {
+            let owners = claimed.entry((proto.layer(), p)).or_default();
+            for j in owners.iter() {
+                overlaps.entry((*j, i)).or_insert(p);
+            }
+            owners.push(i);
+        }
+    }
+
+    let mut missing: Vec<_> = missing.into_iter().collect();
+    missing.sort();
+    for (name, count) in missing {
+        issues.push(AssetIssueKind::MissingPart(name.to_string(), count));
+    }
+
+    for ((a, b), p) in overlaps {
+        issues.push(AssetIssueKind::OverlappingParts(a, b, p));
+    }
+
+    issues
+}
+
+/// Checks every .vehicle file in a directory against the given parts.
+pub fn lint_vehicles_dir(path: &Path, parts: &HashMap<String, PartPrototype>) -> Vec<AssetIssue> {
+    let mut issues = Vec::new();
+
+    let entries = match sorted_entries(path) {
+        Ok(e) => e,
+        Err(e) => {
+            let kind = AssetIssueKind::BadDirectory(e);
+            issues.push(AssetIssue {
+                path: path.to_path_buf(),
+                kind,
+            });
+            return issues;
+        }
+    };
+
+    let vehicles = entries
+        .into_iter()
+        .filter(|f| f.extension().is_some_and(|e| e == "vehicle"));
+
+    for file in vehicles {
+        let storage = std::fs::read_to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|s| {
+                serde_yaml::from_str::<VehicleFileStorage>(&s).map_err(|e| e.to_string())
+            });
+
+        let kinds = match storage {
+            Ok(storage) if storage.version > VEHICLE_FILE_VERSION => {
+                let e = format!("unsupported version {}", storage.version);
+                vec![AssetIssueKind::BadVehicle(e)]
+            }
+            Ok(storage) => lint_vehicle(&storage, parts),
+            Err(e) => vec![AssetIssueKind::BadVehicle(e)],
+        };
+
+        issues.extend(kinds.into_iter().map(|kind| AssetIssue {
+            path: file.clone(),
+            kind,
+        }));
+    }
+
+    issues
+}
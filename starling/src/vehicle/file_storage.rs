@@ -1,15 +1,39 @@
+use crate::factory::{Item, Mass, RecipeListing};
 use crate::math::*;
+use crate::mission::MissionScript;
 use crate::parts::*;
 use crate::vehicle::*;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
+/// Version of the .vehicle format written by this build. Files from before
+/// versioning have no version field, and are treated as version 1; those
+/// only describe the layout of the craft, so every part loads unbuilt and
+/// empty.
+pub const VEHICLE_FILE_VERSION: u32 = 2;
+
+fn first_vehicle_file_version() -> u32 {
+    1
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VehicleFileStorage {
+    #[serde(default = "first_vehicle_file_version")]
+    pub version: u32,
     pub name: String,
     pub parts: Vec<VehiclePartFileStorage>,
     pub lines: HashSet<IVec2>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mission: Option<MissionScript>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +41,120 @@ pub struct VehiclePartFileStorage {
     pub partname: String,
     pub pos: IVec2,
     pub rot: Rotation,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub builds_performed: u32,
+    /// What's in the part if it's a tank or cargo bay
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contents: Vec<(Item, Mass)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipe: Option<RecipeListing>,
+    /// Whether the part is a thruster which has been switched off
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub disabled: bool,
+}
+
+/// Group of parts saved from the editor to be placed again as a unit. See
+/// [`Assembly`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssemblyFileStorage {
+    #[serde(default = "first_vehicle_file_version")]
+    pub version: u32,
+    pub name: String,
+    pub parts: Vec<VehiclePartFileStorage>,
+    pub lines: HashSet<IVec2>,
+}
+
+impl VehiclePartFileStorage {
+    /// Just where the part goes, with none of its state.
+    pub fn layout(pos: IVec2, rot: Rotation, proto: &PartPrototype) -> Self {
+        VehiclePartFileStorage {
+            partname: proto.sprite_path().to_string(),
+            pos,
+            rot,
+            builds_performed: 0,
+            contents: Vec::new(),
+            recipe: None,
+            disabled: false,
+        }
+    }
+
+    pub fn from_part(part: &InstantiatedPart) -> Self {
+        let contents = if let Some((_, d)) = part.as_tank() {
+            d.contents().into_iter().collect()
+        } else if let Some((_, d)) = part.as_cargo() {
+            d.contents().collect()
+        } else {
+            Vec::new()
+        };
+
+        VehiclePartFileStorage {
+            partname: part.prototype().sprite_path().to_string(),
+            pos: part.origin(),
+            rot: part.rotation(),
+            builds_performed: part.builds_performed(),
+            contents,
+            recipe: part.as_machine().map(|(_, d)| d.recipe),
+            disabled: part.as_thruster().is_some_and(|(_, d)| !d.is_enabled()),
+        }
+    }
+
+    fn instantiate(&self, proto: PartPrototype) -> InstantiatedPart {
+        let mut part = InstantiatedPart::from_prototype(proto, self.pos, self.rot);
+        part.set_builds_performed(self.builds_performed);
+        for (item, mass) in &self.contents {
+            if let Some((t, d)) = part.as_tank_mut() {
+                t.put(*item, *mass, d);
+            } else if let Some((_, d)) = part.as_cargo_mut() {
+                d.put(*item, *mass);
+            }
+        }
+        if let (Some(recipe), Some((_, d))) = (self.recipe, part.as_machine_mut()) {
+            d.recipe = recipe;
+        }
+        if let Some((_, d)) = part.as_thruster_mut() {
+            d.set_enabled(!self.disabled);
+        }
+        part
+    }
+}
+
+impl VehicleFileStorage {
+    pub fn from_vehicle(vehicle: &Vehicle) -> Self {
+        let mut parts: Vec<_> = vehicle.parts().collect();
+        parts.sort_by_key(|(id, _)| **id);
+
+        VehicleFileStorage {
+            version: VEHICLE_FILE_VERSION,
+            name: vehicle.model().to_string(),
+            parts: parts
+                .into_iter()
+                .map(|(_, p)| VehiclePartFileStorage::from_part(p))
+                .collect(),
+            lines: vehicle.pipes().collect(),
+            mission: vehicle.mission().cloned(),
+        }
+    }
+
+    pub fn to_vehicle(
+        &self,
+        name: String,
+        parts: &HashMap<String, PartPrototype>,
+    ) -> Result<Vehicle, Box<dyn std::error::Error>> {
+        if self.version > VEHICLE_FILE_VERSION {
+            return Err(Box::new(UnsupportedVersionError(self.version)));
+        }
+        let mut instances = Vec::new();
+        for part in &self.parts {
+            let proto = parts
+                .get(&part.partname)
+                .ok_or(Box::new(NoPartError(part.partname.clone())))?;
+            instances.push(part.instantiate(proto.clone()));
+        }
+        let mut vehicle =
+            Vehicle::from_instances(name, self.name.clone(), instances, self.lines.clone());
+        vehicle.set_mission(self.mission.clone());
+        Ok(vehicle)
+    }
 }
 
 #[derive(Debug)]
@@ -30,6 +168,21 @@ impl std::fmt::Display for NoPartError {
 
 impl std::error::Error for NoPartError {}
 
+#[derive(Debug)]
+pub struct UnsupportedVersionError(u32);
+
+impl std::fmt::Display for UnsupportedVersionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "vehicle file is version {}, but only up to version {} is supported",
+            self.0, VEHICLE_FILE_VERSION
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedVersionError {}
+
 pub fn load_vehicle(
     path: &Path,
     name: String,
@@ -37,19 +190,64 @@ pub fn load_vehicle(
 ) -> Result<Vehicle, Box<dyn std::error::Error>> {
     let s = std::fs::read_to_string(path)?;
     let storage: VehicleFileStorage = serde_yaml::from_str(&s)?;
-    let mut prototypes = Vec::new();
-    for part in &storage.parts {
-        let proto = parts
-            .get(&part.partname)
-            .ok_or(Box::new(NoPartError(part.partname.clone())))?;
-        prototypes.push((part.pos, part.rot, proto.clone()));
+    storage.to_vehicle(name, parts)
+}
+
+pub fn save_vehicle(path: &Path, vehicle: &Vehicle) -> Result<(), Box<dyn std::error::Error>> {
+    let storage = VehicleFileStorage::from_vehicle(vehicle);
+    let s = serde_yaml::to_string(&storage)?;
+    Ok(std::fs::write(path, s)?)
+}
+
+impl AssemblyFileStorage {
+    pub fn from_assembly(assembly: &Assembly) -> Self {
+        AssemblyFileStorage {
+            version: VEHICLE_FILE_VERSION,
+            name: assembly.name().to_string(),
+            parts: assembly
+                .parts()
+                .iter()
+                .map(|(pos, rot, proto)| VehiclePartFileStorage::layout(*pos, *rot, proto))
+                .collect(),
+            lines: assembly.pipes().iter().cloned().collect(),
+        }
+    }
+
+    pub fn to_assembly(
+        &self,
+        parts: &HashMap<String, PartPrototype>,
+    ) -> Result<Assembly, Box<dyn std::error::Error>> {
+        if self.version > VEHICLE_FILE_VERSION {
+            return Err(Box::new(UnsupportedVersionError(self.version)));
+        }
+        let mut prototypes = Vec::new();
+        for part in &self.parts {
+            let proto = parts
+                .get(&part.partname)
+                .ok_or(Box::new(NoPartError(part.partname.clone())))?;
+            prototypes.push((part.pos, part.rot, proto.clone()));
+        }
+        Ok(Assembly::new(
+            self.name.clone(),
+            prototypes,
+            self.lines.iter().cloned(),
+        ))
     }
-    Ok(Vehicle::from_parts(
-        name,
-        storage.name,
-        prototypes,
-        storage.lines,
-    ))
+}
+
+pub fn load_assembly(
+    path: &Path,
+    parts: &HashMap<String, PartPrototype>,
+) -> Result<Assembly, Box<dyn std::error::Error>> {
+    let s = std::fs::read_to_string(path)?;
+    let storage: AssemblyFileStorage = serde_yaml::from_str(&s)?;
+    storage.to_assembly(parts)
+}
+
+pub fn save_assembly(path: &Path, assembly: &Assembly) -> Result<(), Box<dyn std::error::Error>> {
+    let storage = AssemblyFileStorage::from_assembly(assembly);
+    let s = serde_yaml::to_string(&storage)?;
+    Ok(std::fs::write(path, s)?)
 }
 
 fn part_from_path(path: &Path) -> Result<PartPrototype, String> {
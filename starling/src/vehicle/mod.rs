@@ -1,14 +1,22 @@
+mod analysis;
+mod assembly;
+mod asset_lint;
 mod connectivity_group;
 mod file_storage;
 mod rigid_body;
 mod sprite_generation;
+mod validation;
 mod vehicle;
 mod vehicle_control;
 mod vehicle_tests;
 
+pub use analysis::*;
+pub use assembly::*;
+pub use asset_lint::*;
 pub use connectivity_group::*;
 pub use file_storage::*;
 pub use rigid_body::*;
 pub use sprite_generation::*;
+pub use validation::*;
 pub use vehicle::*;
 pub use vehicle_control::*;
@@ -1,5 +1,6 @@
 use crate::math::*;
 use crate::nanotime::Nanotime;
+use crate::nbody::GravityModel;
 use crate::pv::*;
 
 #[derive(Default, Debug, Clone, Copy)]
@@ -17,6 +18,93 @@ pub struct RigidBody {
 
 pub const MAX_ANGULAR_VELOCITY: f64 = 4.0;
 
+/// Numerical scheme used to advance the translational state of a rigid
+/// body under gravity and thrust.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Integrator {
+    /// First order and cheap, but drifts in energy over long arcs
+    SemiImplicitEuler,
+    /// Classic fourth order Runge-Kutta
+    RungeKutta4,
+    /// Fourth order symplectic integrator; energy error stays bounded
+    /// over arbitrarily many orbits
+    #[default]
+    Yoshida4,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicsSettings {
+    pub integrator: Integrator,
+    /// Number of integration steps taken per physics tick
+    pub substeps: u32,
+    /// Whether vehicles which can't be put on rails may be advanced
+    /// several ticks at a time when warping
+    pub warp: bool,
+    /// Largest number of ticks covered by a single physics warp step
+    pub max_warp_ticks: u32,
+    /// Maximum estimated position error per integration step while
+    /// warping, in meters
+    pub warp_tolerance: f64,
+    pub gravity: GravityModel,
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self {
+            integrator: Integrator::default(),
+            substeps: 2,
+            warp: false,
+            max_warp_ticks: 256,
+            warp_tolerance: 0.01,
+            gravity: GravityModel::default(),
+        }
+    }
+}
+
+/// Smallest step the adaptive integrator will shrink to, in seconds.
+const MIN_ADAPTIVE_STEP: f64 = 1E-4;
+
+// https://en.wikipedia.org/wiki/Leapfrog_integration#4th_order_Yoshida_integrator
+const YOSHIDA_W1: f64 = 1.3512071919596578;
+const YOSHIDA_W0: f64 = -1.7024143839193153;
+const YOSHIDA_C: [f64; 4] = [
+    YOSHIDA_W1 / 2.0,
+    (YOSHIDA_W0 + YOSHIDA_W1) / 2.0,
+    (YOSHIDA_W0 + YOSHIDA_W1) / 2.0,
+    YOSHIDA_W1 / 2.0,
+];
+const YOSHIDA_D: [f64; 3] = [YOSHIDA_W1, YOSHIDA_W0, YOSHIDA_W1];
+
+fn integrate_step(pv: PV, accel: impl Fn(DVec2) -> DVec2, dt: f64, integrator: Integrator) -> PV {
+    let PV { mut pos, mut vel } = pv;
+    match integrator {
+        Integrator::SemiImplicitEuler => {
+            vel += accel(pos) * dt;
+            pos += vel * dt;
+        }
+        Integrator::RungeKutta4 => {
+            let k1v = accel(pos);
+            let k1x = vel;
+            let k2v = accel(pos + k1x * dt / 2.0);
+            let k2x = vel + k1v * dt / 2.0;
+            let k3v = accel(pos + k2x * dt / 2.0);
+            let k3x = vel + k2v * dt / 2.0;
+            let k4v = accel(pos + k3x * dt);
+            let k4x = vel + k3v * dt;
+            pos += (k1x + 2.0 * k2x + 2.0 * k3x + k4x) * dt / 6.0;
+            vel += (k1v + 2.0 * k2v + 2.0 * k3v + k4v) * dt / 6.0;
+        }
+        Integrator::Yoshida4 => {
+            for i in 0..3 {
+                pos += vel * YOSHIDA_C[i] * dt;
+                vel += accel(pos) * YOSHIDA_D[i] * dt;
+            }
+            pos += vel * YOSHIDA_C[3] * dt;
+        }
+    }
+    PV { pos, vel }
+}
+
 impl RigidBody {
     pub const ZERO: RigidBody = RigidBody {
         pv: PV::ZERO,
@@ -32,12 +120,74 @@ impl RigidBody {
         }
     }
 
-    pub fn on_sim_tick(&mut self, a: BodyFrameAccel, gravity: DVec2, dt: Nanotime) {
-        let linear = if a.linear != DVec2::ZERO {
+    /// Advances the body by one tick. Thrust is held constant in the
+    /// inertial frame over the tick, while gravity is re-evaluated at
+    /// every stage of every substep.
+    pub fn on_sim_tick(
+        &mut self,
+        a: BodyFrameAccel,
+        gravity: impl Fn(DVec2) -> DVec2,
+        dt: Nanotime,
+        settings: PhysicsSettings,
+    ) {
+        let thrust = self.step_attitude(a, dt);
+        let substeps = settings.substeps.max(1);
+        let h = dt.to_secs_f64() / substeps as f64;
+        for _ in 0..substeps {
+            self.pv = integrate_step(self.pv, |p| gravity(p) + thrust, h, settings.integrator);
+        }
+    }
+
+    /// Advances the body by an arbitrary interval, choosing step sizes by
+    /// step doubling so that the estimated position error of every step
+    /// stays below `tolerance` meters. Attitude is advanced once over the
+    /// whole interval. Returns the number of accepted steps.
+    pub fn on_sim_tick_adaptive(
+        &mut self,
+        a: BodyFrameAccel,
+        gravity: impl Fn(DVec2) -> DVec2,
+        dt: Nanotime,
+        integrator: Integrator,
+        tolerance: f64,
+    ) -> u32 {
+        let thrust = self.step_attitude(a, dt);
+        let accel = |p| gravity(p) + thrust;
+
+        let total = dt.to_secs_f64();
+        let mut elapsed = 0.0;
+        let mut h = total;
+        let mut steps = 0;
+
+        while total - elapsed > 1E-9 {
+            h = h.min(total - elapsed);
+            let full = integrate_step(self.pv, accel, h, integrator);
+            let half = integrate_step(self.pv, accel, h / 2.0, integrator);
+            let half = integrate_step(half, accel, h / 2.0, integrator);
+            let error = full.pos.distance(half.pos);
+
+            if error <= tolerance || h <= MIN_ADAPTIVE_STEP {
+                self.pv = half;
+                elapsed += h;
+                steps += 1;
+                if error < tolerance / 16.0 {
+                    h *= 2.0;
+                }
+            } else {
+                h /= 2.0;
+            }
+        }
+
+        steps
+    }
+
+    /// Advances the attitude of the body, returning the inertial frame
+    /// linear acceleration due to thrust at the new attitude.
+    fn step_attitude(&mut self, a: BodyFrameAccel, dt: Nanotime) -> DVec2 {
+        let thrust = if a.linear != DVec2::ZERO {
             rotate_f64(a.linear, self.angle)
         } else {
             DVec2::ZERO
-        } + gravity;
+        };
 
         self.angular_velocity += a.angular * dt.to_secs_f64();
         // self.angular_velocity = self
@@ -46,19 +196,19 @@ impl RigidBody {
         self.angle += self.angular_velocity * dt.to_secs_f64();
         self.angle = wrap_0_2pi_f64(self.angle);
 
-        // TODO
-        self.pv.vel += linear * dt.to_secs_f64();
-        self.pv.pos += self.pv.vel * dt.to_secs_f64();
+        thrust
     }
 
-    pub fn clamp_with_elevation(&mut self, elevation: f64) -> bool {
+    /// Holds the body on the surface of a planet with the given radius and
+    /// rotation rate, moving along with the ground beneath it.
+    pub fn clamp_with_elevation(&mut self, elevation: f64, rotation_rate: f64) -> bool {
         let elev = self.pv.pos.length() as f64;
 
         let clamped = elev <= elevation;
 
         if clamped {
             self.pv.pos = self.pv.pos.normalize_or_zero() * elevation as f64;
-            self.pv.vel = DVec2::ZERO;
+            self.pv.vel = self.pv.pos.perp() * rotation_rate;
         }
 
         clamped
@@ -71,3 +221,157 @@ pub fn kinematic_apoapis(altitude: f64, vertical_velocity: f64, gravity: f64) ->
     }
     altitude + vertical_velocity.powi(2) / (2.0 * gravity.abs())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orbits::{Body, SparseOrbit};
+
+    const DT: Nanotime = Nanotime::millis(25);
+
+    fn energy(pv: PV, body: &Body) -> f64 {
+        pv.vel.length_squared() / 2.0 - body.mu / pv.pos.length()
+    }
+
+    // propagates a coasting body through `orbits` periods of the given orbit,
+    // returning the worst relative energy error and position error seen
+    fn coast(orbit: &SparseOrbit, orbits: u32, settings: PhysicsSettings) -> (f64, f64) {
+        let body = orbit.body;
+        let mut rb = RigidBody {
+            pv: orbit.initial,
+            ..RigidBody::ZERO
+        };
+        let e0 = energy(rb.pv, &body);
+        let period = orbit.period().unwrap();
+        let ticks = (period * orbits as i64).inner() / DT.inner();
+
+        let mut max_energy_error: f64 = 0.0;
+        let mut max_position_error: f64 = 0.0;
+        for i in 1..=ticks {
+            rb.on_sim_tick(BodyFrameAccel::default(), |p| body.gravity(p), DT, settings);
+            max_energy_error = max_energy_error.max(((energy(rb.pv, &body) - e0) / e0).abs());
+            if i % 100 == 0 {
+                let truth = orbit.pv(orbit.epoch + DT * i).unwrap();
+                max_position_error = max_position_error.max(rb.pv.pos.distance(truth.pos));
+            }
+        }
+        (max_energy_error, max_position_error)
+    }
+
+    fn test_orbit() -> SparseOrbit {
+        let body = Body::with_mass(100.0, 1000.0, 1E6);
+        SparseOrbit::new(400.0, 150.0, 0.3, body, Nanotime::zero(), false).unwrap()
+    }
+
+    #[test]
+    fn symplectic_coast_matches_orbit() {
+        let orbit = test_orbit();
+        let settings = PhysicsSettings {
+            integrator: Integrator::Yoshida4,
+            substeps: 2,
+            ..Default::default()
+        };
+        let (de, dx) = coast(&orbit, 20, settings);
+        assert!(de < 1E-7, "energy error {de}");
+        assert!(dx < 0.1, "position error {dx}");
+
+        // the energy error oscillates rather than growing
+        let (de_short, _) = coast(&orbit, 2, settings);
+        assert!(
+            de < de_short * 1.5,
+            "energy error grew from {de_short} to {de}"
+        );
+    }
+
+    #[test]
+    fn runge_kutta_coast_matches_orbit() {
+        let orbit = test_orbit();
+        let settings = PhysicsSettings {
+            integrator: Integrator::RungeKutta4,
+            substeps: 2,
+            ..Default::default()
+        };
+        let (de, dx) = coast(&orbit, 20, settings);
+        assert!(de < 1E-6, "energy error {de}");
+        assert!(dx < 0.1, "position error {dx}");
+    }
+
+    #[test]
+    fn higher_order_beats_euler() {
+        let orbit = test_orbit();
+        let euler = PhysicsSettings {
+            integrator: Integrator::SemiImplicitEuler,
+            substeps: 1,
+            ..Default::default()
+        };
+        let (euler_de, _) = coast(&orbit, 3, euler);
+        let (yoshida_de, _) = coast(&orbit, 3, PhysicsSettings::default());
+        assert!(yoshida_de * 1000.0 < euler_de);
+    }
+
+    #[test]
+    fn constant_thrust_is_exact() {
+        let accel = BodyFrameAccel {
+            linear: DVec2::new(3.0, 0.0),
+            angular: 0.0,
+        };
+        for integrator in [Integrator::RungeKutta4, Integrator::Yoshida4] {
+            let mut rb = RigidBody::ZERO;
+            let settings = PhysicsSettings {
+                integrator,
+                substeps: 3,
+                ..Default::default()
+            };
+            for _ in 0..400 {
+                rb.on_sim_tick(accel, |_| DVec2::ZERO, DT, settings);
+            }
+            // ten seconds at 3 m/s^2
+            assert!(
+                (rb.pv.pos.x - 150.0).abs() < 1E-9,
+                "{integrator:?} {:?}",
+                rb.pv
+            );
+            assert!(
+                (rb.pv.vel.x - 30.0).abs() < 1E-9,
+                "{integrator:?} {:?}",
+                rb.pv
+            );
+        }
+    }
+
+    #[test]
+    fn adaptive_steps_track_orbit() {
+        let orbit = test_orbit();
+        let body = orbit.body;
+        let mut rb = RigidBody {
+            pv: orbit.initial,
+            ..RigidBody::ZERO
+        };
+        let e0 = energy(rb.pv, &body);
+
+        // several orbits, advanced 64 ticks at a time
+        let chunk = DT * 64i64;
+        let chunks = 5 * orbit.period().unwrap().inner() / chunk.inner();
+        let mut steps = 0;
+        for _ in 0..chunks {
+            steps += rb.on_sim_tick_adaptive(
+                BodyFrameAccel::default(),
+                |p| body.gravity(p),
+                chunk,
+                Integrator::Yoshida4,
+                1E-3,
+            );
+        }
+
+        let truth = orbit.pv(orbit.epoch + chunk * chunks).unwrap();
+        assert!(
+            rb.pv.pos.distance(truth.pos) < 0.5,
+            "{:?} {:?}",
+            rb.pv,
+            truth
+        );
+        assert!(((energy(rb.pv, &body) - e0) / e0).abs() < 1E-5);
+        // far fewer steps than ticks
+        assert!((steps as i64) < chunks * 64 / 4, "{steps} steps");
+    }
+}
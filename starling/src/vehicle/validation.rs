@@ -0,0 +1,206 @@
+use crate::factory::Item;
+use crate::math::*;
+use crate::parts::*;
+use crate::vehicle::*;
+use std::collections::BTreeSet;
+
+/// How far the main engines' line of thrust may miss the center of mass
+/// before it's worth mentioning, in meters.
+const MAX_THRUST_OFFSET: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The craft works, but probably not as intended
+    Warning,
+    /// The craft can't fly as designed
+    Error,
+}
+
+/// Problem with the design of a vehicle, found by [`Vehicle::validate`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DesignIssue {
+    /// Thruster with no pipe to any tank
+    UnpluggedThruster(PartId),
+    /// Machine with no pipe to any tank
+    UnpluggedMachine(PartId),
+    /// Tank feeding thrusters with something that won't burn
+    IncompatiblePropellant(PartId, Item),
+    /// Main engines pointing in the given direction whose combined thrust
+    /// doesn't pass through the center of mass, along with the torque
+    /// they'd produce at full throttle in N-m
+    NetTorque(Rotation, f64),
+    /// Parts which aren't attached to the largest piece of the craft
+    Disconnected(Vec<PartId>),
+    /// No RCS thrusters or magnetorquers to turn the craft with
+    NoAttitudeControl,
+    /// Nothing pushes the craft forwards
+    NotControllable,
+}
+
+impl DesignIssue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::UnpluggedThruster(_) => Severity::Warning,
+            Self::UnpluggedMachine(_) => Severity::Warning,
+            Self::IncompatiblePropellant(_, _) => Severity::Error,
+            Self::NetTorque(_, _) => Severity::Warning,
+            Self::Disconnected(_) => Severity::Error,
+            Self::NoAttitudeControl => Severity::Warning,
+            Self::NotControllable => Severity::Error,
+        }
+    }
+
+    /// Parts responsible for the issue, if it comes down to particular ones.
+    pub fn parts(&self) -> Vec<PartId> {
+        match self {
+            Self::UnpluggedThruster(id)
+            | Self::UnpluggedMachine(id)
+            | Self::IncompatiblePropellant(id, _) => vec![*id],
+            Self::Disconnected(ids) => ids.clone(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl std::fmt::Display for DesignIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnpluggedThruster(id) => write!(f, "Thruster {:?} isn't plumbed to a tank", id),
+            Self::UnpluggedMachine(id) => write!(f, "Machine {:?} isn't plumbed to a tank", id),
+            Self::IncompatiblePropellant(id, item) => {
+                write!(f, "Tank {:?} feeds thrusters with {:?}", id, item)
+            }
+            Self::NetTorque(rot, torque) => write!(
+                f,
+                "Thrusters facing {:?} produce {:0.0} N-m of torque at full throttle",
+                rot, torque
+            ),
+            Self::Disconnected(ids) => {
+                write!(f, "{} parts aren't attached to the craft", ids.len())
+            }
+            Self::NoAttitudeControl => write!(f, "No RCS thrusters or magnetorquers"),
+            Self::NotControllable => write!(f, "No thrusters push the craft forwards"),
+        }
+    }
+}
+
+/// Whether two rectangles of pixels, given as lower corner and size,
+/// overlap or share an edge.
+fn touches(a: (IVec2, IVec2), b: (IVec2, IVec2)) -> bool {
+    let lower = a.0.max(b.0);
+    let upper = (a.0 + a.1).min(b.0 + b.1);
+    let overlap = upper - lower;
+    overlap.x >= 0 && overlap.y >= 0 && (overlap.x > 0 || overlap.y > 0)
+}
+
+impl Vehicle {
+    /// Checks the design of this vehicle for mistakes, most severe first.
+    pub fn validate(&self) -> Vec<DesignIssue> {
+        if self.parts().next().is_none() {
+            return Vec::new();
+        }
+
+        let mut issues = Vec::new();
+
+        if !self.is_controllable() {
+            issues.push(DesignIssue::NotControllable);
+        }
+
+        let has_attitude_control =
+            self.magnetorquers().next().is_some() || self.thrusters().any(|(t, _)| t.is_rcs);
+        if !has_attitude_control {
+            issues.push(DesignIssue::NoAttitudeControl);
+        }
+
+        let plumbed_to = |id: &PartId, f: &dyn Fn(&InstantiatedPart) -> bool| {
+            self.plumbed_to(*id).any(|(_, p)| f(p))
+        };
+
+        let mut sorted: Vec<_> = self.parts().collect();
+        sorted.sort_by_key(|(id, _)| **id);
+
+        for (id, part) in &sorted {
+            let is_tank = |p: &InstantiatedPart| p.as_tank().is_some();
+            let is_thruster = |p: &InstantiatedPart| p.as_thruster().is_some();
+            if part.as_thruster().is_some() && !plumbed_to(id, &is_tank) {
+                issues.push(DesignIssue::UnpluggedThruster(**id));
+            }
+            if part.as_machine().is_some() && !plumbed_to(id, &is_tank) {
+                issues.push(DesignIssue::UnpluggedMachine(**id));
+            }
+            if let Some((_, d)) = part.as_tank() {
+                if let Some(item) = d.item() {
+                    if !item.is_propellant() && plumbed_to(id, &is_thruster) {
+                        issues.push(DesignIssue::IncompatiblePropellant(**id, item));
+                    }
+                }
+            }
+        }
+
+        let com = self.center_of_mass();
+        for rot in enum_iterator::all::<Rotation>() {
+            let mut thrust = 0.0;
+            let mut torque = 0.0;
+            for (_, part) in &sorted {
+                let Some((t, d)) = part.as_thruster() else {
+                    continue;
+                };
+                if t.is_rcs || !d.is_enabled() || part.rotation() != rot {
+                    continue;
+                }
+                let lever_arm = part.center_meters().as_dvec2() - com;
+                let thrust_dir = rotate_f64(DVec2::X, rot.to_angle());
+                thrust += t.max_thrust();
+                torque += cross2d(lever_arm, thrust_dir) * t.max_thrust();
+            }
+            if thrust > 0.0 && (torque / thrust).abs() > MAX_THRUST_OFFSET {
+                issues.push(DesignIssue::NetTorque(rot, torque));
+            }
+        }
+
+        let disconnected = self.disconnected_parts();
+        if !disconnected.is_empty() {
+            issues.push(DesignIssue::Disconnected(disconnected));
+        }
+
+        issues.sort_by_key(|issue| std::cmp::Reverse(issue.severity()));
+        issues
+    }
+
+    /// Parts which don't touch the largest group of parts touching each
+    /// other, in any layer. Of groups the same size, the one holding the
+    /// lowest id counts as the largest.
+    fn disconnected_parts(&self) -> Vec<PartId> {
+        let rects: Vec<_> = self
+            .parts()
+            .map(|(id, p)| (*id, (p.origin(), p.dims_grid().as_ivec2())))
+            .collect();
+
+        let mut unvisited: BTreeSet<PartId> = rects.iter().map(|(id, _)| *id).collect();
+        let mut groups: Vec<Vec<PartId>> = Vec::new();
+
+        // groups are seeded from their lowest id, so they come out in order
+        while let Some(start) = unvisited.pop_first() {
+            let mut group = vec![start];
+            let mut open = vec![start];
+            while let Some(id) = open.pop() {
+                let Some((_, a)) = rects.iter().find(|(other, _)| *other == id) else {
+                    continue;
+                };
+                for (other, b) in &rects {
+                    if unvisited.contains(other) && touches(*a, *b) {
+                        unvisited.remove(other);
+                        group.push(*other);
+                        open.push(*other);
+                    }
+                }
+            }
+            groups.push(group);
+        }
+
+        groups.sort_by_key(|g| (std::cmp::Reverse(g.len()), g[0]));
+        let mut ret: Vec<_> = groups.into_iter().skip(1).flatten().collect();
+        ret.sort();
+        ret
+    }
+}
@@ -1,6 +1,7 @@
 use crate::aabb::AABB;
 use crate::factory::*;
 use crate::math::*;
+use crate::mission::MissionScript;
 use crate::nanotime::Nanotime;
 use crate::parts::*;
 use crate::pid::PDCtrl;
@@ -8,7 +9,7 @@ use crate::vehicle::*;
 use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 
-fn rocket_equation(ve: f64, m0: Mass, m1: Mass) -> f64 {
+pub(crate) fn rocket_equation(ve: f64, m0: Mass, m1: Mass) -> f64 {
     ve * (m0.to_kg_f64() / m1.to_kg_f64()).ln()
 }
 
@@ -34,7 +35,7 @@ pub fn occupied_pixels(pos: IVec2, rot: Rotation, part: &PartPrototype) -> Vec<I
     ret
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct PartId(u64);
 
 #[derive(Debug, Clone, Copy)]
@@ -75,6 +76,8 @@ pub struct Vehicle {
     total_mass: Mass,
     moment_of_inertia: f64,
     is_thrusting: bool,
+
+    mission: Option<MissionScript>,
 }
 
 impl Vehicle {
@@ -92,12 +95,26 @@ impl Vehicle {
         model: String,
         prototypes: Vec<(IVec2, Rotation, PartPrototype)>,
         pipes: HashSet<IVec2>,
+    ) -> Self {
+        let instances = prototypes
+            .into_iter()
+            .map(|(pos, rot, proto)| InstantiatedPart::from_prototype(proto, pos, rot))
+            .collect();
+        Self::from_instances(name, model, instances, pipes)
+    }
+
+    /// Like [`Vehicle::from_parts`], but for parts which may already be
+    /// built or hold something.
+    pub fn from_instances(
+        name: String,
+        model: String,
+        instances: Vec<InstantiatedPart>,
+        pipes: HashSet<IVec2>,
     ) -> Self {
         let mut next_part_id = PartId(0);
         let mut parts = HashMap::new();
 
-        for (pos, rot, proto) in prototypes {
-            let instance = InstantiatedPart::from_prototype(proto, pos, rot);
+        for instance in instances {
             parts.insert(next_part_id, instance);
 
             next_part_id.0 += 1;
@@ -129,6 +146,8 @@ impl Vehicle {
             total_mass: Mass::ZERO,
             moment_of_inertia: 0.0,
             is_thrusting: false,
+
+            mission: None,
         };
 
         ret.update();
@@ -161,8 +180,12 @@ impl Vehicle {
     }
 
     pub fn add_part(&mut self, proto: PartPrototype, pos: IVec2, rot: Rotation) -> PartId {
+        self.add_instance(InstantiatedPart::from_prototype(proto, pos, rot))
+    }
+
+    /// Like [`Vehicle::add_part`], but keeps whatever state the part has.
+    pub fn add_instance(&mut self, instance: InstantiatedPart) -> PartId {
         let id = self.get_next_part_id();
-        let instance = InstantiatedPart::from_prototype(proto, pos, rot);
         self.parts.insert(id, instance);
         self.update();
         id
@@ -386,6 +409,22 @@ impl Vehicle {
         self.conn_groups.iter().any(|g| g.is_connected(id_a, id_b))
     }
 
+    /// Parts sharing a pipe network with the given one.
+    pub fn plumbed_to(
+        &self,
+        id: PartId,
+    ) -> impl Iterator<Item = (PartId, &InstantiatedPart)> + use<'_> {
+        let mut ids: HashSet<PartId> = self
+            .conn_groups
+            .iter()
+            .filter(|g| g.contains(id))
+            .flat_map(|g| g.ids())
+            .collect();
+        ids.remove(&id);
+        ids.into_iter()
+            .filter_map(|id| Some((id, self.parts.get(&id)?)))
+    }
+
     fn update(&mut self) {
         self.construct_connectivity();
         self.update_discriminator();
@@ -440,7 +479,10 @@ impl Vehicle {
         if self.thruster_count() == 0 {
             0.0
         } else {
-            self.thrusters().map(|(t, _)| t.max_thrust()).sum()
+            self.thrusters()
+                .filter(|(_, d)| d.is_enabled())
+                .map(|(t, _)| t.max_thrust())
+                .sum()
         }
     }
 
@@ -455,7 +497,7 @@ impl Vehicle {
 
         for (_, part) in &self.parts {
             if let Some((t, d)) = part.as_thruster() {
-                if t.is_rcs != rcs {
+                if t.is_rcs != rcs || !d.is_enabled() {
                     continue;
                 }
                 let v = rotate_f64(DVec2::X, part.rotation().to_angle());
@@ -576,6 +618,13 @@ impl Vehicle {
             .sum()
     }
 
+    /// Fuel burned per second with every thruster at full throttle.
+    pub fn max_fuel_consumption_rate(&self) -> f64 {
+        self.thrusters()
+            .map(|(t, _)| t.max_thrust() / t.exhaust_velocity as f64)
+            .sum()
+    }
+
     pub fn remaining_dv(&self) -> f64 {
         if self.total_mass() == Mass::ZERO || self.dry_mass() == Mass::ZERO {
             return 0.0;
@@ -600,6 +649,15 @@ impl Vehicle {
         self.model = model;
     }
 
+    /// Script the vehicle carries out on its own once it's flying.
+    pub fn mission(&self) -> Option<&MissionScript> {
+        self.mission.as_ref()
+    }
+
+    pub fn set_mission(&mut self, mission: Option<MissionScript>) {
+        self.mission = mission;
+    }
+
     pub fn title(&self) -> String {
         let model = if self.model.len() >= 4 {
             self.model[0..4].to_uppercase()
@@ -792,6 +850,26 @@ impl Vehicle {
         self.parts.iter().filter_map(|(_, p)| p.as_radar())
     }
 
+    /// Radars along with the direction they face in the vehicle body frame.
+    pub fn radars_with_rotation(&self) -> impl Iterator<Item = (&Radar, Rotation)> + use<'_> {
+        self.parts
+            .values()
+            .filter_map(|p| Some((p.as_radar()?, p.rotation())))
+    }
+
+    pub fn radar_range(&self) -> Option<f64> {
+        self.radars().map(|r| r.range()).reduce(f64::max)
+    }
+
+    pub fn antennas(&self) -> impl Iterator<Item = &Antenna> + use<'_> {
+        self.parts.values().filter_map(|p| p.as_antenna())
+    }
+
+    /// Range of the longest-reaching antenna on this vehicle, if any.
+    pub fn comms_range(&self) -> Option<f64> {
+        self.antennas().map(|a| a.range()).reduce(f64::max)
+    }
+
     pub fn magnetorquers(
         &self,
     ) -> impl Iterator<Item = (&Magnetorquer, &MagnetorquerInstanceData)> + use<'_> {
@@ -818,20 +896,51 @@ impl Vehicle {
         false
     }
 
-    pub fn clear_contents(&mut self, id: PartId) -> bool {
+    pub fn set_thruster_enabled(&mut self, id: PartId, enabled: bool) -> bool {
         if let Some(part) = self.parts.get_mut(&id) {
-            if let Some((_, d)) = part.as_tank_mut() {
-                d.clear_contents();
+            if let Some((_, d)) = part.as_thruster_mut() {
+                d.set_enabled(enabled);
+                self.update_physical_quantities();
                 return true;
             }
+        }
+        false
+    }
 
-            if let Some((_, d)) = part.as_cargo_mut() {
+    /// Tops up every tank which is empty or already holds the given item.
+    pub fn fill_tanks(&mut self, item: Item) {
+        for part in self.parts.values_mut() {
+            if let Some((t, d)) = part.as_tank_mut() {
+                t.put(item, t.capacity(), d);
+            }
+        }
+        self.update_physical_quantities();
+    }
+
+    pub fn empty_tanks(&mut self) {
+        for part in self.parts.values_mut() {
+            if let Some((_, d)) = part.as_tank_mut() {
                 d.clear_contents();
-                return true;
             }
         }
+        self.update_physical_quantities();
+    }
 
-        return false;
+    pub fn clear_contents(&mut self, id: PartId) -> bool {
+        let Some(part) = self.parts.get_mut(&id) else {
+            return false;
+        };
+
+        if let Some((_, d)) = part.as_tank_mut() {
+            d.clear_contents();
+        } else if let Some((_, d)) = part.as_cargo_mut() {
+            d.clear_contents();
+        } else {
+            return false;
+        }
+
+        self.update_physical_quantities();
+        true
     }
 
     pub fn bounding_radius(&self) -> f64 {
@@ -1,7 +1,9 @@
 use crate::math::*;
+use crate::nanotime::Nanotime;
 use crate::orbits::Body;
 use crate::orbits::SparseOrbit;
 use crate::pid::PDCtrl;
+use crate::pv::PV;
 use crate::vehicle::*;
 
 #[derive(Default, Debug, Clone, Copy, PartialEq)]
@@ -311,6 +313,8 @@ pub enum VehicleControlStatus {
     NoVelocityVector,
     ComingAbout,
     HoldingAttitude,
+    Descending,
+    Braking,
 }
 
 impl VehicleControlStatus {
@@ -460,6 +464,117 @@ pub fn burn_along_velocity_vector_control_law(
     (ctrl, status)
 }
 
+/// Burns until the vehicle's velocity relative to its parent matches the
+/// given one, turning to face along the difference first.
+pub fn match_velocity_control_law(
+    target_vel: DVec2,
+    body: &RigidBody,
+    vehicle: &Vehicle,
+) -> (VehicleControl, VehicleControlStatus) {
+    let error = target_vel - body.pv.vel;
+    if error.length() < 0.2 {
+        return (VehicleControl::NULLOPT, VehicleControlStatus::Done);
+    }
+
+    let thrust_angle = error.to_angle();
+    let mut ctrl = VehicleControl::NULLOPT;
+    ctrl.attitude = compute_attitude_control(body, thrust_angle, &vehicle.attitude_controller);
+    let angular_error = wrap_pi_npi_f64(thrust_angle - body.angle);
+    let status = if angular_error.abs() < 0.05 {
+        // ease off on the way in so the burn doesn't overshoot, though
+        // main engines won't run much below a fifth of full throttle
+        let max_accel = vehicle.max_forward_thrust() / vehicle.total_mass().to_kg_f64();
+        ctrl.plus_x.throttle = (error.length() / max_accel).clamp(0.25, 1.0) as f32;
+        VehicleControlStatus::InProgress
+    } else {
+        VehicleControlStatus::ComingAbout
+    };
+
+    (ctrl, status)
+}
+
+/// Acceleration the landing law is after, and whether it's time to use
+/// it yet or the vehicle can keep falling towards the altitude where it
+/// has to start braking.
+fn landing_accel(planet: &Body, pv: PV, max_accel: f64) -> (DVec2, bool) {
+    let up = pv.pos.normalize_or_zero();
+    let altitude = pv.pos.length() - planet.radius;
+    let ground_vel = pv.vel - planet.surface_velocity(pv.pos);
+    let gravity = planet.gravity(pv.pos);
+
+    let braking_accel = (0.7 * max_accel - gravity.length()).max(0.1);
+    let stopping_distance = ground_vel.length_squared() / (2.0 * braking_accel);
+    let braking = altitude <= stopping_distance * 1.5 + 500.0;
+
+    let descent_rate = (0.5 * (2.0 * braking_accel * altitude.max(0.0)).sqrt()).max(2.0);
+    let accel = (-up * descent_rate - ground_vel) - gravity;
+
+    // holding the descent rate comes first, and whatever thrust is left
+    // over goes on shedding horizontal speed
+    let vertical = accel.dot(up).clamp(-max_accel, max_accel);
+    let spare = (max_accel * max_accel - vertical * vertical)
+        .max(0.0)
+        .sqrt();
+    let horizontal = accel.perp_dot(up).clamp(-spare, spare);
+    (up * vertical - up.perp() * horizontal, braking)
+}
+
+/// Brings a falling vehicle down onto the surface of its parent body. The
+/// vehicle coasts until it's close to the last moment it could stop, then
+/// brakes against its velocity relative to the ground, following a
+/// descent rate which tapers off as it approaches the surface.
+pub fn landing_control_law(
+    planet: &Body,
+    body: &RigidBody,
+    vehicle: &Vehicle,
+) -> (VehicleControl, VehicleControlStatus) {
+    let max_accel = vehicle.max_forward_thrust() / vehicle.total_mass().to_kg_f64();
+    let (accel, braking) = landing_accel(planet, body.pv, max_accel);
+
+    // get pointed the right way ahead of time
+    let thrust_angle = accel.to_angle();
+    let mut ctrl = VehicleControl::NULLOPT;
+    ctrl.attitude = compute_attitude_control(body, thrust_angle, &vehicle.attitude_controller);
+    if !braking {
+        return (ctrl, VehicleControlStatus::Descending);
+    }
+
+    let angular_error = wrap_pi_npi_f64(thrust_angle - body.angle);
+    if angular_error.abs() < 0.3 {
+        ctrl.plus_x.throttle = (accel.length() / max_accel).clamp(0.0, 1.0) as f32;
+    }
+
+    (ctrl, VehicleControlStatus::Braking)
+}
+
+/// Where and when a vehicle with the given acceleration would touch down
+/// if it left the landing law to it from here, treating it as a point
+/// that can thrust in any direction straight away.
+pub fn predict_landing(
+    planet: &Body,
+    pv: PV,
+    stamp: Nanotime,
+    max_accel: f64,
+) -> Option<(Nanotime, PV)> {
+    let mut pv = pv;
+    let mut t = 0.0;
+    while t < 86_400.0 {
+        let altitude = pv.pos.length() - planet.radius;
+        if altitude <= 0.0 {
+            return Some((stamp + Nanotime::secs_f64(t), pv));
+        }
+        let thrust = match landing_accel(planet, pv, max_accel) {
+            (accel, true) => accel,
+            (_, false) => DVec2::ZERO,
+        };
+        let dt: f64 = (0.02 * altitude / pv.vel.length().max(1.0)).clamp(0.05, 10.0);
+        pv.vel += (planet.gravity(pv.pos) + thrust) * dt;
+        pv.pos += pv.vel * dt;
+        t += dt;
+    }
+    None
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum VehicleControlPolicy {
     Idle,
@@ -469,6 +584,8 @@ pub enum VehicleControlPolicy {
     BurnPrograde,
     BurnRetrograde,
     HoldAttitude(Option<f64>),
+    MatchVelocity(DVec2),
+    Land,
 }
 
 impl VehicleControlPolicy {
@@ -481,6 +598,8 @@ impl VehicleControlPolicy {
             VehicleControlPolicy::BurnPrograde => "Burning prograde".to_string(),
             VehicleControlPolicy::BurnRetrograde => "Burning retrograde".to_string(),
             VehicleControlPolicy::HoldAttitude(_) => "Holding attitude".to_string(),
+            VehicleControlPolicy::MatchVelocity(_) => "Matching velocity".to_string(),
+            VehicleControlPolicy::Land => "Landing".to_string(),
         }
     }
 }
@@ -589,7 +708,10 @@ impl VehicleController {
             VehicleControlPolicy::LaunchToOrbit(_) => VehicleControlPolicy::BurnPrograde,
             VehicleControlPolicy::BurnPrograde => VehicleControlPolicy::BurnRetrograde,
             VehicleControlPolicy::BurnRetrograde => VehicleControlPolicy::HoldAttitude(None),
-            VehicleControlPolicy::HoldAttitude(_) => VehicleControlPolicy::Idle,
+            VehicleControlPolicy::HoldAttitude(_) => VehicleControlPolicy::Land,
+            VehicleControlPolicy::MatchVelocity(_) | VehicleControlPolicy::Land => {
+                VehicleControlPolicy::Idle
+            }
         };
     }
 
@@ -1,6 +1,7 @@
 #[cfg(test)]
 mod tests {
-    use std::collections::HashSet;
+    use approx::assert_relative_eq;
+    use std::collections::{HashMap, HashSet};
 
     use crate::prelude::*;
 
@@ -61,4 +62,397 @@ mod tests {
         assert_eq!(aabb.span, Vec2::splat(0.5));
         assert_eq!(aabb.center, Vec2::splat(0.25));
     }
+
+    #[test]
+    fn plumbed_vehicle_validation() {
+        let mut vehicle = plumbed_vehicle();
+
+        assert_eq!(vehicle.validate(), vec![DesignIssue::NoAttitudeControl]);
+
+        let tank_id = vehicle
+            .get_part_at(IVec2::new(35, 5), PartLayer::Internal)
+            .unwrap();
+        vehicle.fill_tanks(Item::CO2);
+        let issues = vehicle.validate();
+        assert_eq!(
+            issues[0],
+            DesignIssue::IncompatiblePropellant(tank_id, Item::CO2)
+        );
+        assert_eq!(issues[0].severity(), Severity::Error);
+    }
+
+    #[test]
+    fn badly_designed_vehicle_validation() {
+        let block = PartPrototype::Generic(Generic::new(
+            "".to_string(),
+            UVec2::new(10, 10),
+            PartLayer::Structural,
+            Mass::kilograms(400),
+        ));
+        let thruster = PartPrototype::Thruster(ThrusterModel::main_thruster(5000.0, 3500.0));
+
+        let vehicle = Vehicle::from_parts(
+            "".into(),
+            "".into(),
+            vec![
+                (IVec2::ZERO, Rotation::East, thruster),
+                (IVec2::new(0, 10), Rotation::East, block.clone()),
+                (IVec2::new(100, 100), Rotation::East, block),
+            ],
+            HashSet::new(),
+        );
+
+        let thruster_id = vehicle.get_part_at(IVec2::new(5, 5), None).unwrap();
+        let stray_id = vehicle.get_part_at(IVec2::new(105, 105), None).unwrap();
+
+        let issues = vehicle.validate();
+        assert_eq!(issues[0], DesignIssue::Disconnected(vec![stray_id]));
+        assert!(issues.contains(&DesignIssue::UnpluggedThruster(thruster_id)));
+        assert!(issues.contains(&DesignIssue::NoAttitudeControl));
+        assert!(issues
+            .iter()
+            .any(|i| matches!(i, DesignIssue::NetTorque(Rotation::East, _))));
+        assert!(!issues.contains(&DesignIssue::NotControllable));
+    }
+
+    #[test]
+    fn equal_pieces_keep_the_lowest_id() {
+        let block = PartPrototype::Generic(Generic::new(
+            "".to_string(),
+            UVec2::new(10, 10),
+            PartLayer::Structural,
+            Mass::kilograms(400),
+        ));
+        let parts = (0..6)
+            .map(|i| (IVec2::new(100 * i, 0), Rotation::East, block.clone()))
+            .collect();
+        let vehicle = Vehicle::from_parts("".into(), "".into(), parts, HashSet::new());
+
+        let mut ids: Vec<_> = vehicle.parts().map(|(id, _)| *id).collect();
+        ids.sort();
+        // each HashSet iterates in its own order, so try a few
+        let expected = DesignIssue::Disconnected(ids[1..].to_vec());
+        for _ in 0..10 {
+            assert!(vehicle.validate().contains(&expected));
+        }
+    }
+
+    #[test]
+    fn single_stage_analysis() {
+        let mut vehicle = plumbed_vehicle();
+
+        let system = PlanetarySystem::new(EntityId(0), "Luna", Body::LUNA);
+
+        let empty = vehicle.analyze(&system);
+        assert_eq!(empty.len(), 1);
+        assert_eq!(empty[0].delta_v, 0.0);
+        assert_eq!(empty[0].thrust, 0.0);
+
+        let nothing = Vehicle::new().analyze(&system);
+        assert_eq!(nothing.len(), 1);
+        assert_eq!(nothing[0].twr[0].1, 0.0);
+
+        vehicle.fill_tanks(Item::Methane);
+        assert_eq!(vehicle.total_mass(), Mass::kilograms(1900));
+        let stage = &vehicle.analyze(&system)[0];
+
+        assert_eq!(stage.wet_mass, Mass::kilograms(1900));
+        assert_eq!(stage.dry_mass, Mass::kilograms(900));
+        assert_eq!(stage.thrust, 5000.0);
+        assert_eq!(stage.exhaust_velocity, 3500.0);
+        assert_relative_eq!(stage.delta_v, 3500.0 * (1900.0f64 / 900.0).ln());
+        assert_relative_eq!(stage.burn_time, 1000.0 * 3500.0 / 5000.0);
+
+        let g = Body::LUNA.surface_gravity();
+        assert_eq!(stage.twr[0].0, "Luna");
+        assert_relative_eq!(stage.twr[0].1, 5000.0 / (1900.0 * g));
+
+        vehicle.empty_tanks();
+        assert_eq!(vehicle.total_mass(), Mass::kilograms(900));
+    }
+
+    #[test]
+    fn disabled_engines_give_no_thrust() {
+        let thruster_id = |vehicle: &Vehicle| {
+            vehicle
+                .parts()
+                .find(|(_, p)| p.as_thruster().is_some())
+                .map(|(id, _)| *id)
+                .unwrap()
+        };
+
+        let mut vehicle = plumbed_vehicle();
+        vehicle.fill_tanks(Item::Methane);
+        assert!(vehicle.set_thruster_enabled(thruster_id(&vehicle), false));
+
+        let system = PlanetarySystem::new(EntityId(0), "Luna", Body::LUNA);
+        let stage = &vehicle.analyze(&system)[0];
+        assert_eq!(stage.thrust, 0.0);
+        assert_eq!(stage.delta_v, 0.0);
+        assert_eq!(vehicle.max_thrust(), 0.0);
+        assert_eq!(vehicle.max_forward_thrust(), 0.0);
+
+        let block = PartPrototype::Generic(Generic::new(
+            "".to_string(),
+            UVec2::new(10, 10),
+            PartLayer::Structural,
+            Mass::kilograms(400),
+        ));
+        let thruster = PartPrototype::Thruster(ThrusterModel::main_thruster(5000.0, 3500.0));
+        let mut lopsided = Vehicle::from_parts(
+            "".into(),
+            "".into(),
+            vec![
+                (IVec2::ZERO, Rotation::East, thruster),
+                (IVec2::new(0, 10), Rotation::East, block),
+            ],
+            HashSet::new(),
+        );
+        let has_torque = |vehicle: &Vehicle| {
+            vehicle
+                .validate()
+                .iter()
+                .any(|i| matches!(i, DesignIssue::NetTorque(..)))
+        };
+        assert!(has_torque(&lopsided));
+        lopsided.set_thruster_enabled(thruster_id(&lopsided), false);
+        assert!(!has_torque(&lopsided));
+    }
+
+    /// A thruster piped to a single empty tank; 900 kg dry, 1900 kg full.
+    fn plumbed_vehicle() -> Vehicle {
+        let thruster = PartPrototype::Thruster(ThrusterModel::main_thruster(5000.0, 3500.0));
+        let tank = PartPrototype::Tank(TankModel::new(
+            "".into(),
+            UVec2::new(10, 10),
+            Mass::kilograms(100),
+            Mass::kilograms(1000),
+        ));
+
+        let pipes = (5..=35).map(|x| IVec2::new(x, 5)).collect();
+
+        Vehicle::from_parts(
+            "".into(),
+            "".into(),
+            vec![
+                (IVec2::ZERO, Rotation::East, thruster),
+                (IVec2::new(30, 0), Rotation::East, tank),
+            ],
+            pipes,
+        )
+    }
+
+    fn part_database() -> HashMap<String, PartPrototype> {
+        let tank = TankModel::new(
+            "tank".into(),
+            UVec2::new(10, 10),
+            Mass::kilograms(100),
+            Mass::kilograms(1000),
+        );
+        let thruster = ThrusterModel::main_thruster(5000.0, 3500.0);
+        [PartPrototype::Tank(tank), PartPrototype::Thruster(thruster)]
+            .into_iter()
+            .map(|p| (p.sprite_path().to_string(), p))
+            .collect()
+    }
+
+    #[test]
+    fn vehicle_files_keep_part_state() {
+        let parts = part_database();
+        let mut prototypes: Vec<_> = parts.values().cloned().collect();
+        prototypes.sort_by_key(|p| p.sprite_path().to_string());
+
+        let mut vehicle = Vehicle::from_parts(
+            "".into(),
+            "Model".into(),
+            prototypes
+                .into_iter()
+                .enumerate()
+                .map(|(i, p)| (IVec2::new(i as i32 * 20, 0), Rotation::East, p))
+                .collect(),
+            HashSet::from([IVec2::new(5, 5)]),
+        );
+        vehicle.fill_tanks(Item::H2);
+        let tank_id = vehicle.get_part_at(IVec2::new(5, 5), None).unwrap();
+        vehicle.build_part(tank_id);
+        let thruster_id = vehicle
+            .parts()
+            .find(|(_, p)| p.as_thruster().is_some())
+            .map(|(id, _)| *id)
+            .unwrap();
+        assert!(vehicle.set_thruster_enabled(thruster_id, false));
+
+        let storage = VehicleFileStorage::from_vehicle(&vehicle);
+        assert_eq!(storage.version, VEHICLE_FILE_VERSION);
+        let s = serde_yaml::to_string(&storage).unwrap();
+        let storage: VehicleFileStorage = serde_yaml::from_str(&s).unwrap();
+        let loaded = storage.to_vehicle("".into(), &parts).unwrap();
+
+        assert_eq!(loaded.model(), "Model");
+        assert_eq!(loaded.total_mass(), Mass::kilograms(1900));
+        assert_eq!(loaded.pipes().collect::<Vec<_>>(), vec![IVec2::new(5, 5)]);
+        let (_, thruster) = loaded.get_part(thruster_id).unwrap().as_thruster().unwrap();
+        assert!(!thruster.is_enabled());
+        for (id, part) in vehicle.parts() {
+            let other = loaded.get_part(*id).unwrap();
+            assert_eq!(other.origin(), part.origin());
+            assert_eq!(other.builds_performed(), part.builds_performed());
+            assert_eq!(
+                other.as_tank().map(|(_, d)| d.contents()),
+                part.as_tank().map(|(_, d)| d.contents())
+            );
+            assert_eq!(
+                other.as_thruster().map(|(_, d)| d.is_enabled()),
+                part.as_thruster().map(|(_, d)| d.is_enabled())
+            );
+        }
+    }
+
+    #[test]
+    fn unversioned_vehicle_files_still_load() {
+        let s = "name: Old\nparts:\n- partname: tank\n  pos: [0, 0]\n  rot: East\nlines: []\n";
+        let storage: VehicleFileStorage = serde_yaml::from_str(s).unwrap();
+        assert_eq!(storage.version, 1);
+
+        let vehicle = storage.to_vehicle("".into(), &part_database()).unwrap();
+        let (_, part) = vehicle.parts().next().unwrap();
+        assert_eq!(part.builds_performed(), 0);
+        assert_eq!(vehicle.fuel_mass(), Mass::ZERO);
+
+        let future = s.replace("name: Old", "version: 99\nname: Old");
+        let storage: VehicleFileStorage = serde_yaml::from_str(&future).unwrap();
+        assert!(storage.to_vehicle("".into(), &part_database()).is_err());
+    }
+
+    #[test]
+    fn assemblies_keep_relative_positions() {
+        let parts = part_database();
+        let tank = parts["tank"].clone();
+        let mut pipes: HashSet<_> = (25..=35).map(|x| IVec2::new(x, 5)).collect();
+        pipes.insert(IVec2::new(100, 100));
+
+        let vehicle = Vehicle::from_parts(
+            "".into(),
+            "".into(),
+            vec![
+                (IVec2::new(20, 0), Rotation::East, tank.clone()),
+                (IVec2::new(30, 0), Rotation::East, tank),
+            ],
+            pipes,
+        );
+
+        let ids: Vec<_> = vehicle.parts().map(|(id, _)| *id).collect();
+        let assembly = Assembly::from_vehicle("pair".into(), &vehicle, ids);
+        let positions =
+            |a: &Assembly| -> Vec<_> { a.parts().iter().map(|(p, r, _)| (*p, *r)).collect() };
+
+        assert_eq!(
+            positions(&assembly),
+            vec![
+                (IVec2::ZERO, Rotation::East),
+                (IVec2::new(10, 0), Rotation::East)
+            ]
+        );
+        assert_eq!(assembly.pipes().len(), 11);
+        assert_eq!(assembly.pipes()[0], IVec2::new(5, 5));
+        assert_eq!(assembly.dims(), IVec2::new(20, 10));
+
+        let rotated = assembly.rotated();
+        assert_eq!(
+            positions(&rotated),
+            vec![
+                (IVec2::ZERO, Rotation::North),
+                (IVec2::new(0, 10), Rotation::North)
+            ]
+        );
+        assert_eq!(rotated.dims(), IVec2::new(10, 20));
+        assert!(rotated.pipes().contains(&IVec2::new(4, 5)));
+
+        let turned_around = rotated.rotated().rotated().rotated();
+        assert_eq!(positions(&turned_around), positions(&assembly));
+        assert_eq!(turned_around.pipes(), assembly.pipes());
+
+        let storage = AssemblyFileStorage::from_assembly(&assembly);
+        let s = serde_yaml::to_string(&storage).unwrap();
+        let storage: AssemblyFileStorage = serde_yaml::from_str(&s).unwrap();
+        let loaded = storage.to_assembly(&parts).unwrap();
+        assert_eq!(loaded.name(), "pair");
+        assert_eq!(positions(&loaded), positions(&assembly));
+        assert_eq!(loaded.pipes(), assembly.pipes());
+    }
+
+    #[test]
+    fn shipped_assets_pass_lint() {
+        let assets = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets");
+        let (parts, mut issues) = lint_parts_dir(&assets.join("parts"));
+        issues.extend(lint_vehicles_dir(&assets.join("vehicles"), &parts));
+        assert!(!parts.is_empty());
+        assert_eq!(issues, vec![]);
+    }
+
+    #[test]
+    fn shipped_vehicles_have_antennas() {
+        let assets = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../assets");
+        let parts = load_parts_from_dir(&assets.join("parts")).unwrap();
+        for file in std::fs::read_dir(assets.join("vehicles")).unwrap() {
+            let path = file.unwrap().path();
+            let vehicle = load_vehicle(&path, "".into(), &parts).unwrap();
+            assert!(vehicle.comms_range().is_some(), "{:?}", path);
+        }
+    }
+
+    #[test]
+    fn lint_finds_broken_vehicles_and_parts() {
+        let parts = part_database();
+        let tank = VehiclePartFileStorage::layout(IVec2::ZERO, Rotation::East, &parts["tank"]);
+        let storage = VehicleFileStorage {
+            version: VEHICLE_FILE_VERSION,
+            name: "".into(),
+            parts: vec![
+                tank.clone(),
+                VehiclePartFileStorage {
+                    partname: "nope".into(),
+                    ..tank.clone()
+                },
+                VehiclePartFileStorage {
+                    pos: IVec2::new(5, 8),
+                    ..tank.clone()
+                },
+                VehiclePartFileStorage {
+                    pos: IVec2::new(10, 0),
+                    ..tank.clone()
+                },
+            ],
+            lines: HashSet::new(),
+            mission: None,
+        };
+
+        assert_eq!(
+            lint_vehicle(&storage, &parts),
+            vec![
+                AssetIssueKind::MissingPart("nope".into(), 1),
+                AssetIssueKind::OverlappingParts(0, 2, IVec2::new(5, 8)),
+                AssetIssueKind::OverlappingParts(2, 3, IVec2::new(10, 8)),
+            ]
+        );
+
+        let stacked = VehicleFileStorage {
+            parts: vec![tank.clone(); 3],
+            ..storage
+        };
+        assert_eq!(
+            lint_vehicle(&stacked, &parts),
+            vec![
+                AssetIssueKind::OverlappingParts(0, 1, IVec2::ZERO),
+                AssetIssueKind::OverlappingParts(0, 2, IVec2::ZERO),
+                AssetIssueKind::OverlappingParts(1, 2, IVec2::ZERO),
+            ]
+        );
+
+        let slow = PartPrototype::Thruster(ThrusterModel::main_thruster(5000.0, 0.0));
+        assert!(unphysical_stats(&slow).is_some());
+        let absurd = PartPrototype::Thruster(ThrusterModel::main_thruster(1e9, 3500.0));
+        assert!(unphysical_stats(&absurd).is_some());
+        assert!(unphysical_stats(&parts["tank"]).is_none());
+    }
 }